@@ -0,0 +1,168 @@
+//! Logs in with the auth service before connecting, the token it gives back is the only way into the game server
+//! Auth service runs next to the game server, so we reach it through the same ip on its own port
+//! It only listens on loopback as passwords go unencrypted, so from other machines it needs a tunnel
+use crate::client::config_file::{load_config, save_config};
+use crate::shared::shared_auth::*;
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, IoTaskPool, Task};
+use bincode::Options;
+use lightyear::connection::netcode::ConnectToken;
+use lightyear::prelude::client::{Authentication, ClientCommands, ClientConfig, NetConfig};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream};
+use std::time::Duration;
+
+/// Where we remember who logged in last, password is never saved
+const ACCOUNT_PATH: &str = "./psycho_project/config/account.ron";
+
+/// How long we wait on the auth service before giving up
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct AuthPlugin;
+
+impl Plugin for AuthPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RememberedAccount::load());
+        app.add_event::<AuthOutcome>();
+        app.add_systems(Startup, find_auth_address);
+        app.add_systems(
+            Update,
+            poll_auth_request.run_if(resource_exists::<PendingAuth>),
+        );
+    }
+}
+
+/// Where the auth service lives
+#[derive(Resource)]
+pub struct AuthAddress(pub SocketAddr);
+
+/// Account we are logged in as
+#[derive(Resource, Clone, Debug)]
+pub struct MyAccount {
+    pub account_id: u64,
+    pub display_name: String,
+}
+
+/// Last account that logged in on this machine, fills login screen for us
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct RememberedAccount {
+    pub username: String,
+}
+
+impl RememberedAccount {
+    fn load() -> Self {
+        load_config(ACCOUNT_PATH, "remembered account")
+    }
+
+    pub fn save(&self) {
+        save_config(ACCOUNT_PATH, "remembered account", self);
+    }
+}
+
+/// Request still waiting on auth service, only one at a time
+#[derive(Resource)]
+pub struct PendingAuth(Task<Result<AuthResponse, String>>);
+
+/// What came out of a login or register, login screen shows it
+#[derive(Event, Clone, Debug)]
+pub enum AuthOutcome {
+    LoggedIn(MyAccount),
+    Failed(String),
+}
+
+/// Grabs game server ip from our client config, the port is the one of auth service
+fn find_auth_address(client_config: Res<ClientConfig>, mut commands: Commands) {
+    let server_ip = match &client_config.net {
+        NetConfig::Netcode {
+            auth: Authentication::Manual { server_addr, .. },
+            ..
+        } => server_addr.ip(),
+        _ => {
+            warn!(
+                "Client isnt using netcode with a server address, looking for auth service locally"
+            );
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        }
+    };
+    if !server_ip.is_loopback() {
+        warn!(
+            "Auth service only listens on loopback, {} needs a tunnel to it",
+            server_ip
+        );
+    }
+    let address = SocketAddr::new(server_ip, AUTH_PORT);
+    info!("Auth service expected at {}", address);
+    commands.insert_resource(AuthAddress(address));
+}
+
+/// Sends request to auth service without blocking the frame
+pub fn send_auth_request(address: SocketAddr, request: AuthRequest, commands: &mut Commands) {
+    let task = IoTaskPool::get()
+        .spawn(async move { request_auth(address, request).map_err(|err| err.to_string()) });
+    commands.insert_resource(PendingAuth(task));
+}
+
+fn request_auth(address: SocketAddr, request: AuthRequest) -> anyhow::Result<AuthResponse> {
+    let mut stream = TcpStream::connect_timeout(&address, AUTH_TIMEOUT)?;
+    stream.set_read_timeout(Some(AUTH_TIMEOUT))?;
+    stream.set_write_timeout(Some(AUTH_TIMEOUT))?;
+    auth_bincode().serialize_into(&mut stream, &request)?;
+    Ok(auth_bincode().deserialize_from(&mut stream)?)
+}
+
+/// Once auth service answers we either connect with its token or tell player what went wrong
+fn poll_auth_request(
+    mut pending: ResMut<PendingAuth>,
+    mut client_config: ResMut<ClientConfig>,
+    mut outcome: EventWriter<AuthOutcome>,
+    mut commands: Commands,
+) {
+    let Some(result) = block_on(future::poll_once(&mut pending.0)) else {
+        return;
+    };
+    commands.remove_resource::<PendingAuth>();
+
+    match result {
+        Ok(AuthResponse::Accepted {
+            account_id,
+            display_name,
+            connect_token,
+        }) => {
+            let token = match ConnectToken::try_from_bytes(&connect_token) {
+                Ok(token) => token,
+                Err(err) => {
+                    error!("Auth service sent a broken token: {:?}", err);
+                    outcome.send(AuthOutcome::Failed(
+                        "Server sent a broken token".to_string(),
+                    ));
+                    return;
+                }
+            };
+            info!("Logged in as {} with account {}", display_name, account_id);
+            // Local clients of a host server dont need a token
+            if let NetConfig::Netcode { auth, .. } = &mut client_config.net {
+                *auth = Authentication::Token(token);
+            }
+            info!("Gonna connect to server");
+            commands.connect_client();
+
+            let account = MyAccount {
+                account_id,
+                display_name,
+            };
+            commands.insert_resource(account.clone());
+            outcome.send(AuthOutcome::LoggedIn(account));
+        }
+        Ok(AuthResponse::Rejected(reason)) => {
+            warn!("Auth service rejected us: {}", reason);
+            outcome.send(AuthOutcome::Failed(reason));
+        }
+        Err(err) => {
+            error!("Couldnt reach auth service: {}", err);
+            outcome.send(AuthOutcome::Failed(
+                "Couldnt reach the auth service".to_string(),
+            ));
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Small ron files the client keeps in its config folder, like settings and keybinds
+use bevy::asset::ron;
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Reads a config file, missing or broken file falls back to defaults. What is only used in logs
+pub fn load_config<T: DeserializeOwned + Default>(path: &str, what: &str) -> T {
+    match fs::read_to_string(path) {
+        Ok(config_str) => match ron::de::from_str::<T>(&config_str) {
+            Ok(config) => {
+                info!("Loaded {} from {}", what, path);
+                config
+            }
+            Err(err) => {
+                error!("Couldnt parse {} {}: {}", what, path, err);
+                T::default()
+            }
+        },
+        Err(_) => {
+            info!("No saved {} using defaults", what);
+            T::default()
+        }
+    }
+}
+
+/// Overwrites a config file, config folder is made if it doesnt exist yet
+pub fn save_config<T: Serialize>(path: &str, what: &str, config: &T) {
+    if let Some(folder) = Path::new(path).parent() {
+        if let Err(err) = fs::create_dir_all(folder) {
+            error!("Couldnt create config folder {:?}: {}", folder, err);
+            return;
+        }
+    }
+    let config_str = match ron::ser::to_string_pretty(config, ron::ser::PrettyConfig::default()) {
+        Ok(config_str) => config_str,
+        Err(err) => {
+            error!("Couldnt serialize {}: {}", what, err);
+            return;
+        }
+    };
+    match fs::write(path, config_str) {
+        Ok(_) => info!("Saved {} in {}", what, path),
+        Err(err) => error!("Couldnt write {} {}: {}", what, path, err),
+    }
+}
@@ -0,0 +1,221 @@
+//! Keybinds the player chose, saved in a local config file and loaded on startup instead of the defaults
+use crate::client::config_file::{load_config, save_config};
+use crate::client::player::camera::{CameraMovement, MarkerMainCamera};
+use crate::shared::protocol::player_structs::{PlayerAction, DEFAULT_STICK_DEADZONE};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use leafwing_input_manager::prelude::*;
+use lightyear::prelude::client::Predicted;
+use lightyear::shared::replication::components::Controlled;
+use serde::{Deserialize, Serialize};
+
+/// Where our keybinds are saved
+const CONTROLS_PATH: &str = "./psycho_project/config/controls.ron";
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Controls::load());
+        app.add_systems(Update, apply_controls);
+    }
+}
+
+/// Every action that can be rebound, axis ones stay on the mouse and the rest are filled by code
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ControlAction {
+    Player(PlayerAction),
+    Camera(CameraMovement),
+}
+
+impl ControlAction {
+    /// In the order controls screen shows them
+    pub const ALL: [Self; 9] = [
+        Self::Player(PlayerAction::Forward),
+        Self::Player(PlayerAction::Backward),
+        Self::Player(PlayerAction::Left),
+        Self::Player(PlayerAction::Right),
+        Self::Player(PlayerAction::Jump),
+        Self::Player(PlayerAction::Shoot),
+        Self::Player(PlayerAction::SwitchWeapon),
+        Self::Camera(CameraMovement::SwitchMode),
+        Self::Camera(CameraMovement::SwapShoulder),
+    ];
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::Player(action) => format!("{:?}", action),
+            Self::Camera(action) => format!("{:?}", action),
+        }
+    }
+
+    pub fn default_binding(&self) -> Option<Binding> {
+        let binding = match self {
+            Self::Player(PlayerAction::Forward) => Binding::Key(KeyCode::KeyW),
+            Self::Player(PlayerAction::Backward) => Binding::Key(KeyCode::KeyS),
+            Self::Player(PlayerAction::Left) => Binding::Key(KeyCode::KeyA),
+            Self::Player(PlayerAction::Right) => Binding::Key(KeyCode::KeyD),
+            Self::Player(PlayerAction::Jump) => Binding::Key(KeyCode::Space),
+            Self::Player(PlayerAction::Shoot) => Binding::Mouse(MouseButton::Left),
+            Self::Player(PlayerAction::SwitchWeapon) => Binding::Key(KeyCode::KeyQ),
+            Self::Camera(CameraMovement::SwitchMode) => Binding::Key(KeyCode::KeyV),
+            Self::Camera(CameraMovement::SwapShoulder) => Binding::Key(KeyCode::KeyC),
+            // Not in ALL, code fills them
+            Self::Player(_) | Self::Camera(_) => return None,
+        };
+        Some(binding)
+    }
+}
+
+/// A single key or mouse button
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Key(key) => {
+                let name = format!("{:?}", key);
+                name.strip_prefix("Key").unwrap_or(&name).to_string()
+            }
+            Self::Mouse(button) => format!("Mouse {:?}", button),
+        }
+    }
+}
+
+/// How sticks feel, gamepad buttons themselves are not rebindable
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct GamepadSettings {
+    pub deadzone: f32,
+    // Multiplies camera stick
+    pub look_sensitivity: f32,
+    pub invert_y: bool,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self {
+            deadzone: DEFAULT_STICK_DEADZONE,
+            look_sensitivity: 1.0,
+            invert_y: false,
+        }
+    }
+}
+
+/// Current keybinds of this client
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Controls {
+    pub bindings: HashMap<ControlAction, Binding>,
+    #[serde(default)]
+    pub gamepad: GamepadSettings,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self {
+            bindings: ControlAction::ALL
+                .iter()
+                .filter_map(|action| Some((*action, action.default_binding()?)))
+                .collect(),
+            gamepad: GamepadSettings::default(),
+        }
+    }
+}
+
+impl Controls {
+    pub fn binding(&self, action: ControlAction) -> Option<Binding> {
+        self.bindings
+            .get(&action)
+            .copied()
+            .or_else(|| action.default_binding())
+    }
+
+    /// Helper gives me every action that shares it is binding with another one
+    pub fn conflicts(&self) -> HashSet<ControlAction> {
+        let mut users: HashMap<Binding, Vec<ControlAction>> = HashMap::default();
+        for action in ControlAction::ALL {
+            if let Some(binding) = self.binding(action) {
+                users.entry(binding).or_default().push(action);
+            }
+        }
+        users
+            .into_values()
+            .filter(|actions| actions.len() > 1)
+            .flatten()
+            .collect()
+    }
+
+    /// Reads saved keybinds, anything missing or broken falls back to defaults
+    pub fn load() -> Self {
+        let mut controls: Controls = load_config(CONTROLS_PATH, "keybinds");
+        // Actions added after the file was saved get their default
+        for action in ControlAction::ALL {
+            if let Some(binding) = action.default_binding() {
+                controls.bindings.entry(action).or_insert(binding);
+            }
+        }
+        controls
+    }
+
+    pub fn save(&self) {
+        save_config(CONTROLS_PATH, "keybinds", self);
+    }
+
+    pub fn player_input_map(&self) -> InputMap<PlayerAction> {
+        let mut input_map = PlayerAction::gamepad_input_map(self.gamepad.deadzone);
+        for action in ControlAction::ALL {
+            let (ControlAction::Player(player_action), Some(binding)) =
+                (action, self.binding(action))
+            else {
+                continue;
+            };
+            match binding {
+                Binding::Key(key) => input_map.insert(player_action, key),
+                Binding::Mouse(button) => input_map.insert(player_action, button),
+            };
+        }
+        input_map
+    }
+
+    pub fn camera_input_map(&self) -> InputMap<CameraMovement> {
+        let mut input_map = CameraMovement::axis_input_map();
+        input_map.merge(&CameraMovement::gamepad_input_map(
+            self.gamepad.deadzone,
+            self.gamepad.look_sensitivity,
+            self.gamepad.invert_y,
+        ));
+        for action in ControlAction::ALL {
+            let (ControlAction::Camera(camera_action), Some(binding)) =
+                (action, self.binding(action))
+            else {
+                continue;
+            };
+            match binding {
+                Binding::Key(key) => input_map.insert(camera_action, key),
+                Binding::Mouse(button) => input_map.insert(camera_action, button),
+            };
+        }
+        input_map
+    }
+}
+
+/// Swaps input maps for the saved ones, whenever keybinds change or a new map shows up
+fn apply_controls(
+    controls: Res<Controls>,
+    mut player_maps: Query<&mut InputMap<PlayerAction>, (With<Predicted>, With<Controlled>)>,
+    mut camera_maps: Query<&mut InputMap<CameraMovement>, With<MarkerMainCamera>>,
+) {
+    for mut input_map in player_maps.iter_mut() {
+        if controls.is_changed() || input_map.is_added() {
+            *input_map = controls.player_input_map();
+        }
+    }
+    for mut input_map in camera_maps.iter_mut() {
+        if controls.is_changed() || input_map.is_added() {
+            *input_map = controls.camera_input_map();
+        }
+    }
+}
@@ -1,14 +1,13 @@
 //! Essential systems utilized to connect client and so on
+use crate::shared::protocol::lobby_structs::{Lobbies, LobbyPositionMap};
 use bevy::prelude::*;
 use lightyear::client::events::ConnectEvent;
-use lightyear::prelude::client::ClientCommands;
 use lightyear::prelude::*;
 pub struct SystemsPlugin;
 
 impl Plugin for SystemsPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<EasyClient>();
-        app.add_systems(Startup, connect_client);
         app.add_systems(Update, form_client_id);
     }
 }
@@ -18,10 +17,17 @@ impl Plugin for SystemsPlugin {
 #[reflect(Resource)]
 pub struct EasyClient(pub ClientId);
 
-// First thing we will do is connect the client to server as our server is really important for grabing specific info
-pub fn connect_client(mut commands: Commands) {
-    info!("Gonna connect to server");
-    commands.connect_client();
+/// Helper gives me the lobby I am in, before joining any lobby I just see the major one
+pub fn my_lobby_id(
+    lobbies: &Lobbies,
+    lobby_position_map: &LobbyPositionMap,
+    easy_client: &Option<Res<EasyClient>>,
+) -> Option<u64> {
+    easy_client
+        .as_ref()
+        .and_then(|client| lobby_position_map.0.get(&client.0))
+        .map(|client_info| client_info.lobby_id)
+        .or_else(|| lobbies.lobbies.first().map(|lobby| lobby.lobby_id))
 }
 
 // When we have a connect event grab the client id
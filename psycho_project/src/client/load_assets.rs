@@ -1,11 +1,12 @@
 //! RESPONSIBILITIES - LOAD ALL ASSETS WHEN GAME STARTS
-//! Once loaded we will continue to state UI
+//! Once loaded we will continue to login screen
 
 use bevy::{prelude::*, utils::HashMap};
 use bevy_asset_loader::prelude::*;
 
 pub struct LoadingAssetsPlugin;
 use crate::client::MyAppState;
+use crate::shared::shared_parts::PartCatalog;
 
 impl Plugin for LoadingAssetsPlugin {
     fn build(&self, app: &mut App) {
@@ -13,7 +14,7 @@ impl Plugin for LoadingAssetsPlugin {
         app.register_type::<Images>();
         app.add_loading_state(
             LoadingState::new(MyAppState::LoadingAssets)
-                .continue_to_state(MyAppState::MainMenu)
+                .continue_to_state(MyAppState::Login)
                 .load_collection::<CharCollection>()
                 .load_collection::<Images>(),
         );
@@ -21,32 +22,39 @@ impl Plugin for LoadingAssetsPlugin {
 }
 
 // Resource for easily acessing client based assets, which are mostly things like character world and so on. Each field in the connect is gonna be associate with something.
-#[derive(AssetCollection, Resource, Reflect)]
+// Which gltf files get loaded comes from the part catalog, according to their file path
+#[derive(Resource, Reflect)]
 #[reflect(Resource)]
 pub struct CharCollection {
-    #[asset(
-        paths(
-            // Weapons
-            "weapons/katana.glb",
-            // Full characters
-            "characters/character_mesh.glb",
-            // Heads
-            "characters/parts/suit_head.glb",
-            "characters/parts/soldier_head.glb",
-            // Torsos
-            "characters/parts/scifi_torso.glb",
-            "characters/parts/soldier_torso.glb",
-            // Legs
-            "characters/parts/witch_legs.glb",
-            "characters/parts/soldier_legs.glb",
-            //Skeletons
-            "characters/parts/main_skeleton.glb"
-        ),
-        collection(typed, mapped)
-    )]
     pub gltf_files: HashMap<String, Handle<Gltf>>,
 }
 
+// Done by hand as the derive only accepts paths known at compile time
+impl AssetCollection for CharCollection {
+    fn create(world: &mut World) -> Self {
+        let mesh_paths = world.resource::<PartCatalog>().mesh_paths();
+        let asset_server = world.resource::<AssetServer>();
+        let gltf_files = mesh_paths
+            .into_iter()
+            .map(|path| {
+                // Asset server hands out the same handle we started loading in load
+                let handle = asset_server.load::<Gltf>(path.clone());
+                (path, handle)
+            })
+            .collect();
+        Self { gltf_files }
+    }
+
+    fn load(world: &mut World) -> Vec<UntypedHandle> {
+        let mesh_paths = world.resource::<PartCatalog>().mesh_paths();
+        let asset_server = world.resource::<AssetServer>();
+        mesh_paths
+            .into_iter()
+            .map(|path| asset_server.load::<Gltf>(path).untyped())
+            .collect()
+    }
+}
+
 #[derive(AssetCollection, Resource, Reflect)]
 #[reflect(Resource)]
 pub struct Images {
@@ -64,6 +64,11 @@ fn set_pause_screen(
         next_state.set(match current_state.get() {
             MyAppState::Game => MyAppState::Pause,
             MyAppState::Pause => MyAppState::Game,
+            // Controls screen uses escape to cancel rebinding
+            MyAppState::Controls => return,
+            MyAppState::Settings => MyAppState::MainMenu,
+            // Not connected yet there is nowhere to go
+            MyAppState::Login => return,
             _ => MyAppState::Game,
         });
     }
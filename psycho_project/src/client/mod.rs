@@ -1,26 +1,34 @@
 use crate::client::ui::UiPlugin;
-use crate::shared::protocol::lobby_structs::Lobbies;
-use crate::shared::protocol::player_structs::SavePlayerBundleMap;
+use crate::shared::protocol::lobby_structs::{Lobbies, LobbyPositionMap};
+use crate::shared::protocol::player_structs::{SavePlayerBundleMap, Scoreboard};
 use bevy::prelude::*;
 use bevy_panorbit_camera::PanOrbitCameraPlugin;
 
-mod change_res;
+mod auth;
+mod config_file;
+pub mod controls;
 mod essentials;
 mod load_assets;
 mod manage_game;
 pub mod player;
 pub mod rtt;
+pub mod settings;
+mod spectator;
 mod ui;
 mod voxel_gen;
 mod world;
 
 // SElLF MADE IMPORTS
-use self::change_res::ChangeResPlugin;
+use self::auth::AuthPlugin;
+use self::controls::ControlsPlugin;
 use self::essentials::SystemsPlugin;
 use self::load_assets::LoadingAssetsPlugin;
 use self::manage_game::InGamePlugin;
 use self::player::CreateCharPlugin;
 use self::rtt::FormRttsPlugin;
+use self::settings::SettingsPlugin;
+use self::spectator::SpectatorPlugin;
+use self::voxel_gen::VoxelGenPlugin;
 use self::world::PhysicalWorldPlugin;
 
 /// Important plugin centralizes most of our client related logic
@@ -30,7 +38,9 @@ impl Plugin for ExampleClientPlugin {
     fn build(&self, app: &mut App) {
         // Inserting resources that must exist first
         app.insert_resource(Lobbies::default());
+        app.insert_resource(LobbyPositionMap::default());
         app.insert_resource(SavePlayerBundleMap::default());
+        app.insert_resource(Scoreboard::default());
         // Initializing states that must exist
         app.init_state::<MyAppState>();
 
@@ -39,14 +49,17 @@ impl Plugin for ExampleClientPlugin {
 
         // Self made plugins
         app.add_plugins(SystemsPlugin);
-        app.add_plugins(ChangeResPlugin);
+        app.add_plugins(AuthPlugin);
+        app.add_plugins(SettingsPlugin);
+        app.add_plugins(ControlsPlugin);
         app.add_plugins(LoadingAssetsPlugin);
         app.add_plugins(UiPlugin);
         app.add_plugins(CreateCharPlugin);
         app.add_plugins(FormRttsPlugin);
         app.add_plugins(InGamePlugin);
-        // app.add_plugins(VoxelGenPlugin);
+        app.add_plugins(VoxelGenPlugin);
         app.add_plugins(PhysicalWorldPlugin);
+        app.add_plugins(SpectatorPlugin);
         // Connection systems - Systems that dialogues with server
     }
 }
@@ -56,12 +69,18 @@ pub enum MyAppState {
     #[default]
     // Started loading assets
     LoadingAssets,
+    // Logging in before we connect
+    Login,
     // In main menu for setting player options and such
     MainMenu,
     // Lobby screen
     Pause,
     // Inventory sub-screens
     Inventory,
+    // Rebinding keys
+    Controls,
+    // Window, camera and audio settings
+    Settings,
     // Ingame
     Game,
 }
@@ -1,10 +1,16 @@
 //! Player related animations are here
 use crate::client::load_assets::CharCollection;
+use crate::client::player::char_customizer::SkeletonScene;
 use crate::shared::protocol::player_structs::*;
+use crate::shared::shared_parts::{PartCatalog, PartSlot};
+use avian3d::prelude::*;
+use bevy::animation::{AnimationTarget, AnimationTargetId};
 use bevy::prelude::*;
-use bevy::utils::{Duration, HashMap};
+use bevy::utils::{Duration, HashMap, HashSet};
+use common::shared::FIXED_TIMESTEP_HZ;
 use leafwing_input_manager::prelude::ActionState;
-use lightyear::prelude::client::Predicted;
+use lightyear::prelude::client::{ConnectionManager, Interpolated, Predicted};
+use lightyear::shared::tick_manager::{Tick, TickManager};
 
 use crate::client::MyAppState;
 
@@ -15,6 +21,7 @@ impl Plugin for AnimPlayerPlugin {
         // Debuggin
         app.register_type::<Animations>();
         app.register_type::<PointerAnimatedEntities>();
+        app.register_type::<AnimationStateMachine>();
 
         // Systems
         app.add_systems(Startup, create_animations_resource);
@@ -27,8 +34,19 @@ impl Plugin for AnimPlayerPlugin {
             Update,
             (create_anim_transitions, add_animation_graph).chain(),
         );
+        // Masks come from the bones of spawned skeletons so we wait for one to exist
+        app.add_systems(
+            Update,
+            build_masked_animations.run_if(in_state(MyAppState::Game)),
+        );
+
         //IMPORTANT ONLY PLAY ANIMATION AFTER ADDING ANIMATION GRAPH
-        app.add_systems(Update, state_machine.after(add_animation_graph));
+        app.add_systems(
+            Update,
+            state_machine
+                .after(add_animation_graph)
+                .after(build_masked_animations),
+        );
     }
 }
 
@@ -36,12 +54,198 @@ impl Plugin for AnimPlayerPlugin {
 #[derive(Resource, Reflect)]
 #[reflect(Resource)]
 pub struct Animations {
-    // A node  that tells me exactly the name of an specific animation
-    pub named_nodes: HashMap<String, AnimationNodeIndex>,
+    // According to skeleton file path, a node that tells me exactly the name of an specific animation
+    // Clips only animate bones of the skeleton they came from, so the same name can exist in many skeletons
+    pub named_nodes: HashMap<String, HashMap<String, AnimationNodeIndex>>,
+    // According to skeleton file path, how long each named animation lasts in seconds
+    pub clip_lengths: HashMap<String, HashMap<String, f32>>,
+    // Skeletons that already had their upper body masked clips made
+    pub masked_skeletons: HashSet<String>,
     // It is graph handle
     pub animation_graph: Handle<AnimationGraph>,
 }
 
+impl Animations {
+    /// Gives me the node of a named animation made for the given skeleton
+    pub fn node(&self, skeleton: &str, name: &str) -> Option<AnimationNodeIndex> {
+        self.named_nodes.get(skeleton)?.get(name).copied()
+    }
+
+    pub fn clip_length(&self, skeleton: &str, name: &str) -> Option<Duration> {
+        let length = self.clip_lengths.get(skeleton)?.get(name)?;
+        Some(Duration::from_secs_f32(*length))
+    }
+
+    /// Name of the clip where legs play lower and upper body plays upper
+    pub fn masked_name(lower: &str, upper: &str) -> String {
+        format!("{}+{}", lower, upper)
+    }
+}
+
+/// If we dont know how long an action is we hold it for this long
+const DEFAULT_ACTION_LENGTH: Duration = Duration::from_millis(500);
+/// Arms should react fast so actions blend in quicker than locomotion
+const ACTION_TRANSITION: Duration = Duration::from_millis(100);
+
+/// Below this horizontal speed player is considered standing still
+const WALK_SPEED_THRESHOLD: f32 = 0.5;
+/// Vertical speed that tells us player left the ground by jumping and not by falling off something
+const JUMP_SPEED_THRESHOLD: f32 = 1.0;
+/// Ray still hits the ground for a few frames after jumping, so jump holds a little before air takes over
+const JUMP_HOLD: Duration = Duration::from_millis(300);
+/// How long landing holds before we go back to walking or idling
+const LANDING_HOLD: Duration = Duration::from_millis(200);
+/// Players without ground rays are considered airborne above this vertical speed
+const AIRBORNE_VERTICAL_SPEED: f32 = 0.5;
+
+/// Every locomotion state a player can be in, derived only from things every client knows about every player
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Reflect)]
+pub enum LocomotionState {
+    #[default]
+    Idle,
+    FrontWalk,
+    BackWalk,
+    LeftWalk,
+    RightWalk,
+    Jump,
+    FrontAir,
+    BackAir,
+    LeftAir,
+    RightAir,
+    Landing,
+}
+
+/// How a state gets played
+pub struct StateProperties {
+    // Name of animation clip in skeleton
+    pub name: &'static str,
+    // How long it takes to blend from previous state
+    pub transition: Duration,
+    pub repeat: bool,
+    pub speed: f32,
+}
+
+impl StateProperties {
+    fn new(name: &'static str, transition_millis: u64, repeat: bool, speed: f32) -> Self {
+        Self {
+            name,
+            transition: Duration::from_millis(transition_millis),
+            repeat,
+            speed,
+        }
+    }
+}
+
+impl LocomotionState {
+    pub const ALL: [LocomotionState; 11] = [
+        LocomotionState::Idle,
+        LocomotionState::FrontWalk,
+        LocomotionState::BackWalk,
+        LocomotionState::LeftWalk,
+        LocomotionState::RightWalk,
+        LocomotionState::Jump,
+        LocomotionState::FrontAir,
+        LocomotionState::BackAir,
+        LocomotionState::LeftAir,
+        LocomotionState::RightAir,
+        LocomotionState::Landing,
+    ];
+
+    /// Durations came from our old player mechanics, our skeleton has no air clips so we reuse run ones slowed down
+    /// States sharing a clip should also share repeat, as we dont restart a clip that is already playing
+    pub fn properties(&self) -> StateProperties {
+        match self {
+            LocomotionState::Idle => StateProperties::new("Idle", 400, true, 1.0),
+            LocomotionState::FrontWalk => StateProperties::new("Run", 400, true, 1.0),
+            LocomotionState::BackWalk => StateProperties::new("Run_Back", 400, true, 1.0),
+            LocomotionState::LeftWalk => StateProperties::new("Run_Left", 400, true, 1.0),
+            LocomotionState::RightWalk => StateProperties::new("Run_Right", 400, true, 1.0),
+            LocomotionState::Jump => StateProperties::new("Idle_Neutral", 0, false, 1.0),
+            LocomotionState::FrontAir => StateProperties::new("Run", 400, true, 0.4),
+            LocomotionState::BackAir => StateProperties::new("Run_Back", 400, true, 0.4),
+            LocomotionState::LeftAir => StateProperties::new("Run_Left", 500, true, 0.4),
+            LocomotionState::RightAir => StateProperties::new("Run_Right", 500, true, 0.4),
+            LocomotionState::Landing => StateProperties::new("Idle", 0, true, 1.0),
+        }
+    }
+
+    pub fn is_airborne(&self) -> bool {
+        matches!(
+            self,
+            LocomotionState::Jump
+                | LocomotionState::FrontAir
+                | LocomotionState::BackAir
+                | LocomotionState::LeftAir
+                | LocomotionState::RightAir
+        )
+    }
+
+    /// Decides next state, velocity must be relative to where the player is facing
+    pub fn next(
+        self,
+        elapsed: Duration,
+        local_velocity: Vec3,
+        grounded: bool,
+        jump_pressed: bool,
+    ) -> Self {
+        // Forward is negative z
+        let horizontal = Vec2::new(local_velocity.x, local_velocity.z);
+        let (walk, air) = if horizontal.length() < WALK_SPEED_THRESHOLD {
+            (LocomotionState::Idle, LocomotionState::FrontAir)
+        } else if horizontal.y.abs() >= horizontal.x.abs() {
+            if horizontal.y < 0.0 {
+                (LocomotionState::FrontWalk, LocomotionState::FrontAir)
+            } else {
+                (LocomotionState::BackWalk, LocomotionState::BackAir)
+            }
+        } else if horizontal.x > 0.0 {
+            (LocomotionState::RightWalk, LocomotionState::RightAir)
+        } else {
+            (LocomotionState::LeftWalk, LocomotionState::LeftAir)
+        };
+
+        if self == LocomotionState::Jump && elapsed < JUMP_HOLD {
+            return LocomotionState::Jump;
+        }
+
+        if grounded {
+            if jump_pressed {
+                return LocomotionState::Jump;
+            }
+            if self.is_airborne() {
+                return LocomotionState::Landing;
+            }
+            if self == LocomotionState::Landing && elapsed < LANDING_HOLD {
+                return LocomotionState::Landing;
+            }
+            walk
+        } else {
+            if !self.is_airborne() && local_velocity.y > JUMP_SPEED_THRESHOLD {
+                return LocomotionState::Jump;
+            }
+            air
+        }
+    }
+}
+
+/// Current locomotion state of a player and for how long he has been in it
+#[derive(Component, Default, Reflect)]
+pub struct AnimationStateMachine {
+    pub state: LocomotionState,
+    pub elapsed: Duration,
+    // Plays on top of locomotion in the upper body
+    pub action: Option<UpperBodyAction>,
+}
+
+/// Comes from replicated player animation, elapsed is worked out from ticks so every client agrees on it
+#[derive(Clone, Reflect)]
+pub struct UpperBodyAction {
+    pub clip: String,
+    pub start_tick: Tick,
+    pub elapsed: Duration,
+    pub speed: f32,
+}
+
 /// A usefull component inserted in predicted player entities that tell me exactly who are the children with animation players
 /// Utilized mostly for optimizations
 #[derive(Component, Default, Reflect)]
@@ -60,15 +264,22 @@ fn get_top_parent(mut curr_entity: Entity, entities_with_parent: &Query<&Parent>
     curr_entity
 }
 
-/// Essential components to have when player gets predicted
+/// Essential components to have when player gets predicted or interpolated
 fn add_anim_components_to_player(
-    query: Query<Entity, (Added<Predicted>, With<MarkerPlayer>)>,
+    query: Query<
+        Entity,
+        (
+            Or<(Added<Predicted>, Added<Interpolated>)>,
+            With<MarkerPlayer>,
+        ),
+    >,
     mut commands: Commands,
 ) {
     for predicted in query.iter() {
         commands
             .entity(predicted)
-            .insert(PointerAnimatedEntities::default());
+            .insert(PointerAnimatedEntities::default())
+            .insert(AnimationStateMachine::default());
     }
 }
 
@@ -77,13 +288,16 @@ fn create_animations_resource(
     mut assets_animation_graph: ResMut<Assets<AnimationGraph>>,
     mut commands: Commands,
 ) {
-    let animation_named_nodes: HashMap<String, AnimationNodeIndex> = HashMap::default();
+    let animation_named_nodes: HashMap<String, HashMap<String, AnimationNodeIndex>> =
+        HashMap::default();
     let animation_graph = AnimationGraph::default();
 
     let hand_graph = assets_animation_graph.add(animation_graph.clone());
 
     commands.insert_resource(Animations {
         named_nodes: animation_named_nodes,
+        clip_lengths: HashMap::default(),
+        masked_skeletons: HashSet::default(),
         animation_graph: hand_graph,
     });
 }
@@ -141,55 +355,322 @@ fn add_animation_graph(
     }
 }
 
-/// Grabbing animations from gltf and inserting into graph - TODO EXPAND THIS TO GRAB ALL SKELETONS
+/// Grabbing animations from every skeleton gltf in catalog and inserting into graph
 fn insert_gltf_animations(
     char_collection: Res<CharCollection>,
+    catalog: Res<PartCatalog>,
     assets_gltf: Res<Assets<Gltf>>,
+    assets_clips: Res<Assets<AnimationClip>>,
     mut animations: ResMut<Animations>,
     mut assets_animation_graph: ResMut<Assets<AnimationGraph>>,
 ) {
+    // We come back to main menu after every game, no need to add the same clips again
+    if !animations.named_nodes.is_empty() {
+        return;
+    }
+
     info_once!("Gettting handle for animation graph");
     let animation_graph = assets_animation_graph
         .get_mut(&animations.animation_graph)
         .expect("To have created animation graph");
 
-    let skeleton_gltf = char_collection
-        .gltf_files
-        .get("characters/parts/main_skeleton.glb")
-        .expect("To find skeleton in client collection");
+    for skeleton in catalog.of_slot(PartSlot::Skeleton) {
+        let Some(gltf) = char_collection
+            .gltf_files
+            .get(&skeleton.mesh)
+            .and_then(|skeleton_gltf| assets_gltf.get(skeleton_gltf))
+        else {
+            error!(
+                "Couldnt find skeleton {} in client collection, it will have no animations",
+                skeleton.mesh
+            );
+            continue;
+        };
+
+        let mut skeleton_nodes = HashMap::default();
+        let mut skeleton_lengths = HashMap::default();
+        for (name_animation, animation_clip) in gltf.named_animations.iter() {
+            let node = animation_graph.add_clip(animation_clip.clone(), 1.0, animation_graph.root);
+            skeleton_nodes.insert(name_animation.to_string(), node);
+            if let Some(loaded_clip) = assets_clips.get(animation_clip) {
+                skeleton_lengths.insert(name_animation.to_string(), loaded_clip.duration());
+            }
+        }
+        info!(
+            "Skeleton {} has {} animations",
+            skeleton.mesh,
+            skeleton_nodes.len()
+        );
+        animations
+            .named_nodes
+            .insert(skeleton.mesh.clone(), skeleton_nodes);
+        animations
+            .clip_lengths
+            .insert(skeleton.mesh.clone(), skeleton_lengths);
+    }
+}
+
+/// Combines every locomotion clip with every upper body action, upper body bones follow the action and the rest follows locomotion
+/// Which bones are upper body comes from the skeleton entry in part catalog
+fn build_masked_animations(
+    skeletons: Query<(Entity, &SkeletonScene)>,
+    children_entities: Query<&Children>,
+    names: Query<&Name>,
+    animation_targets: Query<&AnimationTarget>,
+    catalog: Res<PartCatalog>,
+    char_collection: Option<Res<CharCollection>>,
+    assets_gltf: Res<Assets<Gltf>>,
+    mut assets_clips: ResMut<Assets<AnimationClip>>,
+    mut animations: ResMut<Animations>,
+    mut assets_animation_graph: ResMut<Assets<AnimationGraph>>,
+) {
+    let Some(char_collection) = char_collection else {
+        return;
+    };
+
+    for (skeleton_entity, skeleton_scene) in skeletons.iter() {
+        let skeleton = &skeleton_scene.0;
+        if animations.masked_skeletons.contains(skeleton) {
+            continue;
+        }
+        let Some(upper_body_bone) = catalog
+            .find(skeleton)
+            .and_then(|part| part.upper_body_bone.clone())
+        else {
+            info!(
+                "Skeleton {} has no upper body bone, no masked clips",
+                skeleton
+            );
+            animations.masked_skeletons.insert(skeleton.clone());
+            continue;
+        };
+
+        // Scene might not have spawned it is bones yet
+        let Some(upper_root) = children_entities
+            .iter_descendants(skeleton_entity)
+            .find(|entity| {
+                names
+                    .get(*entity)
+                    .is_ok_and(|name| name.as_str() == upper_body_bone)
+            })
+        else {
+            continue;
+        };
+        let upper_targets: HashSet<AnimationTargetId> = std::iter::once(upper_root)
+            .chain(children_entities.iter_descendants(upper_root))
+            .filter_map(|entity| animation_targets.get(entity).ok())
+            .map(|animation_target| animation_target.id)
+            .collect();
+
+        let Some(gltf) = char_collection
+            .gltf_files
+            .get(skeleton)
+            .and_then(|skeleton_gltf| assets_gltf.get(skeleton_gltf))
+        else {
+            continue;
+        };
+
+        let mut lower_clips: Vec<&str> = LocomotionState::ALL
+            .iter()
+            .map(|state| state.properties().name)
+            .collect();
+        lower_clips.sort();
+        lower_clips.dedup();
+
+        let animation_graph = assets_animation_graph
+            .get_mut(&animations.animation_graph)
+            .expect("To have created animation graph");
+        let mut masked_nodes = Vec::new();
+        for lower in lower_clips.iter() {
+            for upper in OneShotAnimation::ALL.map(|animation| animation.clip_name()) {
+                let (Some(lower_clip), Some(upper_clip)) = (
+                    gltf.named_animations
+                        .get(*lower)
+                        .and_then(|clip| assets_clips.get(clip)),
+                    gltf.named_animations
+                        .get(upper)
+                        .and_then(|clip| assets_clips.get(clip)),
+                ) else {
+                    continue;
+                };
+
+                let mut masked_clip = AnimationClip::default();
+                for (target, curves) in lower_clip.curves().iter() {
+                    if upper_targets.contains(target) {
+                        continue;
+                    }
+                    for curve in curves.iter() {
+                        masked_clip.add_curve_to_target(*target, curve.clone());
+                    }
+                }
+                for (target, curves) in upper_clip.curves().iter() {
+                    if !upper_targets.contains(target) {
+                        continue;
+                    }
+                    for curve in curves.iter() {
+                        masked_clip.add_curve_to_target(*target, curve.clone());
+                    }
+                }
 
-    let gltf = assets_gltf
-        .get(skeleton_gltf)
-        .expect("Skeleton path to be found");
+                let handle = assets_clips.add(masked_clip);
+                let node = animation_graph.add_clip(handle, 1.0, animation_graph.root);
+                masked_nodes.push((Animations::masked_name(lower, upper), node));
+            }
+        }
 
-    for (name_animation, animation_clip) in gltf.named_animations.iter() {
-        let node = animation_graph.add_clip(animation_clip.clone(), 1.0, animation_graph.root);
+        info!(
+            "Made {} masked clips for skeleton {} from bone {}",
+            masked_nodes.len(),
+            skeleton,
+            upper_body_bone
+        );
         animations
             .named_nodes
-            .insert(name_animation.to_string(), node);
-        // info!(
-        //     "Current available animations are {} for skeleton {}",
-        //     name_animation, "characters/mod_char/main_skeleton.glb"
-        // );
+            .entry(skeleton.clone())
+            .or_default()
+            .extend(masked_nodes);
+        animations.masked_skeletons.insert(skeleton.clone());
     }
 }
 
-/// Since it already passes this information no need in input channel I dont need to set it
+/// Derives locomotion state from predicted physics, same for the player we control and every other player
+/// Jump action only makes our own jump react a bit sooner, side players just follow their velocity
+/// One shot actions come from replicated player animation and start at the tick it tells us
+/// Interpolated players are shown a bit in the past, so their actions are timed with the interpolation tick
 fn state_machine(
-    query: Query<(&ActionState<PlayerAction>, &PointerAnimatedEntities), With<Predicted>>,
+    time: Res<Time>,
+    tick_manager: Res<TickManager>,
+    connection_manager: Res<ConnectionManager>,
+    mut query: Query<
+        (
+            Has<Interpolated>,
+            &LinearVelocity,
+            &Rotation,
+            Option<&RayHits>,
+            Option<&ActionState<PlayerAction>>,
+            &PlayerAnimation,
+            &PlayerVisuals,
+            &PointerAnimatedEntities,
+            &mut AnimationStateMachine,
+        ),
+        Or<(With<Predicted>, With<Interpolated>)>,
+    >,
     mut animation_components: Query<(&mut AnimationPlayer, &mut AnimationTransitions)>,
     animations: Res<Animations>,
 ) {
-    let named_animations = animations.named_nodes.clone();
+    let current_tick = tick_manager.tick();
+    let interpolation_tick = connection_manager.interpolation_tick(&tick_manager);
+    for (
+        is_interpolated,
+        linear_velocity,
+        rotation,
+        ray_hits,
+        action_state,
+        player_animation,
+        player_visuals,
+        pointer_animated,
+        mut state_machine,
+    ) in query.iter_mut()
+    {
+        let local_velocity = rotation.0.inverse() * linear_velocity.0;
+        // Interpolated players have no physics, so no rays, we guess from their vertical speed
+        let grounded = match ray_hits {
+            Some(ray_hits) => !ray_hits.is_empty(),
+            None => linear_velocity.y.abs() < AIRBORNE_VERTICAL_SPEED,
+        };
+        let jump_pressed =
+            action_state.is_some_and(|action_state| action_state.just_pressed(&PlayerAction::Jump));
 
-    for (action_state, pointer_animated) in query.iter() {
+        let next_state = state_machine.state.next(
+            state_machine.elapsed,
+            local_velocity,
+            grounded,
+            jump_pressed,
+        );
+        if next_state != state_machine.state {
+            state_machine.state = next_state;
+            state_machine.elapsed = Duration::ZERO;
+        } else {
+            state_machine.elapsed += time.delta();
+        }
+
+        // Upper body actions run their whole clip and then hand the arms back to locomotion
+        let previous_start_tick = state_machine
+            .action
+            .as_ref()
+            .map(|action| action.start_tick);
+        let animation_tick = if is_interpolated {
+            interpolation_tick
+        } else {
+            current_tick
+        };
+        state_machine.action = player_animation.clip.and_then(|clip| {
+            let ticks_since_start = (animation_tick - player_animation.start_tick).max(0);
+            let elapsed = Duration::from_secs_f64(ticks_since_start as f64 / FIXED_TIMESTEP_HZ)
+                .mul_f32(player_animation.speed);
+            let length = animations
+                .clip_length(&player_visuals.skeleton, clip.clip_name())
+                .unwrap_or(DEFAULT_ACTION_LENGTH);
+            if elapsed >= length {
+                return None;
+            }
+            Some(UpperBodyAction {
+                clip: clip.clip_name().to_string(),
+                start_tick: player_animation.start_tick,
+                elapsed,
+                speed: player_animation.speed,
+            })
+        });
+
+        // Same action again while previous one is still playing, like shooting twice
+        let restart_action = state_machine.action.as_ref().is_some_and(|action| {
+            previous_start_tick.is_some_and(|start_tick| start_tick != action.start_tick)
+        });
+
+        let properties = state_machine.state.properties();
+        let masked_node = state_machine.action.as_ref().and_then(|action| {
+            let masked_name = Animations::masked_name(properties.name, &action.clip);
+            let node = animations.node(&player_visuals.skeleton, &masked_name)?;
+            Some((node, action.elapsed, action.speed))
+        });
+        let (node, transition, speed, action_elapsed) = match masked_node {
+            Some((node, action_elapsed, action_speed)) => {
+                (node, ACTION_TRANSITION, action_speed, Some(action_elapsed))
+            }
+            None => {
+                let Some(node) = animations.node(&player_visuals.skeleton, properties.name) else {
+                    warn_once!(
+                        "Skeleton {} has no {} animation",
+                        player_visuals.skeleton,
+                        properties.name
+                    );
+                    continue;
+                };
+                (node, properties.transition, properties.speed, None)
+            }
+        };
+
+        // Newly animated parts also pass here, as they have no main animation yet
         for animated_entity in pointer_animated.0.iter() {
             if let Ok((mut animation_player, mut animation_transition)) =
                 animation_components.get_mut(*animated_entity)
             {
-                if action_state.just_pressed(&PlayerAction::Jump) {
-                    let node = named_animations.get("Sword_Slash").unwrap();
-                    animation_transition.play(&mut animation_player, *node, Duration::ZERO);
+                if !restart_action && animation_transition.get_main_animation() == Some(node) {
+                    // Same clip as before, like walking into the air, only speed changes
+                    if let Some(active_animation) = animation_player.animation_mut(node) {
+                        active_animation.set_speed(speed);
+                    }
+                    continue;
+                }
+                let active_animation =
+                    animation_transition.play(&mut animation_player, node, transition);
+                active_animation.set_speed(speed);
+                if properties.repeat {
+                    active_animation.repeat();
+                }
+                // Locomotion changed mid action, upper body continues where it was
+                if let Some(action_elapsed) = action_elapsed {
+                    active_animation.seek_to(action_elapsed.as_secs_f32());
                 }
             }
         }
@@ -1,7 +1,9 @@
 //! Super camera is gonna have orbit mode and some following shit like my old one
 //! YEAH
 use crate::client::MyAppState;
-use avian3d::prelude::PhysicsSet;
+use crate::shared::protocol::player_structs::{MarkerPlayer, DEFAULT_STICK_DEADZONE};
+use crate::shared::shared_physics::GameLayer;
+use avian3d::prelude::*;
 use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, PrimaryWindow};
 use bevy_panorbit_camera::PanOrbitCamera;
@@ -10,6 +12,7 @@ use leafwing_input_manager::prelude::*;
 use leafwing_input_manager::Actionlike;
 use lightyear::client::prediction::Predicted;
 use lightyear::shared::replication::components::Controlled;
+use serde::{Deserialize, Serialize};
 
 pub struct PlayerCameraPlugin;
 
@@ -18,17 +21,23 @@ impl Plugin for PlayerCameraPlugin {
         // Debugging
         app.register_type::<Zoom>();
         app.register_type::<CamInfo>();
+        app.register_type::<CamCollision>();
+        app.register_type::<CamBlend>();
 
         // This might cause issue later
         app.add_plugins(InputManagerPlugin::<CameraMovement>::default());
 
         app.add_systems(Startup, spawn_begin_camera);
+        app.add_systems(Update, target_controlled_player);
 
         app.add_systems(
             Update,
             (
                 toggle_cursor,
+                switch_camera_mode,
+                blend_camera_mode,
                 orbit_mouse.run_if(orbit_condition),
+                orbit_gamepad,
                 zoom_mouse.run_if(zoom_condition),
             )
                 .run_if(in_state(MyAppState::Game))
@@ -60,22 +69,30 @@ fn orbit_condition(cam_q: Query<&CamInfo>) -> bool {
     return cam.cursor_lock_active;
 }
 
-// only zoom if zoom is enabled & the cursor lock feature is enabled & active
+// only zoom if zoom is enabled & in free orbit, no cursor lock needed as gamepad zooms too
 fn zoom_condition(cam_q: Query<&CamInfo, With<CamInfo>>) -> bool {
     let Ok(cam) = cam_q.get_single() else {
         return false;
     };
-    return cam.zoom_enabled && cam.cursor_lock_active;
+    return cam.zoom_enabled && cam.mode == CameraMode::FreeOrbit;
 }
 
 /// Marker component tells me who is my main camera - A lot of mechanic in the future gonna be based on it
 #[derive(Component)]
 pub struct MarkerMainCamera;
 
+/// Marker component tells camera who to follow, my own player unless I am spectating
+#[derive(Component)]
+pub struct CameraTarget;
+
 /// Info for camera mechanics
 #[derive(Reflect, Component, Debug)]
 pub struct CamInfo {
     pub mouse_sens: f32,
+    // Fov of free orbit in radians, other modes scale from it
+    pub fov: f32,
+    // Radians per second at full stick, deadzone and sensitivity are already applied by input map
+    pub stick_orbit_speed: f32,
     pub zoom_enabled: bool,
     pub zoom: Zoom,
     pub zoom_sens: f32,
@@ -83,6 +100,155 @@ pub struct CamInfo {
     pub cursor_lock_active: bool,
     pub yaw_limit: Option<(f32, f32)>,
     pub pitch_limit: Option<(f32, f32)>,
+    pub collision: CamCollision,
+    pub mode: CameraMode,
+    pub shoulder: ShoulderSide,
+    // How fast we go from one mode to another
+    pub mode_transition_speed: f32,
+    // Current values we are at, they keep moving towards the ones of the current mode
+    pub blend: CamBlend,
+}
+
+/// Ways the camera can follow the player
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CameraMode {
+    #[default]
+    FreeOrbit,
+    OverShoulder,
+    AimDownSights,
+}
+
+impl CameraMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::FreeOrbit => Self::OverShoulder,
+            Self::OverShoulder => Self::AimDownSights,
+            Self::AimDownSights => Self::FreeOrbit,
+        }
+    }
+
+    /// Aiming modes turn the player to wherever the camera looks
+    pub fn is_aiming(self) -> bool {
+        self != Self::FreeOrbit
+    }
+
+    pub fn properties(self) -> ModeProperties {
+        match self {
+            Self::FreeOrbit => ModeProperties {
+                shoulder_offset: 0.0,
+                distance: None,
+                fov_scale: 1.0,
+                sens_scale: 1.0,
+            },
+            Self::OverShoulder => ModeProperties {
+                shoulder_offset: 0.75,
+                distance: Some(3.0),
+                fov_scale: 1.0,
+                sens_scale: 0.8,
+            },
+            Self::AimDownSights => ModeProperties {
+                shoulder_offset: 0.5,
+                distance: Some(1.5),
+                fov_scale: 4.0 / 7.0,
+                sens_scale: 0.4,
+            },
+        }
+    }
+}
+
+/// What changes from one mode to another
+pub struct ModeProperties {
+    // How far to the side of player camera sits
+    pub shoulder_offset: f32,
+    // None means we use zoom radius
+    pub distance: Option<f32>,
+    // Multiplies fov from settings
+    pub fov_scale: f32,
+    // Multiplies mouse sens
+    pub sens_scale: f32,
+}
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ShoulderSide {
+    #[default]
+    Right,
+    Left,
+}
+
+impl ShoulderSide {
+    pub fn swap(self) -> Self {
+        match self {
+            Self::Right => Self::Left,
+            Self::Left => Self::Right,
+        }
+    }
+
+    pub fn sign(self) -> f32 {
+        match self {
+            Self::Right => 1.0,
+            Self::Left => -1.0,
+        }
+    }
+}
+
+/// Mode values camera is currently using
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub struct CamBlend {
+    // Sideways offset, positive is right
+    pub offset: f32,
+    pub distance: f32,
+    pub fov: f32,
+    pub sens_scale: f32,
+}
+
+impl CamBlend {
+    /// Values the mode wants to reach
+    pub fn target(mode: CameraMode, shoulder: ShoulderSide, zoom_radius: f32, fov: f32) -> Self {
+        let properties = mode.properties();
+        Self {
+            offset: properties.shoulder_offset * shoulder.sign(),
+            distance: properties.distance.unwrap_or(zoom_radius),
+            fov: fov * properties.fov_scale,
+            sens_scale: properties.sens_scale,
+        }
+    }
+
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            offset: self.offset.lerp(other.offset, t),
+            distance: self.distance.lerp(other.distance, t),
+            fov: self.fov.lerp(other.fov, t),
+            sens_scale: self.sens_scale.lerp(other.sens_scale, t),
+        }
+    }
+}
+
+/// Keeps camera from going through walls, we shape cast from player to where camera wants to be
+#[derive(Reflect, Debug)]
+pub struct CamCollision {
+    pub enabled: bool,
+    // Radius of sphere we cast, bigger keeps camera further from walls
+    pub probe_radius: f32,
+    // What the camera cant go through
+    pub layers: LayerMask,
+    // How fast camera comes closer when something gets in the way, and goes back when it is gone
+    pub pull_in_speed: f32,
+    pub push_out_speed: f32,
+    // Current distance from player, what actually gets used instead of zoom radius
+    pub distance: f32,
+}
+
+impl CamCollision {
+    pub fn new(layers: impl Into<LayerMask>, distance: f32) -> Self {
+        Self {
+            enabled: true,
+            probe_radius: 0.2,
+            layers: layers.into(),
+            pull_in_speed: 25.0,
+            push_out_speed: 4.0,
+            distance,
+        }
+    }
 }
 
 /// Sets the zoom bounds (min & max)
@@ -103,18 +269,60 @@ impl Zoom {
     }
 }
 
-#[derive(Actionlike, Clone, Debug, Copy, PartialEq, Eq, Hash, Reflect)]
-enum CameraMovement {
+#[derive(Actionlike, Clone, Debug, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum CameraMovement {
     #[actionlike(Axis)]
     Zoom,
     #[actionlike(DualAxis)]
     Pan,
+    // Right stick
+    #[actionlike(DualAxis)]
+    Orbit,
+    SwitchMode,
+    SwapShoulder,
+}
+
+impl CameraMovement {
+    /// Mouse movement and scroll, these are not rebindable
+    pub fn axis_input_map() -> InputMap<Self> {
+        InputMap::default()
+            .with_dual_axis(Self::Pan, MouseMove::default())
+            .with_axis(Self::Zoom, MouseScrollAxis::Y)
+    }
+
+    /// Gamepad layout is fixed, stick feel comes from settings
+    pub fn gamepad_input_map(deadzone: f32, sensitivity: f32, invert_y: bool) -> InputMap<Self> {
+        let stick = GamepadStick::RIGHT
+            .with_circle_deadzone(deadzone)
+            .sensitivity(sensitivity);
+        let stick = if invert_y { stick.inverted_y() } else { stick };
+        InputMap::default()
+            .with_dual_axis(Self::Orbit, stick)
+            .with_axis(Self::Zoom, GamepadVirtualAxis::DPAD_Y)
+            .with(Self::SwitchMode, GamepadButtonType::LeftThumb)
+            .with(Self::SwapShoulder, GamepadButtonType::RightThumb)
+    }
+
+    pub fn default_input_map() -> InputMap<Self> {
+        let mut input_map = Self::axis_input_map()
+            .with(Self::SwitchMode, KeyCode::KeyV)
+            .with(Self::SwapShoulder, KeyCode::KeyC);
+        input_map.merge(&Self::gamepad_input_map(DEFAULT_STICK_DEADZONE, 1.0, false));
+        input_map
+    }
 }
 
+/// Controls plugin swaps this map for the one the player saved, settings plugin does the same for sens and fov
 fn spawn_begin_camera(mut commands: Commands) {
-    let input_map = InputMap::default()
-        .with_dual_axis(CameraMovement::Pan, MouseMove::default())
-        .with_axis(CameraMovement::Zoom, MouseScrollAxis::Y);
+    let input_map = CameraMovement::default_input_map();
+    let zoom = Zoom::new(5.0, 10.0);
+    let fov = PI / 4.0;
+    let blend = CamBlend::target(
+        CameraMode::default(),
+        ShoulderSide::default(),
+        zoom.radius,
+        fov,
+    );
 
     commands
         .spawn(Camera3dBundle::default())
@@ -122,13 +330,20 @@ fn spawn_begin_camera(mut commands: Commands) {
         .insert(Name::new("MainCamera"))
         .insert(CamInfo {
             mouse_sens: 0.75,
+            fov,
+            stick_orbit_speed: PI,
             zoom_enabled: true,
-            zoom: Zoom::new(5.0, 10.0),
+            zoom,
             zoom_sens: 2.0,
             cursor_lock_activation_key: KeyCode::KeyR,
             cursor_lock_active: false,
             yaw_limit: None,
             pitch_limit: Some((-PI / 2.0, PI / 20.0)),
+            collision: CamCollision::new(GameLayer::Ground, blend.distance),
+            mode: CameraMode::default(),
+            shoulder: ShoulderSide::default(),
+            mode_transition_speed: 10.0,
+            blend,
         })
         .insert(InputManagerBundle::with_map(input_map));
 }
@@ -157,6 +372,45 @@ fn toggle_cursor(
     }
 }
 
+/// Cycles camera modes and swaps shoulder
+fn switch_camera_mode(
+    mut cam_q: Query<(&mut CamInfo, &ActionState<CameraMovement>), With<MarkerMainCamera>>,
+) {
+    let Ok((mut cam, camera_movement)) = cam_q.get_single_mut() else {
+        return;
+    };
+
+    if camera_movement.just_pressed(&CameraMovement::SwitchMode) {
+        cam.mode = cam.mode.next();
+        info!("Camera mode {:?}", cam.mode);
+    }
+    if camera_movement.just_pressed(&CameraMovement::SwapShoulder) {
+        cam.shoulder = cam.shoulder.swap();
+    }
+}
+
+/// Moves current camera values towards the ones of the mode, also applies fov
+fn blend_camera_mode(
+    mut cam_q: Query<(&mut CamInfo, &mut Projection), With<MarkerMainCamera>>,
+    time: Res<Time>,
+) {
+    let Ok((mut cam, mut projection)) = cam_q.get_single_mut() else {
+        return;
+    };
+
+    let target = CamBlend::target(cam.mode, cam.shoulder, cam.zoom.radius, cam.fov);
+    if cam.blend != target {
+        let t = 1.0 - (-cam.mode_transition_speed * time.delta_seconds()).exp();
+        cam.blend = cam.blend.lerp(&target, t);
+    }
+
+    if let Projection::Perspective(perspective) = projection.as_mut() {
+        if perspective.fov != cam.blend.fov {
+            perspective.fov = cam.blend.fov;
+        }
+    }
+}
+
 /// Adds the possibility to adjust camera angle and position
 fn orbit_mouse(
     window_q: Query<&Window, With<PrimaryWindow>>,
@@ -173,37 +427,62 @@ fn orbit_mouse(
 
         if rotation_delta.length_squared() > 0.0 {
             // Calculate normalized rotation deltas
-            let delta_x = (rotation_delta.x / window.width()) * PI * cam_info.mouse_sens;
-            let delta_y = (rotation_delta.y / window.height()) * PI * cam_info.mouse_sens;
-
-            // Retrieve current yaw and pitch
-            let (yaw, pitch, _) = cam_transform.rotation.to_euler(EulerRot::YXZ);
-
-            // Apply yaw limit if set
-            let new_yaw = if let Some((min_yaw, max_yaw)) = cam_info.yaw_limit {
-                (yaw - delta_x).clamp(min_yaw, max_yaw)
-            } else {
-                yaw - delta_x
-            };
-
-            // Apply pitch limit if set
-            let new_pitch = if let Some((min_pitch, max_pitch)) = cam_info.pitch_limit {
-                (pitch - delta_y).clamp(min_pitch, max_pitch)
-            } else {
-                pitch - delta_y
-            };
-
-            // Apply rotation after limit set
-            cam_transform.rotation = Quat::from_euler(EulerRot::YXZ, new_yaw, new_pitch, 0.0);
-
-            // Update camera translation based on zoom radius
-            let rot_matrix = Mat3::from_quat(cam_transform.rotation);
-            cam_transform.translation =
-                rot_matrix.mul_vec3(Vec3::new(0.0, 0.0, cam_info.zoom.radius));
+            let sens = cam_info.mouse_sens * cam_info.blend.sens_scale;
+            let delta_x = (rotation_delta.x / window.width()) * PI * sens;
+            let delta_y = (rotation_delta.y / window.height()) * PI * sens;
+
+            rotate_camera(cam_info, &mut cam_transform, delta_x, delta_y);
+        }
+    }
+}
+
+/// Right stick orbits at a steady speed no matter the framerate
+fn orbit_gamepad(
+    time: Res<Time>,
+    mut cam_q: Query<
+        (&CamInfo, &ActionState<CameraMovement>, &mut Transform),
+        With<MarkerMainCamera>,
+    >,
+) {
+    if let Ok((cam_info, camera_movement, mut cam_transform)) = cam_q.get_single_mut() {
+        let stick: Vec2 = camera_movement.axis_pair(&CameraMovement::Orbit);
+        if stick.length_squared() > 0.0 {
+            let speed =
+                cam_info.stick_orbit_speed * cam_info.blend.sens_scale * time.delta_seconds();
+            // Pushing stick up looks up
+            rotate_camera(
+                cam_info,
+                &mut cam_transform,
+                stick.x * speed,
+                -stick.y * speed,
+            );
         }
     }
 }
 
+/// Helper turns camera by the given deltas respecting yaw and pitch limits
+fn rotate_camera(cam_info: &CamInfo, cam_transform: &mut Transform, delta_x: f32, delta_y: f32) {
+    // Retrieve current yaw and pitch
+    let (yaw, pitch, _) = cam_transform.rotation.to_euler(EulerRot::YXZ);
+
+    // Apply yaw limit if set
+    let new_yaw = if let Some((min_yaw, max_yaw)) = cam_info.yaw_limit {
+        (yaw - delta_x).clamp(min_yaw, max_yaw)
+    } else {
+        yaw - delta_x
+    };
+
+    // Apply pitch limit if set
+    let new_pitch = if let Some((min_pitch, max_pitch)) = cam_info.pitch_limit {
+        (pitch - delta_y).clamp(min_pitch, max_pitch)
+    } else {
+        pitch - delta_y
+    };
+
+    // Apply rotation after limit set, translation is up to whoever the camera follows
+    cam_transform.rotation = Quat::from_euler(EulerRot::YXZ, new_yaw, new_pitch, 0.0);
+}
+
 /// Zooms in the camera
 fn zoom_mouse(
     mut cam_q: Query<(&mut CamInfo, &ActionState<CameraMovement>), With<MarkerMainCamera>>,
@@ -217,20 +496,65 @@ fn zoom_mouse(
     }
 }
 
-fn sync_player_camera(
-    player_q: Query<&Transform, (With<Predicted>, With<Controlled>)>,
-    mut cam_q: Query<(&mut CamInfo, &mut Transform), Without<Predicted>>,
+/// Places camera behind player according to mode, if geometry is in the way camera comes closer to player
+pub fn sync_player_camera(
+    player_q: Query<(Entity, &Transform), With<CameraTarget>>,
+    mut cam_q: Query<(&mut CamInfo, &mut Transform), Without<CameraTarget>>,
+    spatial_query: SpatialQuery,
+    time: Res<Time>,
 ) {
-    if let Ok(player_transform) = player_q.get_single() {
-        let (cam, mut cam_transform) = cam_q.get_single_mut().expect("Camera to exist");
+    if let Ok((player, player_transform)) = player_q.get_single() {
+        let (mut cam, mut cam_transform) = cam_q.get_single_mut().expect("Camera to exist");
 
         let rotation_matrix = Mat3::from_quat(cam_transform.rotation);
 
-        // Offset actually
-        let offset = rotation_matrix.mul_vec3(Vec3::new(0.0, 0.5, cam.zoom.radius));
+        // Camera orbits around this point a little above player
+        let pivot = player_transform.translation + Vec3::new(0.0, 0.5, 0.0);
+        // Over the shoulder modes push the camera sideways, we cast towards that too so it doesnt clip
+        let desired_offset =
+            rotation_matrix.mul_vec3(Vec3::new(cam.blend.offset, 0.0, cam.blend.distance));
+        let direction = desired_offset.normalize_or_zero();
+        let desired_distance = desired_offset.length();
+
+        let target_distance = match Dir3::new(direction) {
+            Ok(cast_direction) if cam.collision.enabled => spatial_query
+                .cast_shape(
+                    &Collider::sphere(cam.collision.probe_radius),
+                    pivot,
+                    Quat::IDENTITY,
+                    cast_direction,
+                    desired_distance,
+                    true,
+                    SpatialQueryFilter::from_mask(cam.collision.layers)
+                        .with_excluded_entities([player]),
+                )
+                .map(|hit| hit.time_of_impact)
+                .unwrap_or(desired_distance),
+            _ => desired_distance,
+        };
+
+        // Pulling in is fast so we never see through walls, going back out is smooth
+        let current_distance = cam.collision.distance;
+        let speed = if target_distance < current_distance {
+            cam.collision.pull_in_speed
+        } else {
+            cam.collision.push_out_speed
+        };
+        let blend = 1.0 - (-speed * time.delta_seconds()).exp();
+        cam.collision.distance = current_distance + (target_distance - current_distance) * blend;
 
         // Update the camera translation
-        cam_transform.translation = offset + player_transform.translation;
+        cam_transform.translation = pivot + direction * cam.collision.distance;
+    }
+}
+
+/// My own player is who camera follows when I am playing
+fn target_controlled_player(
+    players: Query<Entity, (Added<Predicted>, With<Controlled>, With<MarkerPlayer>)>,
+    mut commands: Commands,
+) {
+    for player in players.iter() {
+        commands.entity(player).insert(CameraTarget);
     }
 }
 
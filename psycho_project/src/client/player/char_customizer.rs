@@ -1,13 +1,17 @@
 //! Plugin responsible for customizing the player character in rtt and the final result shall be used and replicated when enter ingame state
+use crate::client::essentials::EasyClient;
 use crate::client::load_assets::CharCollection;
 use crate::client::MyAppState;
 use crate::shared::protocol::player_structs::*;
+use crate::shared::protocol::weapon_structs::EquippedWeapon;
+use crate::shared::shared_parts::{PartCatalog, PartSlot};
 use bevy::animation::AnimationTarget;
 use bevy::prelude::*;
+use bevy::render::mesh::skinning::SkinnedMesh;
 use bevy::utils::HashMap;
 use lightyear::client::events::MessageEvent;
 use lightyear::connection::id::ClientId;
-use lightyear::prelude::client::Predicted;
+use lightyear::prelude::client::{Interpolated, Predicted};
 use lightyear::shared::replication::components::Controlled;
 use std::collections::VecDeque;
 
@@ -18,6 +22,7 @@ impl Plugin for CustomizeCharPlugin {
         //Events
         app.add_event::<TranferAnim>();
         app.add_event::<ResetAnimation>();
+        app.add_event::<TintChanged>();
 
         // Player debugging
         app.register_type::<PlayerVisuals>();
@@ -26,6 +31,7 @@ impl Plugin for CustomizeCharPlugin {
         // Starting up base resource
         app.init_resource::<BodyPartMap>();
         app.init_resource::<SkeletonMap>();
+        app.init_resource::<TintMap>();
 
         //Debugging
         app.register_type::<BodyPartMap>();
@@ -40,6 +46,21 @@ impl Plugin for CustomizeCharPlugin {
         // System to customize character correctly
         app.add_systems(Update, customizes_character);
 
+        // Colors of parts, pending tints wait for their scene meshes to spawn
+        app.add_systems(
+            Update,
+            (
+                listener_change_tint,
+                revert_rejected_preview,
+                customizes_tint,
+                apply_pending_tints,
+            )
+                .chain(),
+        );
+
+        // Keeps equipped weapon in player hand
+        app.add_systems(Update, attach_weapon.run_if(in_state(MyAppState::Game)));
+
         // Does the anim transfer - I know last here is weird but here is the thing because of the way child entities spawn in bevy
         // We need to wait a good while before running this guy
         app.add_systems(Last, transfer_essential_components);
@@ -75,7 +96,18 @@ impl BodyPartMap {
 /// Tell me who is the current skeleton of that player
 #[derive(Resource, Default, Reflect)]
 #[reflect(Resource)]
-struct SkeletonMap(HashMap<ClientId, Entity>);
+struct SkeletonMap(HashMap<ClientId, SpawnedSkeleton>);
+
+/// Marks a spawned skeleton scene with it is file path, animations use it to build masked clips
+#[derive(Component)]
+pub struct SkeletonScene(pub String);
+
+#[derive(Reflect)]
+struct SpawnedSkeleton {
+    entity: Entity,
+    // File path of skeleton, tells me which bone map to use
+    file_path: String,
+}
 
 /// Tell me when to transfer the anim of a certain player
 #[derive(Event, Reflect)]
@@ -93,24 +125,75 @@ struct HasTarget;
 #[derive(Component)]
 struct HasVisuals;
 
+/// Sent whenever a player part should change colors, comes from server or from our own inventory preview
+#[derive(Event)]
+pub struct TintChanged {
+    pub client_id: ClientId,
+    pub slot: PartSlot,
+    pub tint: PartTint,
+}
+
+/// Tells me current tint of each player part, needed as predicted player visuals only sync once
+#[derive(Resource, Default)]
+struct TintMap(HashMap<(ClientId, PartSlot), PartTint>);
+
+/// Part scene that still needs it is tint applied, scenes take a while to spawn their meshes
+#[derive(Component)]
+struct PendingTint {
+    // File path of part, used to know which of it is materials is the primary one
+    part: String,
+    tint: PartTint,
+}
+
+/// Material the mesh had before we tinted it
+#[derive(Component)]
+struct OriginalMaterial(Handle<StandardMaterial>);
+
+/// Bone our weapons get parented to, in our skeletons it is the handle in the right hand
+const WEAPON_HAND_BONE: &str = "EquipmentHandle.R";
+
+/// Tells me which weapon scene is in the player hand and from what slot it came from
+#[derive(Component)]
+struct AttachedWeapon {
+    slot: usize,
+    // None when weapon scene couldnt be spawned, avoids retrying every frame
+    entity: Option<Entity>,
+}
+
+/// Helper grabs the first scene of a gltf, none if it is not in collection or not loaded
+fn find_scene(
+    visual: &str,
+    client_collection: &Res<CharCollection>,
+    gltfs: &Res<Assets<Gltf>>,
+) -> Option<Handle<Scene>> {
+    let gltf = client_collection.gltf_files.get(visual)?;
+    let loaded_gltf = gltfs.get(gltf)?;
+    loaded_gltf.scenes.first().cloned()
+}
+
 /// Helper function spawns a series of scenes acording to the given batch of visuals being passed
+/// If visual doesnt exist we use the fallback one instead, that way a bad path doesnt crash us
 fn spawn_scene(
     visual: &str,
+    fallback: &str,
     client_collection: &Res<CharCollection>,
     gltfs: &Res<Assets<Gltf>>,
     commands: &mut Commands,
-) -> Entity {
-    // Retrieve the GLTF handle from the collection, error if not found
-    let gltf = client_collection
-        .gltf_files
-        .get(visual)
-        .expect(&format!("Couldn't find GLTF file path for: {}", visual));
-
-    // Retrieve the loaded GLTF and its first scene, expect it to exist
-    let loaded_gltf = gltfs
-        .get(gltf)
-        .expect("To find gltf handle in loaded gltfs");
-    let visual_scene = loaded_gltf.scenes[0].clone();
+) -> Option<Entity> {
+    let visual_scene = match find_scene(visual, client_collection, gltfs) {
+        Some(visual_scene) => visual_scene,
+        None => {
+            warn!(
+                "Couldn't find GLTF file path for: {} using {} instead",
+                visual, fallback
+            );
+            let Some(fallback_scene) = find_scene(fallback, client_collection, gltfs) else {
+                error!("Couldn't find fallback GLTF either {}", fallback);
+                return None;
+            };
+            fallback_scene
+        }
+    };
 
     let scene = SceneBundle {
         scene: visual_scene,
@@ -127,7 +210,7 @@ fn spawn_scene(
     // Spawn and return the appropriate entity
 
     let id = commands.spawn(scene).id();
-    id
+    Some(id)
 }
 
 /// Helper Collects a lot of subchild bones
@@ -178,7 +261,7 @@ fn find_child_with_name_containing(
     return None;
 }
 
-/// Spawns visuals scenes and parents them to predicted player
+/// Spawns visuals scenes and parents them to predicted player, spectators get interpolated ones instead
 fn formulates_players(
     main_player: Query<
         (
@@ -188,12 +271,16 @@ fn formulates_players(
             Has<HasVisuals>,
             Has<Controlled>,
         ),
-        (Added<Predicted>, With<MarkerPlayer>),
+        (
+            Or<(Added<Predicted>, Added<Interpolated>)>,
+            With<MarkerPlayer>,
+        ),
     >,
     gltfs: Res<Assets<Gltf>>,
     client_collection: Res<CharCollection>,
     mut body_part_map: ResMut<BodyPartMap>,
     mut skeleton_map: ResMut<SkeletonMap>,
+    mut tint_map: ResMut<TintMap>,
     mut transfer_anim: EventWriter<TranferAnim>,
     mut commands: Commands,
 ) {
@@ -224,24 +311,44 @@ fn formulates_players(
                     .insert(HasVisuals);
             }
 
-            for file_path in player_visuals.iter_visuals() {
-                if file_path.contains("skeleton") {
-                    info!("Found side player skeleton");
-                    let visual_scene =
-                        spawn_scene(&file_path, &client_collection, &gltfs, &mut commands);
-                    commands.entity(visual_scene).set_parent(entity);
+            let default_visuals = PlayerVisuals::default();
+            for (slot, file_path) in player_visuals.iter_visuals() {
+                let Some(visual_scene) = spawn_scene(
+                    &file_path,
+                    default_visuals.part(slot),
+                    &client_collection,
+                    &gltfs,
+                    &mut commands,
+                ) else {
+                    continue;
+                };
+                commands.entity(visual_scene).set_parent(entity);
 
+                if slot == PartSlot::Skeleton {
                     info!("Inserting skeleton into map");
-                    skeleton_map.0.insert(client_id, visual_scene);
+                    commands
+                        .entity(visual_scene)
+                        .insert(SkeletonScene(file_path.to_string()));
+                    skeleton_map.0.insert(
+                        client_id,
+                        SpawnedSkeleton {
+                            entity: visual_scene,
+                            file_path: file_path.to_string(),
+                        },
+                    );
                 } else {
-                    let visual_scene =
-                        spawn_scene(&file_path, &client_collection, &gltfs, &mut commands);
-
-                    commands.entity(visual_scene).set_parent(entity);
-
                     body_part_map
                         .0
                         .insert((client_id, file_path.to_string()), visual_scene);
+
+                    let tint = player_visuals.tint(slot);
+                    tint_map.0.insert((client_id, slot), tint);
+                    if tint != PartTint::default() {
+                        commands.entity(visual_scene).insert(PendingTint {
+                            part: file_path.to_string(),
+                            tint,
+                        });
+                    }
                 }
             }
             info!(
@@ -261,11 +368,15 @@ fn customizes_character(
     parent: Query<&Parent>,
     mut change_char: EventReader<MessageEvent<ChangeChar>>,
     mut body_part: ResMut<BodyPartMap>,
+    mut skeleton_map: ResMut<SkeletonMap>,
     client_collection: Option<Res<CharCollection>>,
     gltfs: Res<Assets<Gltf>>,
+    catalog: Res<PartCatalog>,
+    tint_map: Res<TintMap>,
     mut transfer_anim: EventWriter<TranferAnim>,
     mut commands: Commands,
 ) {
+    let default_visuals = PlayerVisuals::default();
     for part_to_adjust in change_char.read() {
         let message = part_to_adjust.message();
         let (client_id, part_to_change) = message.0.clone();
@@ -278,7 +389,56 @@ fn customizes_character(
             "Received old parts from inv screen {}",
             part_to_change.new_part
         );
-        if let Some(old_body_part) = body_part.0.remove(&(client_id, part_to_change.old_part)) {
+        let is_skeleton = skeleton_map
+            .0
+            .get(&client_id)
+            .is_some_and(|skeleton| skeleton.file_path == part_to_change.old_part);
+        if is_skeleton {
+            let Some(ref char_collection) = client_collection else {
+                continue;
+            };
+            let old_skeleton = skeleton_map
+                .0
+                .remove(&client_id)
+                .expect("To have checked skeleton above");
+            let player = parent
+                .get(old_skeleton.entity)
+                .expect("To always have a father")
+                .get();
+            commands.entity(old_skeleton.entity).despawn_recursive();
+
+            let Some(scene) = spawn_scene(
+                &part_to_change.new_part,
+                &default_visuals.skeleton,
+                &char_collection,
+                &gltfs,
+                &mut commands,
+            ) else {
+                continue;
+            };
+            info!("Swapped skeleton of client {}", client_id);
+            commands
+                .entity(scene)
+                .insert(SkeletonScene(part_to_change.new_part.clone()))
+                .set_parent(player);
+            skeleton_map.0.insert(
+                client_id,
+                SpawnedSkeleton {
+                    entity: scene,
+                    file_path: part_to_change.new_part.clone(),
+                },
+            );
+
+            // Every part needs to follow the bones of the new skeleton, and weapon was in the old hand
+            for entity in body_part.find_entities_by_client_id(&client_id) {
+                commands.entity(entity).remove::<HasTarget>();
+            }
+            commands.entity(player).remove::<AttachedWeapon>();
+            transfer_anim.send(TranferAnim(client_id));
+        } else if let Some(old_body_part) = body_part
+            .0
+            .remove(&(client_id, part_to_change.old_part.clone()))
+        {
             info!("Found old body part in map removing it");
             let player = parent
                 .get(old_body_part)
@@ -287,15 +447,36 @@ fn customizes_character(
             commands.entity(old_body_part).despawn_recursive();
 
             if let Some(ref char_collection) = client_collection {
-                let scene = spawn_scene(
+                // Falls back to default part of the same slot as the old one
+                let fallback = catalog
+                    .find(&part_to_change.old_part)
+                    .map(|part| default_visuals.part(part.slot))
+                    .unwrap_or(&part_to_change.old_part);
+                let Some(scene) = spawn_scene(
                     &part_to_change.new_part,
+                    fallback,
                     &char_collection,
                     &gltfs,
                     &mut commands,
-                );
+                ) else {
+                    continue;
+                };
                 info!("Setting father of new part to player");
                 commands.entity(scene).set_parent(player);
 
+                // New part keeps the colors the old one had
+                let tint = catalog
+                    .find(&part_to_change.new_part)
+                    .and_then(|part| tint_map.0.get(&(client_id, part.slot)));
+                if let Some(tint) = tint {
+                    if *tint != PartTint::default() {
+                        commands.entity(scene).insert(PendingTint {
+                            part: part_to_change.new_part.clone(),
+                            tint: *tint,
+                        });
+                    }
+                }
+
                 info!("Inserting in resource");
                 body_part
                     .0
@@ -313,13 +494,16 @@ fn customizes_character(
 }
 
 /// Transfer the animations targets to all the visual bones
+/// Part bones follow the skeleton bone with the same name, unless skeleton bone map says otherwise
 fn transfer_essential_components(
-    mut body_part_map: ResMut<BodyPartMap>,
+    body_part_map: Res<BodyPartMap>,
     animation_target: Query<&AnimationTarget>,
     children_entities: Query<&Children>,
     names: Query<&Name>,
+    skinned_meshes: Query<&SkinnedMesh>,
     has_transfered: Query<&HasTarget>,
     skeleton_map: Res<SkeletonMap>,
+    catalog: Res<PartCatalog>,
     mut read_transfer_anim: EventReader<TranferAnim>,
     mut reset_anim: EventWriter<ResetAnimation>,
     mut commands: Commands,
@@ -327,65 +511,83 @@ fn transfer_essential_components(
     for event in read_transfer_anim.read() {
         info!("Lets transfer animations");
         let client_id = event.0;
-        if let Some(skeleton) = skeleton_map.0.get(&client_id) {
-            info!("Grabbing skeleton corresponding to that client_id");
-            let old_entity =
-                find_child_with_name_containing(&children_entities, &names, &skeleton, "Armature")
-                    .expect("Skeleton to have root armature");
-
-            let mut old_bones = HashMap::new();
-            collect_bones(&children_entities, &names, &old_entity, &mut old_bones);
-
-            info!("Grabbing bones in visuals entity and making animation targets for them according to old bones ids");
-
-            // Meh i am lazy not gonna filter out entities
-            for ((_, _), body_part) in body_part_map.0.iter_mut() {
-                if let Ok(_) = has_transfered.get(*body_part) {
-                    // info!(
-                    //     "This part is already ready for animation not gonna do it again {}",
-                    //     file_path
-                    // );
-                } else {
-                    // info!(
-                    //     "Transfering components to apply animation to file path {}",
-                    //     file_path
-                    // );
-                    let new_entity = find_child_with_name_containing(
-                        &children_entities,
-                        &names,
-                        &body_part,
-                        "Armature",
-                    )
-                    .expect("Visual to have root armature");
-
-                    let mut new_bones = HashMap::new();
-                    collect_bones(&children_entities, &names, &new_entity, &mut new_bones);
+        let Some(skeleton) = skeleton_map.0.get(&client_id) else {
+            error!("The base skeleton of this {} doesnt exit", client_id);
+            continue;
+        };
+        let Some(skeleton_part) = catalog.find(&skeleton.file_path) else {
+            error!("Skeleton {} is not in part catalog", skeleton.file_path);
+            continue;
+        };
+
+        info!("Grabbing skeleton corresponding to that client_id");
+        let Some(old_entity) = find_child_with_name_containing(
+            &children_entities,
+            &names,
+            &skeleton.entity,
+            "Armature",
+        ) else {
+            error!("Skeleton {} has no root armature", skeleton.file_path);
+            continue;
+        };
+
+        let mut old_bones = HashMap::new();
+        collect_bones(&children_entities, &names, &old_entity, &mut old_bones);
+
+        info!("Grabbing bones in visuals entity and making animation targets for them according to old bones ids");
+
+        for ((client, file_path), body_part) in body_part_map.0.iter() {
+            if *client != client_id || has_transfered.contains(*body_part) {
+                continue;
+            }
 
-                    commands
-                        .entity(new_entity)
-                        .insert(AnimationPlayer::default());
-
-                    for (name, old_bone) in old_bones.iter() {
-                        let old_animation_target = animation_target
-                            .get(*old_bone)
-                            .expect("To have target if it doesnt well shit");
-
-                        if let Some(corresponding_bone) = new_bones.get(name) {
-                            commands
-                                .entity(*corresponding_bone)
-                                .insert(AnimationTarget {
-                                    id: old_animation_target.id,
-                                    player: new_entity,
-                                });
-                        }
-                    }
-                    commands.entity(*body_part).insert(HasTarget);
+            let Some(new_entity) =
+                find_child_with_name_containing(&children_entities, &names, &body_part, "Armature")
+            else {
+                error!("Part {} has no root armature cant animate it", file_path);
+                continue;
+            };
+
+            let mut new_bones = HashMap::new();
+            collect_bones(&children_entities, &names, &new_entity, &mut new_bones);
+
+            commands
+                .entity(new_entity)
+                .insert(AnimationPlayer::default());
+
+            for (name, new_bone) in new_bones.iter() {
+                let Some(old_bone) = old_bones.get(skeleton_part.skeleton_bone(name)) else {
+                    continue;
+                };
+                if let Ok(old_animation_target) = animation_target.get(*old_bone) {
+                    commands.entity(*new_bone).insert(AnimationTarget {
+                        id: old_animation_target.id,
+                        player: new_entity,
+                    });
                 }
             }
-            reset_anim.send(ResetAnimation(client_id));
-        } else {
-            error!("The base skeleton of this {} doesnt exit", client_id);
+
+            // Only joints of skinned meshes are real bones, armature also has mesh nodes in it
+            let mut unmapped_bones: Vec<String> = children_entities
+                .iter_descendants(*body_part)
+                .filter_map(|entity| skinned_meshes.get(entity).ok())
+                .flat_map(|skinned_mesh| skinned_mesh.joints.iter())
+                .filter_map(|joint| names.get(*joint).ok())
+                .map(|name| name.to_string())
+                .filter(|name| !old_bones.contains_key(skeleton_part.skeleton_bone(name)))
+                .collect();
+            unmapped_bones.sort();
+            unmapped_bones.dedup();
+            if !unmapped_bones.is_empty() {
+                warn!(
+                    "Part {} has bones that dont map to skeleton {}, they wont animate: {:?}",
+                    file_path, skeleton.file_path, unmapped_bones
+                );
+            }
+
+            commands.entity(*body_part).insert(HasTarget);
         }
+        reset_anim.send(ResetAnimation(client_id));
     }
 }
 
@@ -402,12 +604,9 @@ fn reset_animation(
         info!("Reseting animation for client {}", client_id);
         // Iter through scenes that are child of that player find their child recursively that has anim player reset it
         for entity in body_part_map.find_entities_by_client_id(&client_id) {
-            if let Some(entity_with_anim_player) = find_child_with_name_containing(
-                &children_entities,
-                &names,
-                &entity,
-                "CharacterArmature",
-            ) {
+            if let Some(entity_with_anim_player) =
+                find_child_with_name_containing(&children_entities, &names, &entity, "Armature")
+            {
                 if let Ok(mut anim_player) = animation_players.get_mut(entity_with_anim_player) {
                     anim_player.rewind_all();
                 } else {
@@ -420,3 +619,215 @@ fn reset_animation(
         }
     }
 }
+
+/// Parents the equipped weapon scene to the skeleton hand bone, swaps it whenever equipped slot changes
+fn attach_weapon(
+    players: Query<
+        (
+            Entity,
+            &PlayerId,
+            &PlayerVisuals,
+            &EquippedWeapon,
+            Option<&AttachedWeapon>,
+        ),
+        (Or<(With<Predicted>, With<Interpolated>)>, With<HasVisuals>),
+    >,
+    skeleton_map: Res<SkeletonMap>,
+    children_entities: Query<&Children>,
+    names: Query<&Name>,
+    client_collection: Option<Res<CharCollection>>,
+    gltfs: Res<Assets<Gltf>>,
+    mut commands: Commands,
+) {
+    let Some(client_collection) = client_collection else {
+        return;
+    };
+    let default_visuals = PlayerVisuals::default();
+
+    for (entity, player_id, player_visuals, equipped_weapon, attached_weapon) in players.iter() {
+        if let Some(attached_weapon) = attached_weapon {
+            if attached_weapon.slot == equipped_weapon.0 {
+                continue;
+            }
+        }
+
+        let Some(skeleton) = skeleton_map.0.get(&player_id.0) else {
+            continue;
+        };
+        // Skeleton scene might not have spawned it is bones yet, so we just try again next frame
+        let Some(hand_bone) = find_child_with_name_containing(
+            &children_entities,
+            &names,
+            &skeleton.entity,
+            WEAPON_HAND_BONE,
+        ) else {
+            continue;
+        };
+
+        if let Some(old_weapon) = attached_weapon.and_then(|attached_weapon| attached_weapon.entity)
+        {
+            commands.entity(old_weapon).despawn_recursive();
+        }
+
+        info!(
+            "Attaching weapon slot {} to player {}",
+            equipped_weapon.0, player_id.0
+        );
+        let weapon = spawn_scene(
+            player_visuals.weapon(equipped_weapon.0),
+            default_visuals.weapon(0),
+            &client_collection,
+            &gltfs,
+            &mut commands,
+        );
+        if let Some(weapon) = weapon {
+            // Weapon sits exactly where the bone is
+            commands
+                .entity(weapon)
+                .insert(Transform::IDENTITY)
+                .set_parent(hand_bone);
+        }
+        commands.entity(entity).insert(AttachedWeapon {
+            slot: equipped_weapon.0,
+            entity: weapon,
+        });
+    }
+}
+
+/// Turns server tint messages into tint changes
+fn listener_change_tint(
+    mut change_tint: EventReader<MessageEvent<ChangeTint>>,
+    mut tint_changed: EventWriter<TintChanged>,
+) {
+    for event in change_tint.read() {
+        let (client_id, slot, tint) = event.message().0;
+        tint_changed.send(TintChanged {
+            client_id,
+            slot,
+            tint,
+        });
+    }
+}
+
+/// Server rejected what we were previewing, put back the tints it last accepted for us
+fn revert_rejected_preview(
+    mut reject_visual: EventReader<MessageEvent<RejectVisual>>,
+    client_id: Option<Res<EasyClient>>,
+    player_bundle_map: Res<SavePlayerBundleMap>,
+    mut tint_changed: EventWriter<TintChanged>,
+) {
+    if reject_visual.read().count() == 0 {
+        return;
+    }
+    let Some(client_id) = client_id else {
+        return;
+    };
+    let Some(player_bundle) = player_bundle_map.0.get(&client_id.0) else {
+        return;
+    };
+    info!("Reverting preview to visuals server accepted");
+    for slot in [PartSlot::Head, PartSlot::Torso, PartSlot::Legs] {
+        tint_changed.send(TintChanged {
+            client_id: client_id.0,
+            slot,
+            tint: player_bundle.visuals.tint(slot),
+        });
+    }
+}
+
+/// Finds the part scene of the changed slot and marks it to be tinted
+fn customizes_tint(
+    mut tint_changed: EventReader<TintChanged>,
+    body_part_map: Res<BodyPartMap>,
+    catalog: Res<PartCatalog>,
+    mut tint_map: ResMut<TintMap>,
+    mut commands: Commands,
+) {
+    for event in tint_changed.read() {
+        info!(
+            "Changing {:?} tint of client {}",
+            event.slot, event.client_id
+        );
+        tint_map.0.insert((event.client_id, event.slot), event.tint);
+
+        for ((client_id, file_path), scene) in body_part_map.0.iter() {
+            if *client_id != event.client_id {
+                continue;
+            }
+            let is_same_slot = catalog
+                .find(file_path)
+                .is_some_and(|part| part.slot == event.slot);
+            if is_same_slot {
+                commands.entity(*scene).insert(PendingTint {
+                    part: file_path.clone(),
+                    tint: event.tint,
+                });
+            }
+        }
+    }
+}
+
+/// Gives each mesh of a tinted part it is own copy of the material, as materials are shared among every player using that part
+fn apply_pending_tints(
+    pending_tints: Query<(Entity, &PendingTint)>,
+    children_entities: Query<&Children>,
+    mesh_materials: Query<(&Handle<StandardMaterial>, Option<&OriginalMaterial>)>,
+    client_collection: Option<Res<CharCollection>>,
+    gltfs: Res<Assets<Gltf>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands,
+) {
+    for (scene, pending_tint) in pending_tints.iter() {
+        let meshes: Vec<Entity> = children_entities
+            .iter_descendants(scene)
+            .filter(|entity| mesh_materials.contains(*entity))
+            .collect();
+        // Scene didnt spawn yet try again next frame
+        if meshes.is_empty() {
+            continue;
+        }
+
+        let primary_material = client_collection
+            .as_ref()
+            .and_then(|collection| collection.gltf_files.get(&pending_tint.part))
+            .and_then(|gltf| gltfs.get(gltf))
+            .and_then(|gltf| gltf.materials.first().cloned());
+
+        let tint = pending_tint.tint;
+        for mesh in meshes {
+            let Ok((current_material, original_material)) = mesh_materials.get(mesh) else {
+                continue;
+            };
+            let original_material = original_material
+                .map(|original_material| original_material.0.clone())
+                .unwrap_or(current_material.clone());
+
+            if tint == PartTint::default() {
+                commands
+                    .entity(mesh)
+                    .insert(original_material)
+                    .remove::<OriginalMaterial>();
+                continue;
+            }
+
+            let Some(mut tinted_material) = materials.get(&original_material).cloned() else {
+                continue;
+            };
+            let color = if primary_material.as_ref() == Some(&original_material) {
+                tint.primary
+            } else {
+                tint.secondary
+            };
+            tinted_material.base_color = Color::LinearRgba(LinearRgba::from_vec4(
+                tinted_material.base_color.to_linear().to_vec4() * color.to_linear().to_vec4(),
+            ));
+            tinted_material.emissive = tinted_material.emissive + tint.emissive.to_linear();
+
+            commands
+                .entity(mesh)
+                .insert(materials.add(tinted_material))
+                .insert(OriginalMaterial(original_material));
+        }
+        commands.entity(scene).remove::<PendingTint>();
+    }
+}
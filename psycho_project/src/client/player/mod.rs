@@ -4,15 +4,17 @@ use bevy::prelude::*;
 use bevy::render::{mesh::skinning::SkinnedMesh, view::NoFrustumCulling};
 use bevy::utils::HashMap;
 use gun::PlayerGunPlugin;
-use lightyear::prelude::client::Predicted;
+use lightyear::prelude::client::{Interpolated, Predicted};
 use physics::PlayerPhysicsPlugin;
+use ragdoll::RagdollPlugin;
 
 mod animations;
-mod camera;
-mod char_customizer;
+pub mod camera;
+pub mod char_customizer;
 mod client_replicated;
 mod gun;
 mod physics;
+mod ragdoll;
 
 use lightyear::connection::id::ClientId;
 
@@ -32,6 +34,7 @@ impl Plugin for CreateCharPlugin {
         app.add_plugins(AnimPlayerPlugin);
         app.add_plugins(PlayerPhysicsPlugin);
         app.add_plugins(PlayerGunPlugin);
+        app.add_plugins(RagdollPlugin);
 
         app.add_systems(Update, fill_player_map);
         // Debugging RTT
@@ -44,7 +47,13 @@ impl Plugin for CreateCharPlugin {
 pub struct ClientPlayerEntityMap(pub HashMap<ClientId, Entity>);
 
 fn fill_player_map(
-    player_entities: Query<(Entity, &PlayerId), (With<MarkerPlayer>, Added<Predicted>)>,
+    player_entities: Query<
+        (Entity, &PlayerId),
+        (
+            With<MarkerPlayer>,
+            Or<(Added<Predicted>, Added<Interpolated>)>,
+        ),
+    >,
     mut player_map: ResMut<ClientPlayerEntityMap>,
 ) {
     for (entity, player_id) in player_entities.iter() {
@@ -1,4 +1,4 @@
-use super::MarkerMainCamera;
+use super::{CamInfo, MarkerMainCamera};
 use crate::shared::protocol::player_structs::*;
 use crate::shared::shared_physics::*;
 use avian3d::prelude::*;
@@ -103,27 +103,43 @@ fn add_physics_to_players(
 // }
 
 /// You see this guy is necessary because to be completely i dont know how to override forward value utilizing only move
+/// Aiming camera modes also get their look direction passed here
 fn player_go_to_camera(
-    q_cam: Query<&Transform, With<MarkerMainCamera>>,
+    q_cam: Query<(&Transform, &CamInfo), With<MarkerMainCamera>>,
     mut q_actionstate: Query<&mut ActionState<PlayerAction>, (With<Predicted>, With<Controlled>)>,
 ) {
     // I wonder if there is a bertter way to do this
     if let Ok(mut action_state) = q_actionstate.get_single_mut() {
-        if let Ok(q_cam) = q_cam.get_single() {
-            // Dont pass a constant direction
-            action_state.set_axis_pair(&PlayerAction::Direction, Vec2::ZERO);
+        if let Ok((q_cam, cam_info)) = q_cam.get_single() {
+            if cam_info.mode.is_aiming() {
+                let (yaw, pitch, _) = q_cam.rotation.to_euler(EulerRot::YXZ);
+                action_state.press(&PlayerAction::Aim);
+                action_state.set_axis_pair(&PlayerAction::RotateToCamera, Vec2::new(pitch, yaw));
+            } else {
+                action_state.release(&PlayerAction::Aim);
+                action_state.set_axis_pair(&PlayerAction::RotateToCamera, Vec2::ZERO);
+            }
+
+            // Stick and buttons add up, so diagonals dont overwrite each other
+            let mut input = action_state.axis_pair(&PlayerAction::Move);
             if action_state.pressed(&PlayerAction::Forward) {
-                action_state.set_axis_pair(&PlayerAction::Direction, q_cam.forward().xz());
+                input.y += 1.0;
             }
             if action_state.pressed(&PlayerAction::Backward) {
-                action_state.set_axis_pair(&PlayerAction::Direction, q_cam.back().xz());
+                input.y -= 1.0;
             }
             if action_state.pressed(&PlayerAction::Left) {
-                action_state.set_axis_pair(&PlayerAction::Direction, q_cam.left().xz());
+                input.x -= 1.0;
             }
             if action_state.pressed(&PlayerAction::Right) {
-                action_state.set_axis_pair(&PlayerAction::Direction, q_cam.right().xz());
+                input.x += 1.0;
             }
+
+            // Camera looks down at player, flatten it is basis so pitch doesnt slow us down
+            let forward = q_cam.forward().xz().normalize_or_zero();
+            let right = q_cam.right().xz().normalize_or_zero();
+            let direction = (forward * input.y + right * input.x).clamp_length_max(1.0);
+            action_state.set_axis_pair(&PlayerAction::Direction, direction);
         }
     }
 }
@@ -137,6 +153,7 @@ fn handle_character_actions(
             &ActionState<PlayerAction>,
             &InputBuffer<PlayerAction>,
             &RayHits,
+            &PlayerHealth,
             CharacterQuery,
         ),
         With<Predicted>,
@@ -150,7 +167,11 @@ fn handle_character_actions(
         .map(|rb| tick_manager.tick_or_rollback_tick(rb))
         .unwrap_or(tick_manager.tick());
 
-    for (action_state, input_buffer, ray_hits, mut character) in &mut query {
+    for (action_state, input_buffer, ray_hits, player_health, mut character) in &mut query {
+        // Dead players dont move, their ragdoll does
+        if player_health.is_dead() {
+            continue;
+        }
         // Use the current character action if it is.
         if input_buffer.get(tick).is_some() {
             apply_character_action(&time, action_state, ray_hits, &mut character);
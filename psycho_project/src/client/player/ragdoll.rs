@@ -0,0 +1,330 @@
+//! Turns dead players into ragdolls, each skeleton bone gets a physics body and body part bones follow them
+use super::char_customizer::{collect_bones, BodyPartMap, SkeletonScene};
+use super::ClientPlayerEntityMap;
+use crate::client::MyAppState;
+use crate::shared::protocol::player_structs::*;
+use crate::shared::shared_parts::PartCatalog;
+use crate::shared::shared_physics::RagdollPhysics;
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy::utils::{HashMap, HashSet};
+use lightyear::client::events::MessageEvent;
+use lightyear::connection::id::ClientId;
+
+/// Bones shorter than this dont get a body, fingers and such just follow their parent
+const MIN_BONE_LENGTH: f32 = 0.08;
+/// How far a bone can swing and twist away from it is parent in radians
+const SWING_LIMIT: f32 = 1.0;
+const TWIST_LIMIT: f32 = 0.5;
+/// Strength of the push the killing bullet gives, split among every body
+const DEATH_IMPULSE: f32 = 6.0;
+
+pub struct RagdollPlugin;
+
+impl Plugin for RagdollPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (spawn_ragdolls, despawn_ragdolls).run_if(in_state(MyAppState::Game)),
+        );
+
+        // Animations keep running underneath, so we override bones right after them
+        app.add_systems(
+            PostUpdate,
+            follow_ragdoll
+                .after(bevy::animation::Animation)
+                .before(TransformSystem::TransformPropagate),
+        );
+
+        app.add_systems(OnExit(MyAppState::Game), despawn_all_ragdolls);
+    }
+}
+
+/// Physics body or joint of a ragdoll, tells me whose ragdoll it is
+#[derive(Component)]
+struct RagdollBody(ClientId);
+
+/// Inserted in skeleton bones that follow a ragdoll body
+#[derive(Component)]
+struct RagdollBone {
+    owner: ClientId,
+    body: Entity,
+    // Body of parent bone, if parent has none we use it is global transform instead
+    parent_body: Option<Entity>,
+    // Bodies have no scale so we keep the one bones had
+    scale: Vec3,
+    parent_scale: Vec3,
+    // Bones in body parts that map to this bone, they copy whatever it does
+    followers: Vec<Entity>,
+}
+
+/// Helper finds root armature of a scene
+fn find_armature(
+    scene: Entity,
+    children_entities: &Query<&Children>,
+    names: &Query<&Name>,
+) -> Option<Entity> {
+    children_entities.iter_descendants(scene).find(|entity| {
+        names
+            .get(*entity)
+            .is_ok_and(|name| name.as_str().contains("Armature"))
+    })
+}
+
+/// Builds a ragdoll from the current animated pose of the dead player skeleton
+fn spawn_ragdolls(
+    mut player_death: EventReader<MessageEvent<PlayerDeath>>,
+    player_map: Res<ClientPlayerEntityMap>,
+    players: Query<&LinearVelocity>,
+    skeletons: Query<(Entity, &Parent, &SkeletonScene)>,
+    body_part_map: Res<BodyPartMap>,
+    catalog: Res<PartCatalog>,
+    children_entities: Query<&Children>,
+    names: Query<&Name>,
+    parents: Query<&Parent>,
+    global_transforms: Query<&GlobalTransform>,
+    ragdolled: Query<&RagdollBone>,
+    mut commands: Commands,
+) {
+    for event in player_death.read() {
+        let death = event.message();
+        info!("Player {} died to {}", death.victim, death.killer);
+
+        let Some(player) = player_map.0.get(&death.victim) else {
+            warn!("Couldnt find dead player {} entity", death.victim);
+            continue;
+        };
+        let Some((skeleton_entity, _, skeleton_scene)) = skeletons
+            .iter()
+            .find(|(_, parent, _)| parent.get() == *player)
+        else {
+            warn!("Dead player {} has no skeleton to ragdoll", death.victim);
+            continue;
+        };
+        let Some(armature) = find_armature(skeleton_entity, &children_entities, &names) else {
+            continue;
+        };
+
+        let mut bones = HashMap::new();
+        collect_bones(&children_entities, &names, &armature, &mut bones);
+        let bone_entities: HashSet<Entity> = bones.values().copied().collect();
+        if bone_entities.iter().any(|bone| ragdolled.contains(*bone)) {
+            continue;
+        }
+
+        // Each bone body goes from the bone to the middle of it is child bones
+        let mut candidates = Vec::new();
+        for (name, bone) in bones.iter() {
+            let Ok(bone_global) = global_transforms.get(*bone) else {
+                continue;
+            };
+            let child_positions: Vec<Vec3> = children_entities
+                .get(*bone)
+                .into_iter()
+                .flatten()
+                .filter(|child| bone_entities.contains(*child))
+                .filter_map(|child| global_transforms.get(*child).ok())
+                .map(|child_global| child_global.translation())
+                .collect();
+            if child_positions.is_empty() {
+                continue;
+            }
+            let bone_transform = bone_global.compute_transform();
+            let end = child_positions.iter().sum::<Vec3>() / child_positions.len() as f32;
+            if bone_transform.translation.distance(end) < MIN_BONE_LENGTH {
+                continue;
+            }
+            candidates.push((name.clone(), *bone, bone_transform, end));
+        }
+
+        let velocity = players
+            .get(*player)
+            .map(|linear_velocity| linear_velocity.0)
+            .unwrap_or_default();
+        let impulse = death.bullet_velocity.normalize_or_zero() * DEATH_IMPULSE
+            / candidates.len().max(1) as f32;
+
+        let mut bodies: HashMap<Entity, Entity> = HashMap::new();
+        let mut bone_transforms: HashMap<Entity, Transform> = HashMap::new();
+        for (name, bone, bone_transform, end) in candidates {
+            let length = bone_transform.translation.distance(end);
+            let radius = (length * 0.25).clamp(0.03, 0.15);
+            let local_end = bone_transform.rotation.inverse() * (end - bone_transform.translation);
+
+            let body = commands
+                .spawn(RagdollPhysics::new(Collider::capsule_endpoints(
+                    radius,
+                    Vec3::ZERO,
+                    local_end,
+                )))
+                .insert(TransformBundle::from_transform(
+                    Transform::from_translation(bone_transform.translation)
+                        .with_rotation(bone_transform.rotation),
+                ))
+                .insert(Position(bone_transform.translation))
+                .insert(Rotation(bone_transform.rotation))
+                .insert(LinearVelocity(velocity))
+                .insert(ExternalImpulse::new(impulse))
+                .insert(RagdollBody(death.victim))
+                .insert(Name::new(format!("Ragdoll {}", name)))
+                .id();
+            bodies.insert(bone, body);
+            bone_transforms.insert(bone, bone_transform);
+        }
+
+        // Each body hangs from the closest ancestor bone that also has one
+        for (bone, body) in bodies.iter() {
+            let mut current = *bone;
+            let ancestor = loop {
+                let Ok(parent) = parents.get(current) else {
+                    break None;
+                };
+                current = parent.get();
+                if bodies.contains_key(&current) {
+                    break Some(current);
+                }
+                if !bone_entities.contains(&current) {
+                    break None;
+                }
+            };
+            let Some(ancestor) = ancestor else {
+                continue;
+            };
+
+            let ancestor_transform = bone_transforms[&ancestor];
+            let bone_transform = bone_transforms[bone];
+            let anchor = ancestor_transform.rotation.inverse()
+                * (bone_transform.translation - ancestor_transform.translation);
+            commands
+                .spawn(
+                    SphericalJoint::new(bodies[&ancestor], *body)
+                        .with_local_anchor_1(anchor)
+                        .with_swing_limits(-SWING_LIMIT, SWING_LIMIT)
+                        .with_twist_limits(-TWIST_LIMIT, TWIST_LIMIT),
+                )
+                .insert(RagdollBody(death.victim));
+        }
+
+        // Body parts have their own bones, they follow the skeleton bone they map to
+        let skeleton_part = catalog.find(&skeleton_scene.0);
+        let mut followers: HashMap<Entity, Vec<Entity>> = HashMap::new();
+        for part_scene in body_part_map.find_entities_by_client_id(&death.victim) {
+            let Some(part_armature) = find_armature(part_scene, &children_entities, &names) else {
+                continue;
+            };
+            let mut part_bones = HashMap::new();
+            collect_bones(&children_entities, &names, &part_armature, &mut part_bones);
+            for (name, part_bone) in part_bones {
+                let skeleton_bone_name = skeleton_part
+                    .map(|skeleton_part| skeleton_part.skeleton_bone(&name))
+                    .unwrap_or(&name);
+                if let Some(skeleton_bone) = bones.get(skeleton_bone_name) {
+                    if bodies.contains_key(skeleton_bone) {
+                        followers.entry(*skeleton_bone).or_default().push(part_bone);
+                    }
+                }
+            }
+        }
+
+        info!(
+            "Ragdolling player {} with {} bodies",
+            death.victim,
+            bodies.len()
+        );
+        for (bone, body) in bodies.iter() {
+            let direct_parent = parents.get(*bone).map(|parent| parent.get()).ok();
+            let parent_scale = direct_parent
+                .and_then(|parent| global_transforms.get(parent).ok())
+                .map(|parent_global| parent_global.compute_transform().scale)
+                .unwrap_or(Vec3::ONE);
+            commands.entity(*bone).insert(RagdollBone {
+                owner: death.victim,
+                body: *body,
+                parent_body: direct_parent.and_then(|parent| bodies.get(&parent).copied()),
+                scale: bone_transforms[bone].scale,
+                parent_scale,
+                followers: followers.remove(bone).unwrap_or_default(),
+            });
+        }
+    }
+}
+
+/// Sets local transform of ragdolled bones so their global one matches their body
+fn follow_ragdoll(
+    ragdoll_bones: Query<(Entity, &RagdollBone, &Parent)>,
+    bodies: Query<(&Position, &Rotation)>,
+    global_transforms: Query<&GlobalTransform>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for (bone, ragdoll_bone, parent) in ragdoll_bones.iter() {
+        let Ok((position, rotation)) = bodies.get(ragdoll_bone.body) else {
+            continue;
+        };
+        let global = Transform {
+            translation: position.0,
+            rotation: rotation.0,
+            scale: ragdoll_bone.scale,
+        }
+        .compute_affine();
+
+        let parent_global = match ragdoll_bone
+            .parent_body
+            .and_then(|parent_body| bodies.get(parent_body).ok())
+        {
+            Some((parent_position, parent_rotation)) => Transform {
+                translation: parent_position.0,
+                rotation: parent_rotation.0,
+                scale: ragdoll_bone.parent_scale,
+            }
+            .compute_affine(),
+            None => match global_transforms.get(parent.get()) {
+                Ok(parent_global) => parent_global.affine(),
+                Err(_) => continue,
+            },
+        };
+
+        let local = Transform::from_matrix(Mat4::from(parent_global.inverse() * global));
+        for entity in std::iter::once(bone).chain(ragdoll_bone.followers.iter().copied()) {
+            if let Ok(mut transform) = transforms.get_mut(entity) {
+                *transform = local;
+            }
+        }
+    }
+}
+
+/// Respawned players get their bones back to animations
+fn despawn_ragdolls(
+    mut player_respawn: EventReader<MessageEvent<PlayerRespawn>>,
+    ragdoll_bodies: Query<(Entity, &RagdollBody)>,
+    ragdoll_bones: Query<(Entity, &RagdollBone)>,
+    mut commands: Commands,
+) {
+    for event in player_respawn.read() {
+        let client_id = event.message().0;
+        info!("Player {} respawned removing his ragdoll", client_id);
+        for (entity, ragdoll_body) in ragdoll_bodies.iter() {
+            if ragdoll_body.0 == client_id {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        for (bone, ragdoll_bone) in ragdoll_bones.iter() {
+            if ragdoll_bone.owner == client_id {
+                commands.entity(bone).remove::<RagdollBone>();
+            }
+        }
+    }
+}
+
+fn despawn_all_ragdolls(
+    ragdoll_bodies: Query<Entity, With<RagdollBody>>,
+    ragdoll_bones: Query<Entity, With<RagdollBone>>,
+    mut commands: Commands,
+) {
+    for entity in ragdoll_bodies.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for bone in ragdoll_bones.iter() {
+        commands.entity(bone).remove::<RagdollBone>();
+    }
+}
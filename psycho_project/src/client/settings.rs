@@ -0,0 +1,166 @@
+//! Client settings like window, camera and audio, saved in a local config file and applied on startup
+use crate::client::config_file::{load_config, save_config};
+use crate::client::player::camera::{CamInfo, MarkerMainCamera};
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow, WindowMode};
+use bevy::winit::WinitWindows;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+
+/// Where our settings are saved
+const SETTINGS_PATH: &str = "./psycho_project/config/settings.ron";
+
+/// Used when the monitor doesnt tell us what it supports
+const FALLBACK_RESOLUTIONS: [UVec2; 4] = [
+    UVec2::new(2560, 1440),
+    UVec2::new(1920, 1080),
+    UVec2::new(800, 600),
+    UVec2::new(640, 360),
+];
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Settings::load());
+        app.init_resource::<AvailableResolutions>();
+        app.add_systems(
+            Update,
+            (
+                detect_resolutions,
+                apply_window_settings,
+                apply_camera_settings,
+                apply_audio_settings,
+            ),
+        );
+    }
+}
+
+/// Current settings of this client, missing fields in the file get their default
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    // Physical pixels
+    pub resolution: UVec2,
+    pub window_mode: WindowMode,
+    pub vsync: bool,
+    pub mouse_sensitivity: f32,
+    // Degrees, fov of free orbit camera
+    pub fov: f32,
+    // Goes into global volume, music and effects volumes come back once the game has sounds of that kind
+    pub master_volume: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            resolution: UVec2::new(800, 600),
+            window_mode: WindowMode::Windowed,
+            vsync: true,
+            mouse_sensitivity: 0.75,
+            fov: 45.0,
+            master_volume: 1.0,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads saved settings, broken file falls back to defaults
+    pub fn load() -> Self {
+        load_config(SETTINGS_PATH, "settings")
+    }
+
+    pub fn save(&self) {
+        save_config(SETTINGS_PATH, "settings", self);
+    }
+}
+
+/// Resolutions the monitor supports, biggest first
+#[derive(Resource, Default)]
+pub struct AvailableResolutions(Vec<UVec2>);
+
+impl AvailableResolutions {
+    pub fn list(&self) -> &[UVec2] {
+        if self.0.is_empty() {
+            &FALLBACK_RESOLUTIONS
+        } else {
+            &self.0
+        }
+    }
+}
+
+/// Asks winit what our monitor supports, keeps trying until the window has one
+fn detect_resolutions(
+    mut available: ResMut<AvailableResolutions>,
+    windows: Query<Entity, With<PrimaryWindow>>,
+    winit_windows: NonSend<WinitWindows>,
+) {
+    if !available.0.is_empty() {
+        return;
+    }
+    let Ok(entity) = windows.get_single() else {
+        return;
+    };
+    let Some(monitor) = winit_windows
+        .get_window(entity)
+        .and_then(|window| window.current_monitor())
+    else {
+        return;
+    };
+
+    let mut resolutions: Vec<UVec2> = monitor
+        .video_modes()
+        .map(|mode| UVec2::new(mode.size().width, mode.size().height))
+        .collect();
+    resolutions.sort_by_key(|resolution| Reverse((resolution.x, resolution.y)));
+    resolutions.dedup();
+    if resolutions.is_empty() {
+        warn!("Monitor didnt report any resolution using fallback ones");
+        resolutions = FALLBACK_RESOLUTIONS.to_vec();
+    }
+    info!("Monitor supports {} resolutions", resolutions.len());
+    available.0 = resolutions;
+}
+
+fn apply_window_settings(
+    settings: Res<Settings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+
+    window
+        .resolution
+        .set_physical_resolution(settings.resolution.x, settings.resolution.y);
+    window.mode = settings.window_mode;
+    window.present_mode = if settings.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+}
+
+/// Camera spawns with literals so we overwrite them once it shows up and whenever settings change
+fn apply_camera_settings(
+    settings: Res<Settings>,
+    mut cam_q: Query<&mut CamInfo, With<MarkerMainCamera>>,
+) {
+    for mut cam_info in cam_q.iter_mut() {
+        if settings.is_changed() || cam_info.is_added() {
+            cam_info.mouse_sens = settings.mouse_sensitivity;
+            cam_info.fov = settings.fov.to_radians();
+        }
+    }
+}
+
+/// Only sounds that start after this get the new volume
+fn apply_audio_settings(settings: Res<Settings>, mut global_volume: ResMut<GlobalVolume>) {
+    if settings.is_changed() {
+        global_volume.volume = Volume::new(settings.master_volume);
+    }
+}
@@ -0,0 +1,171 @@
+//! Spectators watch a lobby without playing, camera either follows one of the interpolated players or flies freely
+use crate::client::player::camera::{CameraTarget, MarkerMainCamera};
+use crate::client::MyAppState;
+use crate::shared::protocol::lobby_structs::StartGame;
+use crate::shared::protocol::player_structs::*;
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use lightyear::client::events::MessageEvent;
+use lightyear::connection::id::ClientId;
+use lightyear::prelude::client::Interpolated;
+
+/// How fast free fly camera moves, holding shift multiplies it
+const FLY_SPEED: f32 = 10.0;
+const FLY_BOOST: f32 = 3.0;
+
+pub struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, listener_start_spectating);
+        app.add_systems(Update, add_transform_to_interpolated_players);
+        app.add_systems(
+            Update,
+            (cycle_spectate_target, free_fly)
+                .chain()
+                .run_if(in_state(MyAppState::Game))
+                .run_if(resource_exists::<Spectating>),
+        );
+
+        // Camera reads the transform of who it follows right after physics sync
+        app.add_systems(
+            PostUpdate,
+            sync_interpolated_players.before(PhysicsSet::Sync),
+        );
+    }
+}
+
+/// Only exists while I am spectating, tells me who I am following. None means free fly
+#[derive(Resource, Default, Debug)]
+pub struct Spectating {
+    pub following: Option<ClientId>,
+}
+
+/// Server tells us if we joined as player or spectator
+fn listener_start_spectating(
+    mut events: EventReader<MessageEvent<StartGame>>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        if event.message().spectator {
+            info!("Spectating lobby {}", event.message().lobby_id);
+            commands.insert_resource(Spectating::default());
+        } else {
+            commands.remove_resource::<Spectating>();
+        }
+    }
+}
+
+/// Interpolated players have no rigid body, so they need a transform of their own
+fn add_transform_to_interpolated_players(
+    players: Query<(Entity, &Position, &Rotation), (Added<Interpolated>, With<MarkerPlayer>)>,
+    mut commands: Commands,
+) {
+    for (entity, position, rotation) in players.iter() {
+        commands
+            .entity(entity)
+            .insert(Transform::from_translation(position.0).with_rotation(rotation.0));
+    }
+}
+
+fn sync_interpolated_players(
+    mut players: Query<
+        (&Position, &Rotation, &mut Transform),
+        (With<Interpolated>, With<MarkerPlayer>),
+    >,
+) {
+    for (position, rotation, mut transform) in players.iter_mut() {
+        transform.translation = position.0;
+        transform.rotation = rotation.0;
+    }
+}
+
+/// Tab goes to next player, F goes back to free fly
+fn cycle_spectate_target(
+    keys: Res<ButtonInput<KeyCode>>,
+    players: Query<
+        (Entity, &PlayerId, Has<CameraTarget>),
+        (With<Interpolated>, With<MarkerPlayer>),
+    >,
+    mut spectating: ResMut<Spectating>,
+    mut commands: Commands,
+) {
+    let mut player_ids: Vec<ClientId> = players
+        .iter()
+        .map(|(_, player_id, _)| player_id.0)
+        .collect();
+    player_ids.sort_by_key(|client_id| client_id.to_bits());
+
+    // Whoever we followed might have left
+    if let Some(following) = spectating.following {
+        if !player_ids.contains(&following) {
+            info!("Player {} is gone, going back to free fly", following);
+            spectating.following = None;
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Tab) && !player_ids.is_empty() {
+        let next_index = spectating
+            .following
+            .and_then(|following| player_ids.iter().position(|id| *id == following))
+            .map(|index| (index + 1) % player_ids.len())
+            .unwrap_or(0);
+        spectating.following = Some(player_ids[next_index]);
+        info!("Following player {}", player_ids[next_index]);
+    }
+    if keys.just_pressed(KeyCode::KeyF) && spectating.following.is_some() {
+        info!("Free flying");
+        spectating.following = None;
+    }
+
+    // Camera target goes only in the one we follow
+    for (entity, player_id, is_target) in players.iter() {
+        let should_target = spectating.following == Some(player_id.0);
+        if should_target && !is_target {
+            commands.entity(entity).insert(CameraTarget);
+        } else if !should_target && is_target {
+            commands.entity(entity).remove::<CameraTarget>();
+        }
+    }
+}
+
+/// Moves camera around with WASD, space goes up and control goes down
+fn free_fly(
+    keys: Res<ButtonInput<KeyCode>>,
+    spectating: Res<Spectating>,
+    mut cam_q: Query<&mut Transform, With<MarkerMainCamera>>,
+    time: Res<Time>,
+) {
+    if spectating.following.is_some() {
+        return;
+    }
+    let Ok(mut cam_transform) = cam_q.get_single_mut() else {
+        return;
+    };
+
+    let mut direction = Vec3::ZERO;
+    if keys.pressed(KeyCode::KeyW) {
+        direction += *cam_transform.forward();
+    }
+    if keys.pressed(KeyCode::KeyS) {
+        direction += *cam_transform.back();
+    }
+    if keys.pressed(KeyCode::KeyA) {
+        direction += *cam_transform.left();
+    }
+    if keys.pressed(KeyCode::KeyD) {
+        direction += *cam_transform.right();
+    }
+    if keys.pressed(KeyCode::Space) {
+        direction += Vec3::Y;
+    }
+    if keys.pressed(KeyCode::ControlLeft) {
+        direction -= Vec3::Y;
+    }
+
+    let mut speed = FLY_SPEED;
+    if keys.pressed(KeyCode::ShiftLeft) {
+        speed *= FLY_BOOST;
+    }
+    cam_transform.translation += direction.normalize_or_zero() * speed * time.delta_seconds();
+}
@@ -0,0 +1,350 @@
+//! Screen where player rebinds his controls, changes only apply and get saved when he hits save
+use crate::client::controls::{Binding, ControlAction, Controls, GamepadSettings};
+use crate::client::ui::main_screen::ScreenMainMenu;
+use crate::client::ui::settings_rows::{SettingsStyle, TEXT_COLOR};
+use crate::client::MyAppState;
+use bevy::prelude::*;
+
+const CONFLICT_COLOR: Color = Color::srgb(0.9, 0.2, 0.2);
+
+pub struct ControlsScreenPlugin;
+
+impl Plugin for ControlsScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(MyAppState::Controls),
+            (despawn_main_menu, controls_screen),
+        );
+        app.add_systems(OnExit(MyAppState::Controls), despawn_controls_screen);
+        app.add_systems(
+            Update,
+            (
+                binding_buttons,
+                capture_rebind,
+                gamepad_setting_buttons,
+                controls_menu_buttons,
+                update_binding_texts,
+                update_gamepad_texts,
+            )
+                .chain()
+                .run_if(in_state(MyAppState::Controls)),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct ScreenControls;
+
+// Button that starts rebinding an action
+#[derive(Component)]
+struct BindingButton(ControlAction);
+
+// Text that displays the current binding of an action
+#[derive(Component)]
+struct BindingText(ControlAction);
+
+// Text that warns about conflicts
+#[derive(Component)]
+struct ControlsMessage;
+
+// Button that moves a gamepad setting up or down
+#[derive(Component)]
+struct GamepadSettingButton(GamepadSetting, f32);
+
+// Text that displays the value of a gamepad setting
+#[derive(Component)]
+struct GamepadSettingText(GamepadSetting);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GamepadSetting {
+    Sensitivity,
+    Deadzone,
+    InvertY,
+}
+
+impl GamepadSetting {
+    const ALL: [Self; 3] = [Self::Sensitivity, Self::Deadzone, Self::InvertY];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Sensitivity => "Stick look speed",
+            Self::Deadzone => "Stick deadzone",
+            Self::InvertY => "Invert look",
+        }
+    }
+
+    fn value(&self, settings: &GamepadSettings) -> String {
+        match self {
+            Self::Sensitivity => format!("{:.1}", settings.look_sensitivity),
+            Self::Deadzone => format!("{:.2}", settings.deadzone),
+            Self::InvertY => if settings.invert_y { "Yes" } else { "No" }.to_string(),
+        }
+    }
+
+    /// Helper moves setting a step in the given direction, invert just toggles
+    fn step(&self, settings: &mut GamepadSettings, direction: f32) {
+        match self {
+            Self::Sensitivity => {
+                settings.look_sensitivity =
+                    (settings.look_sensitivity + 0.1 * direction).clamp(0.1, 3.0);
+            }
+            Self::Deadzone => {
+                settings.deadzone = (settings.deadzone + 0.05 * direction).clamp(0.0, 0.5);
+            }
+            Self::InvertY => settings.invert_y = !settings.invert_y,
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+enum ControlsMenuButton {
+    Save,
+    Reset,
+    Back,
+}
+
+/// Keybinds being edited, only become the real ones on save
+#[derive(Resource)]
+struct EditingControls(Controls);
+
+/// Exists while we wait for the player to press what he wants for an action
+#[derive(Resource)]
+struct Rebinding {
+    action: ControlAction,
+    // The click that started rebinding must be released first, else it would bind itself
+    waiting_release: bool,
+}
+
+fn despawn_main_menu(main_menu: Query<Entity, With<ScreenMainMenu>>, mut commands: Commands) {
+    for screen in main_menu.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+}
+
+fn controls_screen(
+    controls: Res<Controls>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    commands.insert_resource(EditingControls(controls.clone()));
+
+    let style = SettingsStyle::new(&asset_server);
+    style.spawn_screen(&mut commands, "CONTROLS", ScreenControls, |parent| {
+        for action in ControlAction::ALL {
+            style.spawn_row(parent, &action.label(), |parent| {
+                parent
+                    .spawn((
+                        style.button_bundle(style.button.clone()),
+                        BindingButton(action),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section("", style.text.clone()),
+                            BindingText(action),
+                        ));
+                    });
+            });
+        }
+
+        for setting in GamepadSetting::ALL {
+            style.spawn_stepper_row(
+                parent,
+                setting.label(),
+                100.0,
+                |direction| GamepadSettingButton(setting, direction),
+                GamepadSettingText(setting),
+            );
+        }
+
+        parent.spawn((
+            TextBundle::from_section("", style.text.clone()),
+            ControlsMessage,
+        ));
+
+        style.spawn_menu_buttons(
+            parent,
+            [
+                ("SAVE", ControlsMenuButton::Save),
+                ("DEFAULTS", ControlsMenuButton::Reset),
+                ("BACK", ControlsMenuButton::Back),
+            ],
+        );
+    });
+}
+
+fn despawn_controls_screen(screen: Query<Entity, With<ScreenControls>>, mut commands: Commands) {
+    for screen in screen.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+    commands.remove_resource::<EditingControls>();
+    commands.remove_resource::<Rebinding>();
+}
+
+/// Clicking a binding waits for the next key or mouse button
+fn binding_buttons(
+    interaction_query: Query<(&Interaction, &BindingButton), Changed<Interaction>>,
+    mut commands: Commands,
+) {
+    for (interaction, binding_button) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            commands.insert_resource(Rebinding {
+                action: binding_button.0,
+                waiting_release: true,
+            });
+        }
+    }
+}
+
+/// Grabs whatever gets pressed for the action being rebound, escape cancels it
+fn capture_rebind(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    rebinding: Option<ResMut<Rebinding>>,
+    mut editing: ResMut<EditingControls>,
+    mut commands: Commands,
+) {
+    let Some(mut rebinding) = rebinding else {
+        return;
+    };
+
+    if rebinding.waiting_release {
+        if mouse.get_pressed().next().is_none() {
+            rebinding.waiting_release = false;
+        }
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        commands.remove_resource::<Rebinding>();
+        return;
+    }
+
+    let binding = keys
+        .get_just_pressed()
+        .next()
+        .map(|key| Binding::Key(*key))
+        .or_else(|| {
+            mouse
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::Mouse(*button))
+        });
+    if let Some(binding) = binding {
+        info!(
+            "Binding {} to {}",
+            rebinding.action.label(),
+            binding.label()
+        );
+        editing.0.bindings.insert(rebinding.action, binding);
+        commands.remove_resource::<Rebinding>();
+    }
+}
+
+fn gamepad_setting_buttons(
+    interaction_query: Query<(&Interaction, &GamepadSettingButton), Changed<Interaction>>,
+    mut editing: ResMut<EditingControls>,
+) {
+    for (interaction, setting_button) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            setting_button
+                .0
+                .step(&mut editing.0.gamepad, setting_button.1);
+        }
+    }
+}
+
+fn controls_menu_buttons(
+    interaction_query: Query<(&Interaction, &ControlsMenuButton), Changed<Interaction>>,
+    mut editing: ResMut<EditingControls>,
+    mut controls: ResMut<Controls>,
+    mut message: Query<&mut Text, With<ControlsMessage>>,
+    mut next_state: ResMut<NextState<MyAppState>>,
+) {
+    for (interaction, menu_button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match menu_button {
+            ControlsMenuButton::Save => {
+                if !editing.0.conflicts().is_empty() {
+                    if let Ok(mut text) = message.get_single_mut() {
+                        text.sections[0].value = "Fix the red ones before saving".to_string();
+                    }
+                    continue;
+                }
+                *controls = editing.0.clone();
+                controls.save();
+                next_state.set(MyAppState::MainMenu);
+            }
+            ControlsMenuButton::Reset => {
+                editing.0 = Controls::default();
+            }
+            ControlsMenuButton::Back => {
+                next_state.set(MyAppState::MainMenu);
+            }
+        }
+    }
+}
+
+/// Shows current bindings, the one waiting for input and the ones that conflict
+fn update_binding_texts(
+    editing: Res<EditingControls>,
+    rebinding: Option<Res<Rebinding>>,
+    mut binding_texts: Query<
+        (&BindingText, &mut Text),
+        (Without<ControlsMessage>, Without<GamepadSettingText>),
+    >,
+    mut message: Query<&mut Text, (With<ControlsMessage>, Without<GamepadSettingText>)>,
+    mut was_rebinding: Local<bool>,
+) {
+    let rebinding_changed = rebinding.is_some() != *was_rebinding
+        || rebinding
+            .as_ref()
+            .is_some_and(|rebinding| rebinding.is_changed());
+    *was_rebinding = rebinding.is_some();
+    if !editing.is_changed() && !rebinding_changed {
+        return;
+    }
+
+    let conflicts = editing.0.conflicts();
+    for (binding_text, mut text) in binding_texts.iter_mut() {
+        let action = binding_text.0;
+        text.sections[0].value = if rebinding
+            .as_ref()
+            .is_some_and(|rebinding| rebinding.action == action)
+        {
+            "PRESS ANY KEY".to_string()
+        } else {
+            editing
+                .0
+                .binding(action)
+                .map(|binding| binding.label())
+                .unwrap_or_else(|| "-".to_string())
+        };
+        text.sections[0].style.color = if conflicts.contains(&action) {
+            CONFLICT_COLOR
+        } else {
+            TEXT_COLOR
+        };
+    }
+
+    if let Ok(mut text) = message.get_single_mut() {
+        text.sections[0].value = if conflicts.is_empty() {
+            String::new()
+        } else {
+            "Some actions share the same key".to_string()
+        };
+    }
+}
+
+fn update_gamepad_texts(
+    editing: Res<EditingControls>,
+    mut setting_texts: Query<(&GamepadSettingText, &mut Text)>,
+) {
+    if !editing.is_changed() {
+        return;
+    }
+    for (setting_text, mut text) in setting_texts.iter_mut() {
+        text.sections[0].value = setting_text.0.value(&editing.0.gamepad);
+    }
+}
@@ -0,0 +1,411 @@
+//! What player sees on top of the game, health, weapon, scores, kill feed and crosshair
+//! Spawned once and only visible while in game, so it keeps it is kill feed when we pause
+use crate::client::essentials::{my_lobby_id, EasyClient};
+use crate::client::player::camera::CameraTarget;
+use crate::client::MyAppState;
+use crate::shared::protocol::lobby_structs::{Lobbies, LobbyPositionMap};
+use crate::shared::protocol::player_structs::*;
+use crate::shared::protocol::weapon_structs::{EquippedWeapon, Weapon};
+use crate::shared::shared_gun::BulletHitEvent;
+use bevy::prelude::*;
+use lightyear::client::events::MessageEvent;
+use lightyear::connection::id::ClientId;
+use lightyear::shared::tick_manager::TickManager;
+use std::path::Path;
+
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const BAR_BACKGROUND: Color = Color::srgba(0.1, 0.1, 0.1, 0.7);
+const HEALTH_COLOR: Color = Color::srgb(0.8, 0.15, 0.15);
+const COOLDOWN_COLOR: Color = Color::srgb(0.9, 0.7, 0.2);
+const HIT_COLOR: Color = Color::srgb(1.0, 0.2, 0.2);
+
+/// How long kill feed lines stay and how many of them fit
+const KILL_FEED_SECONDS: f32 = 5.0;
+const KILL_FEED_SIZE: usize = 5;
+/// How long crosshair stays red after we hit someone
+const HIT_MARKER_SECONDS: f32 = 0.2;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<KillFeed>();
+        app.init_resource::<HitMarker>();
+
+        app.add_systems(Startup, spawn_hud);
+        app.add_systems(Update, toggle_hud.run_if(state_changed::<MyAppState>));
+        // Feed and hit marker listen outside of game too, else we miss messages while paused
+        app.add_systems(Update, (listener_kill_feed, listener_hit_marker));
+        app.add_systems(
+            Update,
+            (
+                update_player_panel,
+                update_crosshair,
+                update_kill_feed,
+                update_scoreboard,
+            )
+                .run_if(in_state(MyAppState::Game)),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct ScreenHud;
+
+// Holds health and weapon, hidden when there is no one to show them for
+#[derive(Component)]
+struct PlayerPanel;
+
+#[derive(Component)]
+struct HealthFill;
+
+#[derive(Component)]
+struct HealthText;
+
+#[derive(Component)]
+struct CooldownFill;
+
+#[derive(Component)]
+struct WeaponText;
+
+#[derive(Component)]
+struct Crosshair;
+
+#[derive(Component)]
+struct KillFeedText;
+
+#[derive(Component)]
+struct ScoreboardText;
+
+/// Latest deaths in the lobby, oldest first
+#[derive(Resource, Default)]
+struct KillFeed(Vec<(String, Timer)>);
+
+/// Runs while crosshair should show we hit someone
+#[derive(Resource, Default)]
+struct HitMarker(Option<Timer>);
+
+/// Helper gives me the display name of a client, falls back to his id until his bundle arrives
+pub fn player_label(client_id: ClientId, player_bundle_map: &SavePlayerBundleMap) -> String {
+    player_bundle_map
+        .0
+        .get(&client_id)
+        .map(|bundle| bundle.account.display_name.clone())
+        .filter(|display_name| !display_name.is_empty())
+        .unwrap_or_else(|| format!("Player {}", client_id.to_bits()))
+}
+
+/// Helper spawns a bar, fill marker goes in the part systems resize
+fn spawn_bar(parent: &mut ChildBuilder, width: f32, color: Color, fill_marker: impl Component) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(width),
+                height: Val::Px(16.0),
+                margin: UiRect::vertical(Val::Px(4.0)),
+                ..default()
+            },
+            background_color: BAR_BACKGROUND.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    ..default()
+                },
+                fill_marker,
+            ));
+        });
+}
+
+fn spawn_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let text_style = TextStyle {
+        font: asset_server.load("grafitti.ttf"),
+        font_size: 24.0,
+        color: TEXT_COLOR,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            ScreenHud,
+            Name::new("Hud"),
+        ))
+        .with_children(|parent| {
+            // Scoreboard top left
+            parent.spawn((
+                TextBundle::from_section("", text_style.clone()).with_style(Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    ..default()
+                }),
+                ScoreboardText,
+            ));
+
+            // Kill feed top right
+            parent.spawn((
+                TextBundle::from_section("", text_style.clone())
+                    .with_text_justify(JustifyText::Right)
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(10.0),
+                        right: Val::Px(10.0),
+                        ..default()
+                    }),
+                KillFeedText,
+            ));
+
+            // Crosshair dead center
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Px(6.0),
+                                height: Val::Px(6.0),
+                                ..default()
+                            },
+                            background_color: TEXT_COLOR.into(),
+                            ..default()
+                        },
+                        Crosshair,
+                    ));
+                });
+
+            // Health and weapon bottom left, diagnostics already live bottom right
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            bottom: Val::Px(10.0),
+                            left: Val::Px(10.0),
+                            flex_direction: FlexDirection::Column,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    PlayerPanel,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((TextBundle::from_section("", text_style.clone()), WeaponText));
+                    spawn_bar(parent, 200.0, COOLDOWN_COLOR, CooldownFill);
+                    parent.spawn((TextBundle::from_section("", text_style.clone()), HealthText));
+                    spawn_bar(parent, 300.0, HEALTH_COLOR, HealthFill);
+                });
+        });
+}
+
+/// Hud only shows in game, pause and inventory have their own screens
+fn toggle_hud(state: Res<State<MyAppState>>, mut hud: Query<&mut Visibility, With<ScreenHud>>) {
+    for mut visibility in hud.iter_mut() {
+        *visibility = if *state.get() == MyAppState::Game {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// Shows health and weapon of whoever camera follows, me or the player I spectate
+fn update_player_panel(
+    target: Query<
+        (
+            &PlayerHealth,
+            Option<&Weapon>,
+            Option<&EquippedWeapon>,
+            Option<&PlayerVisuals>,
+        ),
+        With<CameraTarget>,
+    >,
+    tick_manager: Res<TickManager>,
+    mut panel: Query<&mut Visibility, With<PlayerPanel>>,
+    mut health_fill: Query<&mut Style, (With<HealthFill>, Without<CooldownFill>)>,
+    mut cooldown_fill: Query<&mut Style, (With<CooldownFill>, Without<HealthFill>)>,
+    mut health_text: Query<&mut Text, (With<HealthText>, Without<WeaponText>)>,
+    mut weapon_text: Query<&mut Text, (With<WeaponText>, Without<HealthText>)>,
+) {
+    let Ok(mut panel_visibility) = panel.get_single_mut() else {
+        return;
+    };
+    let Ok((player_health, weapon, equipped_weapon, visuals)) = target.get_single() else {
+        *panel_visibility = Visibility::Hidden;
+        return;
+    };
+    *panel_visibility = Visibility::Inherited;
+
+    let health = player_health.0.max(0);
+    if let Ok(mut style) = health_fill.get_single_mut() {
+        style.width = Val::Percent(100.0 * health as f32 / MAX_HEALTH as f32);
+    }
+    if let Ok(mut text) = health_text.get_single_mut() {
+        text.sections[0].value = format!("HP {}/{}", health, MAX_HEALTH);
+    }
+
+    // Interpolated players dont carry their weapon, so spectators only see the slot
+    let ready = weapon.map_or(1.0, |weapon| {
+        let since_fire = (tick_manager.tick() - weapon.last_fire_tick) as f32;
+        (since_fire / weapon.cooldown.max(1) as f32).clamp(0.0, 1.0)
+    });
+    if let Ok(mut style) = cooldown_fill.get_single_mut() {
+        style.width = Val::Percent(100.0 * ready);
+    }
+    if let Ok(mut text) = weapon_text.get_single_mut() {
+        let slot = equipped_weapon.map_or(0, |equipped| equipped.0);
+        let weapon_name = visuals
+            .and_then(|visuals| Path::new(visuals.weapon(slot)).file_stem())
+            .map(|stem| stem.to_string_lossy().to_uppercase())
+            .unwrap_or_default();
+        text.sections[0].value = format!("{} {}", slot + 1, weapon_name);
+    }
+}
+
+/// Our bullets hitting a player turn crosshair red for a moment
+fn listener_hit_marker(
+    mut bullet_hits: EventReader<BulletHitEvent>,
+    easy_client: Option<Res<EasyClient>>,
+    mut hit_marker: ResMut<HitMarker>,
+) {
+    let Some(easy_client) = easy_client else {
+        bullet_hits.clear();
+        return;
+    };
+    for bullet_hit in bullet_hits.read() {
+        if bullet_hit.bullet_owner == easy_client.0 && bullet_hit.victim_client_id.is_some() {
+            hit_marker.0 = Some(Timer::from_seconds(HIT_MARKER_SECONDS, TimerMode::Once));
+        }
+    }
+}
+
+fn update_crosshair(
+    time: Res<Time>,
+    mut hit_marker: ResMut<HitMarker>,
+    mut crosshair: Query<&mut BackgroundColor, With<Crosshair>>,
+) {
+    let hitting = match hit_marker.0.as_mut() {
+        Some(timer) => !timer.tick(time.delta()).finished(),
+        None => false,
+    };
+    if !hitting {
+        hit_marker.0 = None;
+    }
+    for mut color in crosshair.iter_mut() {
+        *color = if hitting { HIT_COLOR } else { TEXT_COLOR }.into();
+    }
+}
+
+fn listener_kill_feed(
+    mut player_death: EventReader<MessageEvent<PlayerDeath>>,
+    mut kill_feed: ResMut<KillFeed>,
+    player_bundle_map: Res<SavePlayerBundleMap>,
+) {
+    for event in player_death.read() {
+        let death = event.message();
+        kill_feed.0.push((
+            format!(
+                "{} killed {}",
+                player_label(death.killer, &player_bundle_map),
+                player_label(death.victim, &player_bundle_map)
+            ),
+            Timer::from_seconds(KILL_FEED_SECONDS, TimerMode::Once),
+        ));
+        if kill_feed.0.len() > KILL_FEED_SIZE {
+            kill_feed.0.remove(0);
+        }
+    }
+}
+
+/// Ticks feed lines away and rewrites the text whenever a line comes or goes
+fn update_kill_feed(
+    time: Res<Time>,
+    mut kill_feed: ResMut<KillFeed>,
+    mut feed_text: Query<&mut Text, With<KillFeedText>>,
+) {
+    // Only new lines count as a change, expiring ones we notice by length
+    let before = kill_feed.0.len();
+    kill_feed
+        .bypass_change_detection()
+        .0
+        .retain_mut(|(_, timer)| !timer.tick(time.delta()).finished());
+    if before == kill_feed.0.len() && !kill_feed.is_changed() {
+        return;
+    }
+
+    let lines: Vec<&str> = kill_feed.0.iter().map(|(line, _)| line.as_str()).collect();
+    for mut text in feed_text.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
+
+/// Players of my lobby, best first
+fn update_scoreboard(
+    scoreboard: Res<Scoreboard>,
+    lobbies: Res<Lobbies>,
+    lobby_position_map: Res<LobbyPositionMap>,
+    easy_client: Option<Res<EasyClient>>,
+    player_bundle_map: Res<SavePlayerBundleMap>,
+    mut scoreboard_text: Query<&mut Text, With<ScoreboardText>>,
+) {
+    if !scoreboard.is_changed() && !lobbies.is_changed() && !player_bundle_map.is_changed() {
+        return;
+    }
+    let Some(lobby) = my_lobby_id(&lobbies, &lobby_position_map, &easy_client)
+        .and_then(|lobby_id| lobbies.find(lobby_id))
+    else {
+        return;
+    };
+
+    let mut rows: Vec<(ClientId, PlayerScore)> = lobby
+        .players
+        .iter()
+        .map(|client_id| {
+            let score = scoreboard.0.get(client_id).copied().unwrap_or_default();
+            (*client_id, score)
+        })
+        .collect();
+    rows.sort_by_key(|(_, score)| (std::cmp::Reverse(score.kills), score.deaths));
+
+    let mut lines = vec!["PLAYER  K / D".to_string()];
+    for (client_id, score) in rows {
+        let you = easy_client
+            .as_ref()
+            .is_some_and(|easy_client| easy_client.0 == client_id);
+        lines.push(format!(
+            "{}{}  {} / {}",
+            player_label(client_id, &player_bundle_map),
+            if you { " (you)" } else { "" },
+            score.kills,
+            score.deaths
+        ));
+    }
+    for mut text in scoreboard_text.iter_mut() {
+        text.sections[0].value = lines.join("\n");
+    }
+}
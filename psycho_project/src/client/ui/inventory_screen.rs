@@ -2,12 +2,14 @@
 use crate::shared::protocol::CommonChannel;
 use bevy::prelude::*;
 use lightyear::client::connection::ConnectionManager;
+use lightyear::client::events::MessageEvent;
 
-use super::pause_screen::{ToDisplayVisuals, VisualToChange};
+use super::pause_screen::{ImageVisualInfo, ToDisplayVisuals, VisualToChange};
+use crate::client::player::char_customizer::TintChanged;
 use crate::client::MyAppState;
 use crate::client::{essentials::EasyClient, load_assets::Images};
 use crate::shared::protocol::player_structs::*;
-use lightyear::shared::replication::network_target::NetworkTarget;
+use crate::shared::shared_parts::UnlockRequirement;
 
 pub struct InventoryPlugin;
 
@@ -17,6 +19,8 @@ impl Plugin for InventoryPlugin {
         app.register_type::<ChangeChar>();
         app.register_type::<PartToChange>();
 
+        app.init_resource::<MyOwnedParts>();
+
         app.add_systems(
             OnEnter(MyAppState::Inventory),
             (inventory_screen, display_selected_visuals).chain(),
@@ -29,9 +33,15 @@ impl Plugin for InventoryPlugin {
             Update,
             assets_buttons.run_if(in_state(MyAppState::Inventory)),
         );
+        app.add_systems(Update, tint_buttons.run_if(in_state(MyAppState::Inventory)));
+        app.add_systems(Update, listener_reject_visual);
+        app.add_systems(Update, listener_owned_parts);
     }
 }
 
+/// Shown when a part image is missing from images folder
+const DEFAULT_THUMBNAIL: &str = "images/default.png";
+
 // Marker componet utilized to easily despawn entire inventory screen
 #[derive(Component)]
 pub struct ScreenInventory;
@@ -43,10 +53,26 @@ struct ReturnButton;
 #[derive(Component)]
 struct AssetButton(String);
 
+// Component utilized to store the tint preset correlated to that button
+#[derive(Component)]
+struct TintButton(PartTint);
+
 // Simple marker tell me what node to insert children in
 #[derive(Component)]
 struct OrganizingNode;
 
+/// Parts we own according to server, the rest of owned parts are shown locked
+#[derive(Resource, Default)]
+struct MyOwnedParts(Vec<String>);
+
+// Marker for part buttons we cant pick, they dont get an asset button
+#[derive(Component)]
+struct LockedPart;
+
+// Marker for text that tells player why server refused his visuals
+#[derive(Component)]
+struct InventoryStatusText;
+
 fn inventory_screen(asset_server: Res<AssetServer>, mut commands: Commands) {
     let button_style = Style {
         width: Val::Px(350.0),
@@ -134,6 +160,75 @@ fn inventory_screen(asset_server: Res<AssetServer>, mut commands: Commands) {
                         },
                         OrganizingNode,
                     ));
+                })
+                //Node with color presets of the part being displayed
+                .with_children(|parent| {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                align_items: AlignItems::Center,
+                                margin: UiRect::all(Val::Px(20.0)),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for (name, tint) in PartTint::presets() {
+                                parent
+                                    .spawn(NodeBundle {
+                                        style: Style {
+                                            flex_direction: FlexDirection::Column,
+                                            align_items: AlignItems::Center,
+                                            margin: UiRect::all(Val::Px(10.0)),
+                                            ..default()
+                                        },
+                                        ..default()
+                                    })
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            name,
+                                            TextStyle {
+                                                font: asset_server.load("grafitti.ttf"),
+                                                font_size: 30.,
+                                                ..default()
+                                            },
+                                        ));
+                                        parent.spawn((
+                                            ButtonBundle {
+                                                style: Style {
+                                                    width: Val::Px(80.0),
+                                                    height: Val::Px(80.0),
+                                                    border: UiRect::all(Val::Px(10.0)),
+                                                    ..default()
+                                                },
+                                                border_color: BorderColor(tint.secondary),
+                                                background_color: tint.primary.into(),
+                                                ..default()
+                                            },
+                                            TintButton(tint),
+                                        ));
+                                    });
+                            }
+                        });
+                })
+                // Why server refused our last pick, empty until it does
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font: asset_server.load("grafitti.ttf"),
+                                font_size: 30.,
+                                color: Color::srgb(0.9, 0.3, 0.3),
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::all(Val::Px(20.0)),
+                            ..default()
+                        }),
+                        InventoryStatusText,
+                    ));
                 });
         });
 }
@@ -146,7 +241,22 @@ fn spawn_image_button(
     asset_server: &AssetServer,
     images: &Res<Images>,
     assets: String,
+    name: String,
+    locked: bool,
 ) -> Entity {
+    // A part without thumbnail shouldnt stop us from displaying it
+    let image = match images.map.get(image_path) {
+        Some(image) => image.clone(),
+        None => {
+            warn!("Image '{}' doesnt exist using default one", image_path);
+            images
+                .map
+                .get(DEFAULT_THUMBNAIL)
+                .expect("Default image to exist")
+                .clone()
+        }
+    };
+
     let button_entity = commands
         .spawn(NodeBundle {
             style: Style {
@@ -158,8 +268,13 @@ fn spawn_image_button(
             ..default()
         })
         .with_children(|parent| {
+            let name = if locked {
+                format!("{} (Locked)", name)
+            } else {
+                name
+            };
             parent.spawn(TextBundle::from_section(
-                format!("{}", assets),
+                name,
                 TextStyle {
                     font: asset_server.load("grafitti.ttf"),
                     font_size: 40.,
@@ -167,35 +282,32 @@ fn spawn_image_button(
                 },
             ));
 
-            // Spawn the button with the image
-            parent
-                .spawn((
-                    ButtonBundle {
-                        style: image_button_style.clone(),
-                        border_color: BorderColor(Color::BLACK),
-                        ..default()
-                    },
-                    AssetButton(assets),
-                ))
-                .with_children(|parent| {
-                    parent.spawn((
-                        NodeBundle {
-                            style: Style {
-                                width: Val::Percent(100.0), // Image width fills the button
-                                height: Val::Percent(100.0),
-                                ..default()
-                            },
+            // Spawn the button with the image, locked ones are greyed out and do nothing
+            let mut button = parent.spawn(ButtonBundle {
+                style: image_button_style.clone(),
+                border_color: BorderColor(Color::BLACK),
+                ..default()
+            });
+            let image_color = if locked {
+                button.insert(LockedPart);
+                Color::srgb(0.3, 0.3, 0.3)
+            } else {
+                button.insert(AssetButton(assets));
+                Color::WHITE
+            };
+            button.with_children(|parent| {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.0), // Image width fills the button
+                            height: Val::Percent(100.0),
                             ..default()
                         },
-                        UiImage::new(
-                            images
-                                .map
-                                .get(image_path)
-                                .expect(&format!("Image '{}' to exist", image_path))
-                                .clone(),
-                        ),
-                    ));
-                });
+                        ..default()
+                    },
+                    UiImage::new(image).with_color(image_color),
+                ));
+            });
         })
         .id();
     return button_entity;
@@ -207,6 +319,7 @@ fn display_selected_visuals(
     asset_server: Res<AssetServer>,
     images: Res<Images>,
     display_visuals: Res<ToDisplayVisuals>,
+    owned_parts: Res<MyOwnedParts>,
     mut commands: Commands,
 ) {
     if let Ok(node) = organizing_node.get_single() {
@@ -231,6 +344,8 @@ fn display_selected_visuals(
                         &asset_server,
                         &images,
                         visual.asset.clone(),
+                        visual.name.clone(),
+                        is_locked(visual, &owned_parts),
                     ));
                 }
             }
@@ -244,6 +359,8 @@ fn display_selected_visuals(
                         &asset_server,
                         &images,
                         visual.asset.clone(),
+                        visual.name.clone(),
+                        is_locked(visual, &owned_parts),
                     ));
                 }
             }
@@ -257,6 +374,8 @@ fn display_selected_visuals(
                         &asset_server,
                         &images,
                         visual.asset.clone(),
+                        visual.name.clone(),
+                        is_locked(visual, &owned_parts),
                     ));
                 }
             }
@@ -268,6 +387,11 @@ fn display_selected_visuals(
     }
 }
 
+/// Helper tells me if a part needs to be owned and we dont
+fn is_locked(visual: &ImageVisualInfo, owned_parts: &MyOwnedParts) -> bool {
+    visual.unlock == UnlockRequirement::Owned && !owned_parts.0.contains(&visual.asset)
+}
+
 fn return_button(
     mut interaction_query: Query<
         (&Interaction, &mut BorderColor),
@@ -320,62 +444,18 @@ fn assets_buttons(
 
                     let mut player_visuals = player_bundle_map.visuals.clone();
 
+                    // Server is the one that tells everyone, including us, once it accepts the change
                     match visuals_displayed {
                         VisualToChange::Head(_) => {
-                            info!("Visual to adjust was in head");
-
-                            info!("Grabbing old part {}", player_visuals.head.clone());
-                            info!("Grabbing new part {}", asset_path.clone());
-
-                            info!("Sending super message to all clients");
-                            let _ = connection_manager
-                                .send_message_to_target::<CommonChannel, ChangeChar>(
-                                    &mut ChangeChar((
-                                        client_id,
-                                        PartToChange {
-                                            old_part: player_visuals.head.clone(),
-                                            new_part: asset_path.clone(),
-                                        },
-                                    )),
-                                    NetworkTarget::All,
-                                );
-
-                            info!("Adjusting cloned visual to send to replicated resource later");
+                            info!("Changing head {} to {}", player_visuals.head, asset_path);
                             player_visuals.head = asset_path.clone();
                         }
                         VisualToChange::Torso(_) => {
-                            info!("Grabbing old part {}", player_visuals.torso.clone());
-                            info!("Grabbing new part {}", asset_path.clone());
-                            let _ = connection_manager
-                                .send_message_to_target::<CommonChannel, ChangeChar>(
-                                    &mut ChangeChar((
-                                        client_id,
-                                        PartToChange {
-                                            old_part: player_visuals.torso.clone(),
-                                            new_part: asset_path.clone(),
-                                        },
-                                    )),
-                                    NetworkTarget::All,
-                                );
-
-                            info!("Adjusting cloned visual to send to resource later");
+                            info!("Changing torso {} to {}", player_visuals.torso, asset_path);
                             player_visuals.torso = asset_path.clone();
                         }
                         VisualToChange::Legs(_) => {
-                            info!("Grabbing old part {}", player_visuals.legs.clone());
-                            info!("Grabbing new part {}", asset_path.clone());
-                            let _ = connection_manager
-                                .send_message_to_target::<CommonChannel, ChangeChar>(
-                                    &mut ChangeChar((
-                                        client_id,
-                                        PartToChange {
-                                            old_part: player_visuals.legs.clone(),
-                                            new_part: asset_path.clone(),
-                                        },
-                                    )),
-                                    NetworkTarget::All,
-                                );
-                            info!("Adjusting cloned visual to send to resource later");
+                            info!("Changing legs {} to {}", player_visuals.legs, asset_path);
                             player_visuals.legs = asset_path.clone();
                         }
                     }
@@ -396,3 +476,68 @@ fn assets_buttons(
         }
     }
 }
+
+/// Changes the color of the part being displayed, we preview it right away while server decides if it is okay
+fn tint_buttons(
+    interaction_query: Query<(&Interaction, &TintButton), Changed<Interaction>>,
+    client_id: Res<EasyClient>,
+    player_bundle_map: Res<SavePlayerBundleMap>,
+    to_display_visuals: Res<ToDisplayVisuals>,
+    mut tint_changed: EventWriter<TintChanged>,
+    mut connection_manager: ResMut<ConnectionManager>,
+) {
+    let slot = to_display_visuals.0.slot();
+
+    for (interaction, tint_button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let client_id = client_id.0;
+        let Some(player_bundle) = player_bundle_map.0.get(&client_id) else {
+            error!("Couldnt find you in server my man cant adjust your visuals");
+            continue;
+        };
+
+        let mut player_visuals = player_bundle.visuals.clone();
+        if let Some(tint) = player_visuals.tint_mut(slot) {
+            *tint = tint_button.0;
+        }
+
+        info!("Previewing {:?} tint", slot);
+        tint_changed.send(TintChanged {
+            client_id,
+            slot,
+            tint: tint_button.0,
+        });
+
+        info!("Sending message to server to adjust visual resource");
+        let _ = connection_manager
+            .send_message::<CommonChannel, SaveVisual>(&mut SaveVisual(player_visuals));
+    }
+}
+
+/// Server didnt like the visuals we picked, let the player know why
+fn listener_reject_visual(
+    mut events: EventReader<MessageEvent<RejectVisual>>,
+    mut status: Query<&mut Text, With<InventoryStatusText>>,
+) {
+    for event in events.read() {
+        let reason = &event.message().0;
+        warn!("Server rejected your visuals: {}", reason);
+        // Inventory might be closed, like when loadout panel picked the visuals
+        for mut text in status.iter_mut() {
+            text.sections[0].value = format!("Cant wear that: {}", reason);
+        }
+    }
+}
+
+/// Server tells us what we own when we connect and whenever we get something new
+fn listener_owned_parts(
+    mut events: EventReader<MessageEvent<SendOwnedParts>>,
+    mut owned_parts: ResMut<MyOwnedParts>,
+) {
+    for event in events.read() {
+        info!("Server says we own {} parts", event.message().0.len());
+        owned_parts.0 = event.message().0.clone();
+    }
+}
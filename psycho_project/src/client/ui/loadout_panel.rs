@@ -0,0 +1,253 @@
+//! Small panel in pause screen that lets the player swap between his loadout presets
+use crate::client::essentials::EasyClient;
+use crate::client::MyAppState;
+use crate::shared::protocol::player_structs::*;
+use crate::shared::protocol::CommonChannel;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use lightyear::client::connection::ConnectionManager;
+
+pub struct LoadoutPlugin;
+
+impl Plugin for LoadoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (loadout_buttons, type_loadout_name, update_loadout_text)
+                .chain()
+                .run_if(in_state(MyAppState::Pause)),
+        );
+        app.add_systems(OnExit(MyAppState::Pause), stop_renaming);
+    }
+}
+
+// What each button of the panel does
+#[derive(Component, Clone, Copy, Debug)]
+enum LoadoutButton {
+    Previous,
+    Next,
+    New,
+    Rename,
+    Delete,
+}
+
+// Marker for text that displays active loadout
+#[derive(Component)]
+struct LoadoutNameText;
+
+/// Exists while player is typing a new name for his active loadout
+#[derive(Resource)]
+struct RenamingLoadout(String);
+
+/// Helper spawns the loadout panel as a child of the given node
+pub fn spawn_loadout_panel(parent: &mut ChildBuilder, asset_server: &AssetServer) {
+    let text_style = TextStyle {
+        font: asset_server.load("grafitti.ttf"),
+        font_size: 30.,
+        ..default()
+    };
+    let button_style = Style {
+        padding: UiRect::all(Val::Px(10.0)),
+        margin: UiRect::all(Val::Px(5.0)),
+        border: UiRect::all(Val::Px(5.0)),
+        ..default()
+    };
+
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                margin: UiRect::all(Val::Px(20.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section("Loadout", text_style.clone()),
+                LoadoutNameText,
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (label, loadout_button) in [
+                        ("<", LoadoutButton::Previous),
+                        (">", LoadoutButton::Next),
+                        ("New", LoadoutButton::New),
+                        ("Rename", LoadoutButton::Rename),
+                        ("Delete", LoadoutButton::Delete),
+                    ] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: button_style.clone(),
+                                    border_color: BorderColor(Color::BLACK),
+                                    ..default()
+                                },
+                                loadout_button,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(label, text_style.clone()));
+                            });
+                    }
+                });
+        });
+}
+
+/// Helper gives me my loadouts according to the replicated save map
+fn my_loadouts<'a>(
+    player_bundle_map: &'a SavePlayerBundleMap,
+    client_id: &EasyClient,
+) -> Option<&'a PlayerLoadouts> {
+    player_bundle_map
+        .0
+        .get(&client_id.0)
+        .map(|player_bundle| &player_bundle.loadouts)
+}
+
+/// Server decides what happens with our loadouts, we just ask
+fn loadout_buttons(
+    mut interaction_query: Query<
+        (&Interaction, &mut BorderColor, &LoadoutButton),
+        Changed<Interaction>,
+    >,
+    client_id: Option<Res<EasyClient>>,
+    player_bundle_map: Res<SavePlayerBundleMap>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut commands: Commands,
+) {
+    for (interaction, mut border_color, loadout_button) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                let Some(loadouts) = client_id
+                    .as_ref()
+                    .and_then(|client_id| my_loadouts(&player_bundle_map, client_id))
+                else {
+                    error!("Couldnt find you in server my man cant adjust your loadouts");
+                    continue;
+                };
+                let amount = loadouts.presets.len();
+                let active = loadouts.active;
+
+                let mut change_loadout = match loadout_button {
+                    LoadoutButton::Previous => {
+                        ChangeLoadout::Select((active + amount - 1) % amount)
+                    }
+                    LoadoutButton::Next => ChangeLoadout::Select((active + 1) % amount),
+                    LoadoutButton::New => {
+                        if amount >= MAX_LOADOUT_PRESETS {
+                            info!("Already have the most loadouts you can");
+                            continue;
+                        }
+                        ChangeLoadout::Create(format!("Loadout {}", amount + 1))
+                    }
+                    LoadoutButton::Delete => ChangeLoadout::Delete(active),
+                    LoadoutButton::Rename => {
+                        info!("Typing new name for loadout {}", active);
+                        let name = loadouts.active_preset().name.clone();
+                        commands.insert_resource(RenamingLoadout(name));
+                        continue;
+                    }
+                };
+                info!("Asking server to {:?}", change_loadout);
+                let _ = connection_manager
+                    .send_message::<CommonChannel, ChangeLoadout>(&mut change_loadout);
+            }
+            Interaction::Hovered => {
+                *border_color = BorderColor(Color::WHITE);
+            }
+            Interaction::None => {
+                *border_color = BorderColor(Color::BLACK);
+            }
+        }
+    }
+}
+
+/// While renaming, keyboard types in the name. Enter confirms and escape cancels
+fn type_loadout_name(
+    renaming: Option<ResMut<RenamingLoadout>>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    client_id: Option<Res<EasyClient>>,
+    player_bundle_map: Res<SavePlayerBundleMap>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut commands: Commands,
+) {
+    let Some(mut renaming) = renaming else {
+        keyboard_events.clear();
+        return;
+    };
+
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(character) => renaming.0.push_str(character),
+            Key::Space => renaming.0.push(' '),
+            Key::Backspace => {
+                renaming.0.pop();
+            }
+            Key::Escape => {
+                commands.remove_resource::<RenamingLoadout>();
+                return;
+            }
+            Key::Enter => {
+                if let Some(loadouts) = client_id
+                    .as_ref()
+                    .and_then(|client_id| my_loadouts(&player_bundle_map, client_id))
+                {
+                    let _ = connection_manager.send_message::<CommonChannel, ChangeLoadout>(
+                        &mut ChangeLoadout::Rename(loadouts.active, renaming.0.clone()),
+                    );
+                }
+                commands.remove_resource::<RenamingLoadout>();
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_loadout_text(
+    mut loadout_text: Query<&mut Text, With<LoadoutNameText>>,
+    renaming: Option<Res<RenamingLoadout>>,
+    client_id: Option<Res<EasyClient>>,
+    player_bundle_map: Res<SavePlayerBundleMap>,
+) {
+    let Ok(mut text) = loadout_text.get_single_mut() else {
+        return;
+    };
+
+    let value = if let Some(renaming) = renaming {
+        format!("Name: {}_", renaming.0)
+    } else if let Some(loadouts) = client_id
+        .as_ref()
+        .and_then(|client_id| my_loadouts(&player_bundle_map, client_id))
+    {
+        format!(
+            "Loadout: {} ({}/{})",
+            loadouts.active_preset().name,
+            loadouts.active + 1,
+            loadouts.presets.len()
+        )
+    } else {
+        String::from("Loadout")
+    };
+
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+fn stop_renaming(mut commands: Commands) {
+    commands.remove_resource::<RenamingLoadout>();
+}
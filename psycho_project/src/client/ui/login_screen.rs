@@ -0,0 +1,450 @@
+//! First screen after loading, player logs in or registers before we connect to the game server
+//! Fields are typed into straight from keyboard events, tab or a click changes which one is focused
+use crate::client::auth::{
+    send_auth_request, AuthAddress, AuthOutcome, PendingAuth, RememberedAccount,
+};
+use crate::client::MyAppState;
+use crate::shared::shared_auth::*;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+
+const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+const FOCUSED_FIELD: Color = Color::srgb(0.3, 0.3, 0.3);
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const ERROR_COLOR: Color = Color::srgb(0.9, 0.3, 0.3);
+
+/// Caps what we keep typing into a field, server checks real limits
+const MAX_FIELD_LENGTH: usize = 32;
+
+pub struct LoginScreenPlugin;
+
+impl Plugin for LoginScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(MyAppState::Login), login_screen);
+        app.add_systems(OnExit(MyAppState::Login), despawn_login_screen);
+        app.add_systems(
+            Update,
+            (
+                focus_field,
+                type_into_field,
+                login_buttons,
+                listener_auth_outcome,
+                update_field_texts,
+            )
+                .chain()
+                .run_if(in_state(MyAppState::Login)),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct ScreenLogin;
+
+// Clickable box of a field
+#[derive(Component)]
+struct FieldBox(LoginField);
+
+// Text inside of a field box
+#[derive(Component)]
+struct FieldText(LoginField);
+
+// Tells player what is going on
+#[derive(Component)]
+struct StatusText;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LoginField {
+    Username,
+    Password,
+    DisplayName,
+}
+
+impl LoginField {
+    const ALL: [Self; 3] = [Self::Username, Self::Password, Self::DisplayName];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Username => "Username",
+            Self::Password => "Password",
+            Self::DisplayName => "Display name",
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            Self::Username => Self::Password,
+            Self::Password => Self::DisplayName,
+            Self::DisplayName => Self::Username,
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+enum LoginButton {
+    Login,
+    Register,
+}
+
+/// What player typed so far and which field he is typing into
+#[derive(Resource)]
+struct LoginForm {
+    username: String,
+    password: String,
+    display_name: String,
+    focused: LoginField,
+}
+
+impl LoginForm {
+    fn field_mut(&mut self, field: LoginField) -> &mut String {
+        match field {
+            LoginField::Username => &mut self.username,
+            LoginField::Password => &mut self.password,
+            LoginField::DisplayName => &mut self.display_name,
+        }
+    }
+
+    /// Helper gives me what a field shows, password is hidden behind stars
+    fn shown(&self, field: LoginField) -> String {
+        match field {
+            LoginField::Username => self.username.clone(),
+            LoginField::Password => "*".repeat(self.password.chars().count()),
+            LoginField::DisplayName => self.display_name.clone(),
+        }
+    }
+}
+
+fn login_screen(
+    remembered: Res<RememberedAccount>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    // Remembered player only needs his password
+    let focused = if remembered.username.is_empty() {
+        LoginField::Username
+    } else {
+        LoginField::Password
+    };
+    commands.insert_resource(LoginForm {
+        username: remembered.username.clone(),
+        password: String::new(),
+        display_name: String::new(),
+        focused,
+    });
+
+    let text_style = TextStyle {
+        font: asset_server.load("grafitti.ttf"),
+        font_size: 30.0,
+        color: TEXT_COLOR,
+    };
+    let button_style = Style {
+        width: Val::Px(200.0),
+        padding: UiRect::all(Val::Px(5.0)),
+        margin: UiRect::all(Val::Px(5.0)),
+        border: UiRect::all(Val::Px(5.0)),
+        justify_content: JustifyContent::Center,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::srgb(0.10, 0.10, 0.10).into(),
+                ..default()
+            },
+            ScreenLogin,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "LOGIN",
+                TextStyle {
+                    font_size: 60.0,
+                    ..text_style.clone()
+                },
+            ));
+
+            for field in LoginField::ALL {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(field.label(), text_style.clone()).with_style(
+                                Style {
+                                    width: Val::Px(250.0),
+                                    ..default()
+                                },
+                            ),
+                        );
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(350.0),
+                                        justify_content: JustifyContent::Start,
+                                        ..button_style.clone()
+                                    },
+                                    border_color: BorderColor(Color::BLACK),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                FieldBox(field),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    TextBundle::from_section("", text_style.clone()),
+                                    FieldText(field),
+                                ));
+                            });
+                    });
+            }
+
+            parent.spawn((
+                TextBundle::from_section(
+                    "Display name is only needed to register",
+                    TextStyle {
+                        font_size: 20.0,
+                        ..text_style.clone()
+                    },
+                ),
+                StatusText,
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (label, login_button) in [
+                        ("LOGIN", LoginButton::Login),
+                        ("REGISTER", LoginButton::Register),
+                    ] {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: button_style.clone(),
+                                    border_color: BorderColor(Color::BLACK),
+                                    background_color: NORMAL_BUTTON.into(),
+                                    ..default()
+                                },
+                                login_button,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(label, text_style.clone()));
+                            });
+                    }
+                });
+        });
+}
+
+fn despawn_login_screen(screen: Query<Entity, With<ScreenLogin>>, mut commands: Commands) {
+    for screen in screen.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+    commands.remove_resource::<LoginForm>();
+}
+
+fn focus_field(
+    interaction_query: Query<(&Interaction, &FieldBox), Changed<Interaction>>,
+    mut form: ResMut<LoginForm>,
+) {
+    for (interaction, field_box) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            form.focused = field_box.0;
+        }
+    }
+}
+
+/// Typing goes into focused field, tab jumps to the next one and enter logs in
+fn type_into_field(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut form: ResMut<LoginForm>,
+    address: Option<Res<AuthAddress>>,
+    pending: Option<Res<PendingAuth>>,
+    mut status: Query<&mut Text, With<StatusText>>,
+    mut commands: Commands,
+) {
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        let focused = form.focused;
+        match &event.logical_key {
+            Key::Character(characters) => {
+                let field = form.field_mut(focused);
+                for character in characters.chars().filter(|c| !c.is_control()) {
+                    if field.chars().count() < MAX_FIELD_LENGTH {
+                        field.push(character);
+                    }
+                }
+            }
+            Key::Space if focused == LoginField::DisplayName => {
+                let field = form.field_mut(focused);
+                if field.chars().count() < MAX_FIELD_LENGTH {
+                    field.push(' ');
+                }
+            }
+            Key::Backspace => {
+                form.field_mut(focused).pop();
+            }
+            Key::Tab => form.focused = focused.next(),
+            Key::Enter => {
+                if let Ok(mut text) = status.get_single_mut() {
+                    submit(
+                        LoginButton::Login,
+                        &form,
+                        &address,
+                        &pending,
+                        &mut text,
+                        &mut commands,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn login_buttons(
+    interaction_query: Query<(&Interaction, &LoginButton), Changed<Interaction>>,
+    form: Res<LoginForm>,
+    address: Option<Res<AuthAddress>>,
+    pending: Option<Res<PendingAuth>>,
+    mut status: Query<&mut Text, With<StatusText>>,
+    mut commands: Commands,
+) {
+    let Ok(mut text) = status.get_single_mut() else {
+        return;
+    };
+    for (interaction, login_button) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            submit(
+                *login_button,
+                &form,
+                &address,
+                &pending,
+                &mut text,
+                &mut commands,
+            );
+        }
+    }
+}
+
+/// Helper checks what player typed and sends it to auth service
+fn submit(
+    login_button: LoginButton,
+    form: &LoginForm,
+    address: &Option<Res<AuthAddress>>,
+    pending: &Option<Res<PendingAuth>>,
+    status: &mut Text,
+    commands: &mut Commands,
+) {
+    if pending.is_some() {
+        return;
+    }
+    let Some(address) = address else {
+        set_status(status, "Auth service address unknown", ERROR_COLOR);
+        return;
+    };
+    if let Some(reason) = validate_credentials(&form.username, &form.password) {
+        set_status(status, &reason, ERROR_COLOR);
+        return;
+    }
+
+    let request = match login_button {
+        LoginButton::Login => AuthRequest::Login {
+            username: form.username.clone(),
+            password: form.password.clone(),
+        },
+        LoginButton::Register => {
+            let name = display_name(&form.display_name);
+            if name.is_empty() {
+                set_status(status, "Pick a display name", ERROR_COLOR);
+                return;
+            }
+            AuthRequest::Register {
+                username: form.username.clone(),
+                password: form.password.clone(),
+                display_name: name,
+            }
+        }
+    };
+    info!("Sending {:?} request for {}", login_button, form.username);
+    set_status(status, "Talking to auth service...", TEXT_COLOR);
+    send_auth_request(address.0, request, commands);
+}
+
+fn set_status(status: &mut Text, message: &str, color: Color) {
+    status.sections[0].value = message.to_string();
+    status.sections[0].style.color = color;
+}
+
+/// Logged in players move on to main menu and we remember who they were
+fn listener_auth_outcome(
+    mut outcomes: EventReader<AuthOutcome>,
+    form: Res<LoginForm>,
+    mut remembered: ResMut<RememberedAccount>,
+    mut status: Query<&mut Text, With<StatusText>>,
+    mut next_state: ResMut<NextState<MyAppState>>,
+) {
+    for outcome in outcomes.read() {
+        match outcome {
+            AuthOutcome::LoggedIn(account) => {
+                info!(
+                    "Welcome {} your account is {}",
+                    account.display_name, account.account_id
+                );
+                remembered.username = form.username.clone();
+                remembered.save();
+                next_state.set(MyAppState::MainMenu);
+            }
+            AuthOutcome::Failed(reason) => {
+                if let Ok(mut text) = status.get_single_mut() {
+                    set_status(&mut text, reason, ERROR_COLOR);
+                }
+            }
+        }
+    }
+}
+
+fn update_field_texts(
+    form: Res<LoginForm>,
+    mut field_texts: Query<(&FieldText, &mut Text)>,
+    mut field_boxes: Query<(&FieldBox, &mut BackgroundColor)>,
+) {
+    if !form.is_changed() {
+        return;
+    }
+    for (field_text, mut text) in field_texts.iter_mut() {
+        let mut shown = form.shown(field_text.0);
+        if field_text.0 == form.focused {
+            shown.push('|');
+        }
+        text.sections[0].value = shown;
+    }
+    for (field_box, mut background) in field_boxes.iter_mut() {
+        background.0 = if field_box.0 == form.focused {
+            FOCUSED_FIELD
+        } else {
+            NORMAL_BUTTON
+        };
+    }
+}
@@ -1,5 +1,6 @@
+use crate::client::MyAppState;
+use crate::shared::protocol::lobby_structs::{EnterLobby, SpectateLobby};
 use crate::shared::protocol::CommonChannel;
-use crate::{client::MyAppState, shared::protocol::lobby_structs::EnterLobby};
 use bevy::prelude::*;
 use lightyear::client::connection::ConnectionManager;
 
@@ -13,6 +14,18 @@ impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(MyAppState::MainMenu), menu_screen);
         app.add_systems(Update, start_button.run_if(in_state(MyAppState::MainMenu)));
+        app.add_systems(
+            Update,
+            spectate_button.run_if(in_state(MyAppState::MainMenu)),
+        );
+        app.add_systems(
+            Update,
+            settings_button.run_if(in_state(MyAppState::MainMenu)),
+        );
+        app.add_systems(
+            Update,
+            controls_button.run_if(in_state(MyAppState::MainMenu)),
+        );
         app.add_systems(Update, exit_button.run_if(in_state(MyAppState::MainMenu)));
     }
 }
@@ -25,6 +38,18 @@ pub struct ScreenMainMenu;
 #[derive(Component)]
 struct StartButton;
 
+// Marker component for the spectate button
+#[derive(Component)]
+struct SpectateButton;
+
+// Marker component for the settings button
+#[derive(Component)]
+struct SettingsButton;
+
+// Marker component for the controls button
+#[derive(Component)]
+struct ControlsButton;
+
 // Marker component for the exit button
 #[derive(Component)]
 struct ExitButton;
@@ -99,12 +124,32 @@ fn menu_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
                         });
 
                     parent
-                        .spawn((ButtonBundle {
-                            style: button_style.clone(),
-                            border_color: BorderColor(Color::BLACK),
-                            background_color: NORMAL_BUTTON.into(),
-                            ..default()
-                        },))
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style.clone(),
+                                border_color: BorderColor(Color::BLACK),
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            SpectateButton,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "SPECTATE",
+                                button_text_style.clone(),
+                            ));
+                        });
+
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style.clone(),
+                                border_color: BorderColor(Color::BLACK),
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            SettingsButton,
+                        ))
                         .with_children(|parent| {
                             parent.spawn(TextBundle::from_section(
                                 "SETTINGS",
@@ -112,6 +157,23 @@ fn menu_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
                             ));
                         });
 
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: button_style.clone(),
+                                border_color: BorderColor(Color::BLACK),
+                                background_color: NORMAL_BUTTON.into(),
+                                ..default()
+                            },
+                            ControlsButton,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "CONTROLS",
+                                button_text_style.clone(),
+                            ));
+                        });
+
                     parent
                         .spawn((
                             ButtonBundle {
@@ -172,6 +234,125 @@ fn start_button(
     }
 }
 
+/// Button responsible for watching lobby without playing
+fn spectate_button(
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &mut BorderColor,
+            &Children,
+        ),
+        (Changed<Interaction>, With<SpectateButton>),
+    >,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut text_query: Query<&mut Text>,
+) {
+    if let Ok((interaction, mut color, mut border_color, children)) =
+        interaction_query.get_single_mut()
+    {
+        let mut text = text_query.get_mut(children[0]).unwrap();
+
+        match *interaction {
+            Interaction::Pressed => {
+                text.sections[0].value = "GRAB POPCORN".to_string();
+                *color = PRESSED_BUTTON.into();
+                border_color.0 = Color::srgb(255.0, 0.0, 0.0);
+                let _ = connection_manager
+                    .send_message::<CommonChannel, SpectateLobby>(&mut SpectateLobby);
+            }
+            Interaction::Hovered => {
+                text.sections[0].value = "JUST WATCH".to_string();
+                *color = HOVERED_BUTTON.into();
+                border_color.0 = Color::WHITE;
+            }
+            Interaction::None => {
+                text.sections[0].value = "SPECTATE".to_string();
+                *color = NORMAL_BUTTON.into();
+                border_color.0 = Color::BLACK;
+            }
+        }
+    }
+}
+
+/// Button that takes us to the settings screen
+fn settings_button(
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &mut BorderColor,
+            &Children,
+        ),
+        (Changed<Interaction>, With<SettingsButton>),
+    >,
+    mut text_query: Query<&mut Text>,
+    mut next_state: ResMut<NextState<MyAppState>>,
+) {
+    if let Ok((interaction, mut color, mut border_color, children)) =
+        interaction_query.get_single_mut()
+    {
+        let mut text = text_query.get_mut(children[0]).unwrap();
+
+        match *interaction {
+            Interaction::Pressed => {
+                *color = PRESSED_BUTTON.into();
+                border_color.0 = Color::srgb(255.0, 0.0, 0.0);
+                next_state.set(MyAppState::Settings);
+            }
+            Interaction::Hovered => {
+                text.sections[0].value = "TWEAK".to_string();
+                *color = HOVERED_BUTTON.into();
+                border_color.0 = Color::WHITE;
+            }
+            Interaction::None => {
+                text.sections[0].value = "SETTINGS".to_string();
+                *color = NORMAL_BUTTON.into();
+                border_color.0 = Color::BLACK;
+            }
+        }
+    }
+}
+
+/// Button that takes us to the controls screen
+fn controls_button(
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &mut BorderColor,
+            &Children,
+        ),
+        (Changed<Interaction>, With<ControlsButton>),
+    >,
+    mut text_query: Query<&mut Text>,
+    mut next_state: ResMut<NextState<MyAppState>>,
+) {
+    if let Ok((interaction, mut color, mut border_color, children)) =
+        interaction_query.get_single_mut()
+    {
+        let mut text = text_query.get_mut(children[0]).unwrap();
+
+        match *interaction {
+            Interaction::Pressed => {
+                *color = PRESSED_BUTTON.into();
+                border_color.0 = Color::srgb(255.0, 0.0, 0.0);
+                next_state.set(MyAppState::Controls);
+            }
+            Interaction::Hovered => {
+                text.sections[0].value = "KEYS".to_string();
+                *color = HOVERED_BUTTON.into();
+                border_color.0 = Color::WHITE;
+            }
+            Interaction::None => {
+                text.sections[0].value = "CONTROLS".to_string();
+                *color = NORMAL_BUTTON.into();
+                border_color.0 = Color::BLACK;
+            }
+        }
+    }
+}
+
 /// Buttons responsible for leaving app
 fn exit_button(
     mut interaction_query: Query<
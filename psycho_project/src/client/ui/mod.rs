@@ -1,17 +1,34 @@
 use bevy::prelude::*;
 
+pub mod controls_screen;
+pub mod hud;
 pub mod inventory_screen;
+pub mod loadout_panel;
+pub mod login_screen;
 pub mod main_screen;
+pub mod nameplates;
 pub mod pause_screen;
+pub mod settings_rows;
+pub mod settings_screen;
 
-use self::{inventory_screen::*, main_screen::*, pause_screen::PausePlugin};
+use self::{
+    controls_screen::ControlsScreenPlugin, hud::HudPlugin, inventory_screen::*,
+    loadout_panel::LoadoutPlugin, login_screen::LoginScreenPlugin, main_screen::*,
+    nameplates::NameplatesPlugin, pause_screen::PausePlugin, settings_screen::SettingsScreenPlugin,
+};
 
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
+        app.add_plugins(LoginScreenPlugin);
         app.add_plugins(MainMenuPlugin);
         app.add_plugins(PausePlugin);
         app.add_plugins(InventoryPlugin);
+        app.add_plugins(LoadoutPlugin);
+        app.add_plugins(ControlsScreenPlugin);
+        app.add_plugins(SettingsScreenPlugin);
+        app.add_plugins(HudPlugin);
+        app.add_plugins(NameplatesPlugin);
     }
 }
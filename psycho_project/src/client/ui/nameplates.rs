@@ -0,0 +1,260 @@
+//! Names and health floating over other players, they are ui nodes we move to where the player shows on screen
+//! Plates live inside the hud so they hide with it outside of game
+use crate::client::player::camera::{sync_player_camera, CameraTarget, MarkerMainCamera};
+use crate::client::ui::hud::{player_label, ScreenHud};
+use crate::shared::protocol::player_structs::*;
+use crate::shared::shared_physics::GameLayer;
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+use lightyear::prelude::client::{Interpolated, Predicted};
+use lightyear::shared::replication::components::Controlled;
+
+const PLATE_WIDTH: f32 = 120.0;
+const HEALTH_COLOR: Color = Color::srgb(0.8, 0.15, 0.15);
+const BAR_BACKGROUND: Color = Color::srgb(0.1, 0.1, 0.1);
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+
+/// How far above player position the plate sits
+const PLATE_HEIGHT: f32 = 1.5;
+/// Plates start fading at this distance and are gone at the other
+const FADE_START: f32 = 15.0;
+const FADE_END: f32 = 30.0;
+/// How fast plates fade in and out
+const FADE_SPEED: f32 = 8.0;
+
+pub struct NameplatesPlugin;
+
+impl Plugin for NameplatesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (spawn_nameplates, rename_nameplates, despawn_nameplates).chain(),
+        );
+        // After camera moved so plates dont lag a frame behind it
+        app.add_systems(
+            PostUpdate,
+            update_nameplates
+                .after(sync_player_camera)
+                .before(UiSystem::Layout),
+        );
+    }
+}
+
+/// Plate that follows a player
+#[derive(Component)]
+struct Nameplate {
+    player: Entity,
+    alpha: f32,
+}
+
+#[derive(Component)]
+struct NameplateText;
+
+#[derive(Component)]
+struct NameplateHealth;
+
+/// Other players get a plate, predicted ones while playing and interpolated ones while spectating
+fn spawn_nameplates(
+    players: Query<
+        (Entity, &PlayerId),
+        (
+            With<MarkerPlayer>,
+            Without<Controlled>,
+            Or<(Added<Predicted>, Added<Interpolated>)>,
+        ),
+    >,
+    hud: Query<Entity, With<ScreenHud>>,
+    player_bundle_map: Res<SavePlayerBundleMap>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    let Ok(hud) = hud.get_single() else {
+        return;
+    };
+    for (player, player_id) in players.iter() {
+        info!("Spawning nameplate for player {}", player_id.0);
+        let plate = commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Px(PLATE_WIDTH),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                Nameplate { player, alpha: 0.0 },
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_section(
+                        player_label(player_id.0, &player_bundle_map),
+                        TextStyle {
+                            font: asset_server.load("grafitti.ttf"),
+                            font_size: 18.0,
+                            color: TEXT_COLOR,
+                        },
+                    ),
+                    NameplateText,
+                ));
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(PLATE_WIDTH * 0.6),
+                            height: Val::Px(6.0),
+                            ..default()
+                        },
+                        background_color: BAR_BACKGROUND.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Percent(100.0),
+                                    height: Val::Percent(100.0),
+                                    ..default()
+                                },
+                                background_color: HEALTH_COLOR.into(),
+                                ..default()
+                            },
+                            NameplateHealth,
+                        ));
+                    });
+            })
+            .id();
+        commands.entity(hud).add_child(plate);
+    }
+}
+
+/// Display names arrive with the bundle map, which can come after the player itself
+fn rename_nameplates(
+    player_bundle_map: Res<SavePlayerBundleMap>,
+    plates: Query<(&Nameplate, &Children)>,
+    players: Query<&PlayerId>,
+    mut texts: Query<&mut Text, With<NameplateText>>,
+) {
+    if !player_bundle_map.is_changed() {
+        return;
+    }
+    for (plate, children) in plates.iter() {
+        let Ok(player_id) = players.get(plate.player) else {
+            continue;
+        };
+        let label = player_label(player_id.0, &player_bundle_map);
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                if text.sections[0].value != label {
+                    text.sections[0].value = label.clone();
+                }
+            }
+        }
+    }
+}
+
+/// Player left or got despawned so his plate goes too
+fn despawn_nameplates(
+    plates: Query<(Entity, &Nameplate)>,
+    players: Query<(), With<MarkerPlayer>>,
+    mut commands: Commands,
+) {
+    for (plate_entity, plate) in plates.iter() {
+        if players.get(plate.player).is_err() {
+            commands.entity(plate_entity).despawn_recursive();
+        }
+    }
+}
+
+/// Moves plates over their players, fades them with distance and walls and keeps health up to date
+fn update_nameplates(
+    cam_q: Query<(&Camera, &Transform), With<MarkerMainCamera>>,
+    players: Query<(&Transform, &PlayerHealth, Has<CameraTarget>)>,
+    mut plates: Query<(&mut Nameplate, &mut Style, &mut Visibility, &Children)>,
+    mut texts: Query<&mut Text, With<NameplateText>>,
+    mut bars: Query<
+        (&mut Style, &mut BackgroundColor),
+        (With<NameplateHealth>, Without<Nameplate>),
+    >,
+    mut bar_backgrounds: Query<
+        &mut BackgroundColor,
+        (Without<NameplateHealth>, Without<Nameplate>, Without<Text>),
+    >,
+    children_entities: Query<&Children>,
+    spatial_query: SpatialQuery,
+    time: Res<Time>,
+) {
+    let Ok((camera, cam_transform)) = cam_q.get_single() else {
+        return;
+    };
+    let cam_global = GlobalTransform::from(*cam_transform);
+
+    for (mut plate, mut style, mut visibility, children) in plates.iter_mut() {
+        let Ok((player_transform, player_health, is_target)) = players.get(plate.player) else {
+            continue;
+        };
+        let plate_position = player_transform.translation + Vec3::Y * PLATE_HEIGHT;
+        let screen_position = camera.world_to_viewport(&cam_global, plate_position);
+
+        let to_plate = plate_position - cam_transform.translation;
+        let distance = to_plate.length();
+        // Whoever camera follows is right in front of us, he doesnt need a plate
+        let mut target_alpha = if is_target || player_health.is_dead() || screen_position.is_none()
+        {
+            0.0
+        } else {
+            1.0 - ((distance - FADE_START) / (FADE_END - FADE_START)).clamp(0.0, 1.0)
+        };
+        // Level geometry between us hides it
+        if target_alpha > 0.0 {
+            if let Ok(direction) = Dir3::new(to_plate) {
+                let occluded = spatial_query
+                    .cast_ray(
+                        cam_transform.translation,
+                        direction,
+                        distance,
+                        true,
+                        SpatialQueryFilter::from_mask(GameLayer::Ground),
+                    )
+                    .is_some();
+                if occluded {
+                    target_alpha = 0.0;
+                }
+            }
+        }
+        let blend = 1.0 - (-FADE_SPEED * time.delta_seconds()).exp();
+        plate.alpha += (target_alpha - plate.alpha) * blend;
+
+        let Some(screen_position) = screen_position else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        if plate.alpha < 0.01 {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        *visibility = Visibility::Inherited;
+        style.left = Val::Px(screen_position.x - PLATE_WIDTH / 2.0);
+        style.top = Val::Px(screen_position.y);
+
+        let alpha = plate.alpha;
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].style.color = TEXT_COLOR.with_alpha(alpha);
+            }
+            if let Ok(mut background) = bar_backgrounds.get_mut(*child) {
+                background.0 = BAR_BACKGROUND.with_alpha(alpha * 0.7);
+            }
+            for bar_child in children_entities.iter_descendants(*child) {
+                if let Ok((mut bar_style, mut bar_color)) = bars.get_mut(bar_child) {
+                    let health = player_health.0.clamp(0, MAX_HEALTH);
+                    bar_style.width = Val::Percent(100.0 * health as f32 / MAX_HEALTH as f32);
+                    bar_color.0 = HEALTH_COLOR.with_alpha(alpha);
+                }
+            }
+        }
+    }
+}
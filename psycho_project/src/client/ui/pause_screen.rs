@@ -1,7 +1,9 @@
 use crate::client::load_assets::Images;
 use crate::client::rtt::{spawn_rtt_orbit_camera, RttImages};
+use crate::client::ui::loadout_panel::spawn_loadout_panel;
 use crate::client::MyAppState;
 use crate::shared::protocol::lobby_structs::StartGame;
+use crate::shared::shared_parts::{PartCatalog, PartDefinition, PartSlot, UnlockRequirement};
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 use bevy::{
@@ -54,55 +56,26 @@ pub enum VisualToChange {
 }
 impl Default for VisualToChange {
     fn default() -> Self {
-        VisualToChange::Head(vec![
-            ImageVisualInfo::new(
-                "images/default.png".to_string(),
-                "characters/parts/suit_head.glb".to_string(),
-            ),
-            ImageVisualInfo::new(
-                "images/default.png".to_string(),
-                "characters/parts/soldier_head.glb".to_string(),
-            ),
-        ])
+        VisualToChange::Head(Vec::new())
     }
 }
-// This will ensure you only see a portion of the given images - TODO- MAKE IT IMAGES OF SCENES
+// Which parts show up comes from the part catalog - TODO- MAKE IT IMAGES OF SCENES
 impl VisualToChange {
-    pub fn default_head() -> Self {
-        VisualToChange::Head(vec![
-            ImageVisualInfo::new(
-                "images/default.png".to_string(),
-                "characters/parts/suit_head.glb".to_string(),
-            ),
-            ImageVisualInfo::new(
-                "images/shatur.png".to_string(),
-                "characters/parts/soldier_head.glb".to_string(),
-            ),
-        ])
+    pub fn head(catalog: &PartCatalog) -> Self {
+        VisualToChange::Head(ImageVisualInfo::from_slot(catalog, PartSlot::Head))
     }
-    pub fn default_torso() -> Self {
-        VisualToChange::Torso(vec![
-            ImageVisualInfo::new(
-                "images/default.png".to_string(),
-                "characters/parts/scifi_torso.glb".to_string(),
-            ),
-            ImageVisualInfo::new(
-                "images/default.png".to_string(),
-                "characters/parts/soldier_torso.glb".to_string(),
-            ),
-        ])
+    pub fn torso(catalog: &PartCatalog) -> Self {
+        VisualToChange::Torso(ImageVisualInfo::from_slot(catalog, PartSlot::Torso))
     }
-    pub fn default_legs() -> Self {
-        VisualToChange::Legs(vec![
-            ImageVisualInfo::new(
-                "images/shatur.png".to_string(),
-                "characters/parts/witch_legs.glb".to_string(),
-            ),
-            ImageVisualInfo::new(
-                "images/default.png".to_string(),
-                "characters/parts/soldier_legs.glb".to_string(),
-            ),
-        ])
+    pub fn legs(catalog: &PartCatalog) -> Self {
+        VisualToChange::Legs(ImageVisualInfo::from_slot(catalog, PartSlot::Legs))
+    }
+    pub fn slot(&self) -> PartSlot {
+        match self {
+            VisualToChange::Head(_) => PartSlot::Head,
+            VisualToChange::Torso(_) => PartSlot::Torso,
+            VisualToChange::Legs(_) => PartSlot::Legs,
+        }
     }
 }
 
@@ -113,15 +86,26 @@ pub struct ImageVisualInfo {
     pub image: String,
     // File path to visual asset
     pub asset: String,
+    // Name shown to player
+    pub name: String,
+    // Owned parts are locked until server says we have them
+    pub unlock: UnlockRequirement,
 }
 
 impl ImageVisualInfo {
-    fn new(image: String, asset: String) -> Self {
+    fn new(part: &PartDefinition) -> Self {
         return Self {
-            image: image,
-            asset: asset,
+            image: part.thumbnail.clone(),
+            asset: part.mesh.clone(),
+            name: part.display_name.clone(),
+            unlock: part.unlock,
         };
     }
+
+    // Helper grabs every part of a slot
+    fn from_slot(catalog: &PartCatalog, slot: PartSlot) -> Vec<Self> {
+        catalog.of_slot(slot).map(ImageVisualInfo::new).collect()
+    }
 }
 
 /// Placedholder on where to put our ui image and what ui image to put grab via file path()
@@ -134,7 +118,12 @@ struct ScrollingList {
     position: f32,
 }
 
-fn pause_screen(asset_server: Res<AssetServer>, images: Res<Images>, mut commands: Commands) {
+fn pause_screen(
+    asset_server: Res<AssetServer>,
+    images: Res<Images>,
+    catalog: Res<PartCatalog>,
+    mut commands: Commands,
+) {
     let image_button_style = Style {
         width: Val::Px(250.0),
         height: Val::Px(200.0),
@@ -210,7 +199,7 @@ fn pause_screen(asset_server: Res<AssetServer>, images: Res<Images>, mut command
                                                 border_color: BorderColor(Color::BLACK),
                                                 ..default()
                                             },
-                                            VisualToChange::default_head(),
+                                            VisualToChange::head(&catalog),
                                         ))
                                         .with_children(|parent| {
                                             parent.spawn((
@@ -258,7 +247,7 @@ fn pause_screen(asset_server: Res<AssetServer>, images: Res<Images>, mut command
                                                 border_color: BorderColor(Color::BLACK),
                                                 ..default()
                                             },
-                                            VisualToChange::default_torso(),
+                                            VisualToChange::torso(&catalog),
                                         ))
                                         .with_children(|parent| {
                                             parent.spawn((
@@ -306,7 +295,7 @@ fn pause_screen(asset_server: Res<AssetServer>, images: Res<Images>, mut command
                                                 border_color: BorderColor(Color::BLACK),
                                                 ..default()
                                             },
-                                            VisualToChange::default_legs(),
+                                            VisualToChange::legs(&catalog),
                                         ))
                                         .with_children(|parent| {
                                             parent.spawn((
@@ -330,6 +319,9 @@ fn pause_screen(asset_server: Res<AssetServer>, images: Res<Images>, mut command
                                 });
                         });
 
+                    // Loadout presets
+                    spawn_loadout_panel(parent, &asset_server);
+
                     // Title for scrolling list
                     parent.spawn(TextBundle::from_section(
                         "WHO IS FIGHTING",
@@ -0,0 +1,164 @@
+//! Pieces settings and controls screens are both made of, a full screen column, labeled rows and menu buttons at the bottom
+use bevy::prelude::*;
+
+pub const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+pub const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+
+/// Text and button style every settings like screen uses
+#[derive(Clone)]
+pub struct SettingsStyle {
+    pub text: TextStyle,
+    pub button: Style,
+}
+
+impl SettingsStyle {
+    pub fn new(asset_server: &AssetServer) -> Self {
+        Self {
+            text: TextStyle {
+                font: asset_server.load("grafitti.ttf"),
+                font_size: 30.0,
+                color: TEXT_COLOR,
+            },
+            button: Style {
+                width: Val::Px(200.0),
+                padding: UiRect::all(Val::Px(5.0)),
+                margin: UiRect::all(Val::Px(5.0)),
+                border: UiRect::all(Val::Px(5.0)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+        }
+    }
+
+    /// Full screen column with a title on top, despawning the screen marker takes everything with it
+    pub fn spawn_screen(
+        &self,
+        commands: &mut Commands,
+        title: &str,
+        screen_marker: impl Component,
+        spawn_children: impl FnOnce(&mut ChildBuilder),
+    ) {
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: Color::srgb(0.10, 0.10, 0.10).into(),
+                    ..default()
+                },
+                screen_marker,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    title,
+                    TextStyle {
+                        font_size: 60.0,
+                        ..self.text.clone()
+                    },
+                ));
+                spawn_children(parent);
+            });
+    }
+
+    pub fn button_bundle(&self, style: Style) -> ButtonBundle {
+        ButtonBundle {
+            style,
+            border_color: BorderColor(Color::BLACK),
+            background_color: NORMAL_BUTTON.into(),
+            ..default()
+        }
+    }
+
+    /// Label on the left and whatever the row needs after it
+    pub fn spawn_row(
+        &self,
+        parent: &mut ChildBuilder,
+        label: &str,
+        spawn_content: impl FnOnce(&mut ChildBuilder),
+    ) {
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn(
+                    TextBundle::from_section(label, self.text.clone()).with_style(Style {
+                        width: Val::Px(250.0),
+                        ..default()
+                    }),
+                );
+                spawn_content(parent);
+            });
+    }
+
+    /// Row that shows a value between a < and a > button, arrows get -1.0 and 1.0 as direction
+    pub fn spawn_stepper_row<B: Component>(
+        &self,
+        parent: &mut ChildBuilder,
+        label: &str,
+        value_width: f32,
+        arrow_button: impl Fn(f32) -> B,
+        value_text: impl Component,
+    ) {
+        self.spawn_row(parent, label, |parent| {
+            self.spawn_arrow(parent, "<", arrow_button(-1.0));
+            parent.spawn((
+                TextBundle::from_section("", self.text.clone()).with_style(Style {
+                    width: Val::Px(value_width),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                }),
+                value_text,
+            ));
+            self.spawn_arrow(parent, ">", arrow_button(1.0));
+        });
+    }
+
+    fn spawn_arrow(&self, parent: &mut ChildBuilder, label: &str, arrow_button: impl Component) {
+        let style = Style {
+            width: Val::Px(50.0),
+            ..self.button.clone()
+        };
+        parent
+            .spawn((self.button_bundle(style), arrow_button))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(label, self.text.clone()));
+            });
+    }
+
+    /// Row of buttons at the bottom of the screen, like save and back
+    pub fn spawn_menu_buttons<B: Component>(
+        &self,
+        parent: &mut ChildBuilder,
+        buttons: impl IntoIterator<Item = (&'static str, B)>,
+    ) {
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                for (label, menu_button) in buttons {
+                    parent
+                        .spawn((self.button_bundle(self.button.clone()), menu_button))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(label, self.text.clone()));
+                        });
+                }
+            });
+    }
+}
@@ -0,0 +1,232 @@
+//! Screen where player tweaks window, camera and audio, changes only apply and get saved when he hits save
+use crate::client::settings::{AvailableResolutions, Settings};
+use crate::client::ui::main_screen::ScreenMainMenu;
+use crate::client::ui::settings_rows::SettingsStyle;
+use crate::client::MyAppState;
+use bevy::prelude::*;
+use bevy::window::WindowMode;
+
+/// Window modes we let the player pick, in the order arrows go through them
+const WINDOW_MODES: [WindowMode; 3] = [
+    WindowMode::Windowed,
+    WindowMode::BorderlessFullscreen,
+    WindowMode::Fullscreen,
+];
+
+pub struct SettingsScreenPlugin;
+
+impl Plugin for SettingsScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(MyAppState::Settings),
+            (despawn_main_menu, settings_screen),
+        );
+        app.add_systems(OnExit(MyAppState::Settings), despawn_settings_screen);
+        app.add_systems(
+            Update,
+            (setting_buttons, settings_menu_buttons, update_setting_texts)
+                .chain()
+                .run_if(in_state(MyAppState::Settings)),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct ScreenSettings;
+
+// Button that moves a setting up or down
+#[derive(Component)]
+struct SettingButton(SettingRow, f32);
+
+// Text that displays the value of a setting
+#[derive(Component)]
+struct SettingText(SettingRow);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SettingRow {
+    Resolution,
+    WindowMode,
+    Vsync,
+    Sensitivity,
+    Fov,
+    MasterVolume,
+}
+
+impl SettingRow {
+    const ALL: [Self; 6] = [
+        Self::Resolution,
+        Self::WindowMode,
+        Self::Vsync,
+        Self::Sensitivity,
+        Self::Fov,
+        Self::MasterVolume,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Resolution => "Resolution",
+            Self::WindowMode => "Window",
+            Self::Vsync => "Vsync",
+            Self::Sensitivity => "Mouse speed",
+            Self::Fov => "Fov",
+            Self::MasterVolume => "Master volume",
+        }
+    }
+
+    fn value(&self, settings: &Settings) -> String {
+        match self {
+            Self::Resolution => format!("{}x{}", settings.resolution.x, settings.resolution.y),
+            Self::WindowMode => match settings.window_mode {
+                WindowMode::Windowed => "Windowed",
+                WindowMode::BorderlessFullscreen => "Borderless",
+                WindowMode::SizedFullscreen | WindowMode::Fullscreen => "Fullscreen",
+            }
+            .to_string(),
+            Self::Vsync => if settings.vsync { "On" } else { "Off" }.to_string(),
+            Self::Sensitivity => format!("{:.2}", settings.mouse_sensitivity),
+            Self::Fov => format!("{:.0}", settings.fov),
+            Self::MasterVolume => format!("{:.0}%", settings.master_volume * 100.0),
+        }
+    }
+
+    /// Helper moves setting a step in the given direction, lists stop at their ends and vsync just toggles
+    fn step(&self, settings: &mut Settings, direction: f32, resolutions: &[UVec2]) {
+        match self {
+            Self::Resolution => {
+                // List is biggest first, so going up means going back in it
+                let index = resolutions
+                    .iter()
+                    .position(|resolution| *resolution == settings.resolution)
+                    .map(|index| (index as isize - direction as isize).max(0) as usize)
+                    .unwrap_or(0)
+                    .min(resolutions.len() - 1);
+                settings.resolution = resolutions[index];
+            }
+            Self::WindowMode => {
+                let index = WINDOW_MODES
+                    .iter()
+                    .position(|mode| *mode == settings.window_mode)
+                    .unwrap_or(0) as isize;
+                let len = WINDOW_MODES.len() as isize;
+                settings.window_mode =
+                    WINDOW_MODES[(index + direction as isize).rem_euclid(len) as usize];
+            }
+            Self::Vsync => settings.vsync = !settings.vsync,
+            Self::Sensitivity => {
+                settings.mouse_sensitivity =
+                    (settings.mouse_sensitivity + 0.05 * direction).clamp(0.05, 3.0);
+            }
+            Self::Fov => {
+                settings.fov = (settings.fov + 5.0 * direction).clamp(30.0, 100.0);
+            }
+            Self::MasterVolume => {
+                settings.master_volume = (settings.master_volume + 0.1 * direction).clamp(0.0, 1.0);
+            }
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+enum SettingsMenuButton {
+    Save,
+    Reset,
+    Back,
+}
+
+/// Settings being edited, only become the real ones on save
+#[derive(Resource)]
+struct EditingSettings(Settings);
+
+fn despawn_main_menu(main_menu: Query<Entity, With<ScreenMainMenu>>, mut commands: Commands) {
+    for screen in main_menu.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+}
+
+fn settings_screen(
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    commands.insert_resource(EditingSettings(settings.clone()));
+
+    let style = SettingsStyle::new(&asset_server);
+    style.spawn_screen(&mut commands, "SETTINGS", ScreenSettings, |parent| {
+        for row in SettingRow::ALL {
+            style.spawn_stepper_row(
+                parent,
+                row.label(),
+                200.0,
+                |direction| SettingButton(row, direction),
+                SettingText(row),
+            );
+        }
+        style.spawn_menu_buttons(
+            parent,
+            [
+                ("SAVE", SettingsMenuButton::Save),
+                ("DEFAULTS", SettingsMenuButton::Reset),
+                ("BACK", SettingsMenuButton::Back),
+            ],
+        );
+    });
+}
+
+fn despawn_settings_screen(screen: Query<Entity, With<ScreenSettings>>, mut commands: Commands) {
+    for screen in screen.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+    commands.remove_resource::<EditingSettings>();
+}
+
+fn setting_buttons(
+    interaction_query: Query<(&Interaction, &SettingButton), Changed<Interaction>>,
+    resolutions: Res<AvailableResolutions>,
+    mut editing: ResMut<EditingSettings>,
+) {
+    for (interaction, setting_button) in interaction_query.iter() {
+        if *interaction == Interaction::Pressed {
+            setting_button
+                .0
+                .step(&mut editing.0, setting_button.1, resolutions.list());
+        }
+    }
+}
+
+fn settings_menu_buttons(
+    interaction_query: Query<(&Interaction, &SettingsMenuButton), Changed<Interaction>>,
+    mut editing: ResMut<EditingSettings>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<MyAppState>>,
+) {
+    for (interaction, menu_button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match menu_button {
+            SettingsMenuButton::Save => {
+                *settings = editing.0.clone();
+                settings.save();
+                next_state.set(MyAppState::MainMenu);
+            }
+            SettingsMenuButton::Reset => {
+                editing.0 = Settings::default();
+            }
+            SettingsMenuButton::Back => {
+                next_state.set(MyAppState::MainMenu);
+            }
+        }
+    }
+}
+
+fn update_setting_texts(
+    editing: Res<EditingSettings>,
+    mut setting_texts: Query<(&SettingText, &mut Text)>,
+) {
+    if !editing.is_changed() {
+        return;
+    }
+    for (setting_text, mut text) in setting_texts.iter_mut() {
+        text.sections[0].value = setting_text.0.value(&editing.0);
+    }
+}
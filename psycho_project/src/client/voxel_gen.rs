@@ -1,174 +1,205 @@
-//! A simple voxel generator made only to make cute bevel like cubes no lod no nothing just cubes
+//! Builds the visual and physical chunks of the voxel terrain of my lobby, the data itself comes from shared voxel
+//! Server only sends the terrain settings so every client generates the exact same chunks, edits come on top of that
+use crate::client::essentials::{my_lobby_id, EasyClient};
+use crate::shared::protocol::lobby_structs::{Lobbies, LobbyPositionMap};
+use crate::shared::protocol::world_structs::TerrainEdits;
+use crate::shared::shared_physics::StaticPhysics;
+use crate::shared::shared_voxel::*;
+use avian3d::prelude::*;
 use bevy::prelude::*;
-use bevy::render::{
-    mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology,
-};
+use bevy::utils::{HashMap, HashSet};
+use lightyear::client::events::MessageEvent;
 
 pub struct VoxelGenPlugin;
 
 impl Plugin for VoxelGenPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_cube);
+        app.init_resource::<ClientTerrain>();
+
+        app.add_systems(Startup, create_voxel_material);
+        app.add_systems(
+            Update,
+            (
+                build_my_terrain,
+                receive_terrain_edits,
+                apply_pending_terrain_edits,
+            )
+                .chain(),
+        );
     }
 }
 
-fn spawn_cube(
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut commands: Commands,
-) {
-    let cube_mesh_handle: Handle<Mesh> = meshes.add(create_cube_mesh());
+/// Terrain of the lobby I am in
+#[derive(Resource, Default)]
+pub struct ClientTerrain {
+    pub lobby_id: Option<u64>,
+    pub terrain: Option<VoxelTerrain>,
+    // Edits that arrived before we had the terrain of their lobby, according to lobby id
+    pub pending_edits: HashMap<u64, Vec<VoxelEdit>>,
+}
 
-    commands
+/// Every chunk shares the same material, colors come from the vertices
+#[derive(Resource)]
+pub struct VoxelMaterial(pub Handle<StandardMaterial>);
+
+fn create_voxel_material(mut materials: ResMut<Assets<StandardMaterial>>, mut commands: Commands) {
+    let material = materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        perceptual_roughness: 0.9,
+        ..default()
+    });
+    commands.insert_resource(VoxelMaterial(material));
+}
+
+/// Helper spawns both mesh and collider of a chunk, returns none if chunk is only air
+pub fn spawn_chunk(
+    world: &VoxelWorld,
+    chunk_pos: IVec3,
+    material: &Handle<StandardMaterial>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    commands: &mut Commands,
+) -> Option<Entity> {
+    let chunk_mesh = world.mesh_chunk(chunk_pos);
+    let collider = chunk_mesh.collider()?;
+    let origin = chunk_origin(chunk_pos);
+
+    let entity = commands
         .spawn(PbrBundle {
-            mesh: cube_mesh_handle,
-            material: materials.add(StandardMaterial {
-                base_color: Color::WHITE,
-                ..default()
-            }),
+            mesh: meshes.add(chunk_mesh.to_mesh()),
+            material: material.clone(),
+            transform: Transform::from_translation(origin),
             ..default()
         })
-        .insert(Name::new("MyCube".to_string()));
+        .insert(StaticPhysics::new(collider))
+        .insert(Position(origin))
+        .insert(VoxelChunkMarker(chunk_pos))
+        .insert(Name::new(format!("VoxelChunk {}", chunk_pos)))
+        .id();
+    Some(entity)
 }
 
-/// Creates a simple cube mesh, but made and configured by us
-#[rustfmt::skip]
-fn create_cube_mesh() -> Mesh {
-    // Keep the mesh data accessible in future frames to be able to mutate it in toggle_texture.
-    Mesh::new(
-        PrimitiveTopology::TriangleList,
-        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
-    )
-    .with_inserted_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        // Each array is an [x, y, z] coordinate in local space.
-        // The camera coordinate space is right-handed x-right, y-up, z-back. This means "forward" is -Z.
-        // Meshes always rotate around their local [0, 0, 0] when a rotation is applied to their Transform.
-        // By centering our mesh around the origin, rotating the mesh preserves its center of mass.
-        vec![
-            // top (facing towards +y)
-            [-0.5, 0.5, -0.5], // vertex with index 0
-            [0.5, 0.5, -0.5],  // vertex with index 1
-            [0.5, 0.5, 0.5],   // etc. until 23
-            [-0.5, 0.5, 0.5],
-            // bottom   (-y)
-            [-0.5, -0.5, -0.5],
-            [0.5, -0.5, -0.5],
-            [0.5, -0.5, 0.5],
-            [-0.5, -0.5, 0.5],
-            // right    (+x)
-            [0.5, -0.5, -0.5],
-            [0.5, -0.5, 0.5],
-            [0.5, 0.5, 0.5], // This vertex is at the same position as vertex with index 2, but they'll have different UV and normal
-            [0.5, 0.5, -0.5],
-            // left     (-x)
-            [-0.5, -0.5, -0.5],
-            [-0.5, -0.5, 0.5],
-            [-0.5, 0.5, 0.5],
-            [-0.5, 0.5, -0.5],
-            // back     (+z)
-            [-0.5, -0.5, 0.5],
-            [-0.5, 0.5, 0.5],
-            [0.5, 0.5, 0.5],
-            [0.5, -0.5, 0.5],
-            // forward  (-z)
-            [-0.5, -0.5, -0.5],
-            [-0.5, 0.5, -0.5],
-            [0.5, 0.5, -0.5],
-            [0.5, -0.5, -0.5],
-        ],
-    )
-    // Set-up UV coordinates to point to the upper (V < 0.5), "dirt+grass" part of the texture.
-    // Take a look at the custom image (assets/textures/array_texture.png)
-    // so the UV coords will make more sense
-    // Note: (0.0, 0.0) = Top-Left in UV mapping, (1.0, 1.0) = Bottom-Right in UV mapping
-    .with_inserted_attribute(
-        Mesh::ATTRIBUTE_UV_0,
-        vec![
-            // Assigning the UV coords for the top side.
-            [0.0, 0.2],
-            [0.0, 0.0],
-            [1.0, 0.0],
-            [1.0, 0.2],
-            // Assigning the UV coords for the bottom side.
-            [0.0, 0.45],
-            [0.0, 0.25],
-            [1.0, 0.25],
-            [1.0, 0.45],
-            // Assigning the UV coords for the right side.
-            [1.0, 0.45],
-            [0.0, 0.45],
-            [0.0, 0.2],
-            [1.0, 0.2],
-            // Assigning the UV coords for the left side.
-            [1.0, 0.45],
-            [0.0, 0.45],
-            [0.0, 0.2],
-            [1.0, 0.2],
-            // Assigning the UV coords for the back side.
-            [0.0, 0.45],
-            [0.0, 0.2],
-            [1.0, 0.2],
-            [1.0, 0.45],
-            // Assigning the UV coords for the forward side.
-            [0.0, 0.45],
-            [0.0, 0.2],
-            [1.0, 0.2],
-            [1.0, 0.45],
-        ],
-    )
-    // For meshes with flat shading, normals are orthogonal (pointing out) from the direction of
-    // the surface.
-    // Normals are required for correct lighting calculations.
-    // Each array represents a normalized vector, which length should be equal to 1.0.
-    .with_inserted_attribute(
-        Mesh::ATTRIBUTE_NORMAL,
-        vec![
-            // Normals for the top side (towards +y)
-            [0.0, 1.0, 0.0],
-            [0.0, 1.0, 0.0],
-            [0.0, 1.0, 0.0],
-            [0.0, 1.0, 0.0],
-            // Normals for the bottom side (towards -y)
-            [0.0, -1.0, 0.0],
-            [0.0, -1.0, 0.0],
-            [0.0, -1.0, 0.0],
-            [0.0, -1.0, 0.0],
-            // Normals for the right side (towards +x)
-            [1.0, 0.0, 0.0],
-            [1.0, 0.0, 0.0],
-            [1.0, 0.0, 0.0],
-            [1.0, 0.0, 0.0],
-            // Normals for the left side (towards -x)
-            [-1.0, 0.0, 0.0],
-            [-1.0, 0.0, 0.0],
-            [-1.0, 0.0, 0.0],
-            [-1.0, 0.0, 0.0],
-            // Normals for the back side (towards +z)
-            [0.0, 0.0, 1.0],
-            [0.0, 0.0, 1.0],
-            [0.0, 0.0, 1.0],
-            [0.0, 0.0, 1.0],
-            // Normals for the forward side (towards -z)
-            [0.0, 0.0, -1.0],
-            [0.0, 0.0, -1.0],
-            [0.0, 0.0, -1.0],
-            [0.0, 0.0, -1.0],
-        ],
-    )
-    // Create the triangles out of the 24 vertices we created.
-    // To construct a square, we need 2 triangles, therefore 12 triangles in total.
-    // To construct a triangle, we need the indices of its 3 defined vertices, adding them one
-    // by one, in a counter-clockwise order (relative to the position of the viewer, the order
-    // should appear counter-clockwise from the front of the triangle, in this case from outside the cube).
-    // Read more about how to correctly build a mesh manually in the Bevy documentation of a Mesh,
-    // further examples and the implementation of the built-in shapes.
-    .with_inserted_indices(Indices::U32(vec![
-        0, 3, 1, 1, 3, 2, // triangles making up the top (+y) facing side.
-        4, 5, 7, 5, 6, 7, // bottom (-y)
-        8, 11, 9, 9, 11, 10, // right (+x)
-        12, 13, 15, 13, 14, 15, // left (-x)
-        16, 19, 17, 17, 19, 18, // back (+z)
-        20, 21, 23, 21, 22, 23, // forward (-z)
-    ]))
+/// Generates my lobby terrain whenever the settings of it change
+fn build_my_terrain(
+    lobbies: Res<Lobbies>,
+    lobby_position_map: Res<LobbyPositionMap>,
+    easy_client: Option<Res<EasyClient>>,
+    voxel_material: Res<VoxelMaterial>,
+    mut client_terrain: ResMut<ClientTerrain>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut commands: Commands,
+) {
+    if !lobbies.is_changed() && !lobby_position_map.is_changed() {
+        return;
+    }
+
+    let lobby_id = my_lobby_id(&lobbies, &lobby_position_map, &easy_client);
+    let settings = lobby_id
+        .and_then(|lobby_id| lobbies.find(lobby_id))
+        .and_then(|lobby| lobby.terrain.clone());
+
+    let current_settings = client_terrain
+        .terrain
+        .as_ref()
+        .map(|terrain| terrain.settings.clone());
+    if client_terrain.lobby_id == lobby_id && current_settings == settings {
+        return;
+    }
+
+    if let Some(old_terrain) = client_terrain.terrain.take() {
+        info!("Despawning old terrain");
+        for entity in old_terrain.chunk_entities.values() {
+            commands.entity(*entity).despawn_recursive();
+        }
+    }
+    client_terrain.lobby_id = lobby_id;
+
+    let Some(settings) = settings else {
+        return;
+    };
+
+    info!("Generating terrain with seed {}", settings.seed);
+    let mut terrain = VoxelTerrain::new(&settings);
+    let chunk_positions: Vec<IVec3> = terrain.world.chunks.keys().copied().collect();
+    for chunk_pos in chunk_positions {
+        if let Some(entity) = spawn_chunk(
+            &terrain.world,
+            chunk_pos,
+            &voxel_material.0,
+            &mut meshes,
+            &mut commands,
+        ) {
+            terrain.chunk_entities.insert(chunk_pos, entity);
+        }
+    }
+    client_terrain.terrain = Some(terrain);
+}
+
+/// Helper remakes mesh and collider of the given chunks
+fn rebuild_chunks(
+    terrain: &mut VoxelTerrain,
+    dirty_chunks: HashSet<IVec3>,
+    material: &Handle<StandardMaterial>,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    commands: &mut Commands,
+) {
+    for chunk_pos in dirty_chunks {
+        if let Some(old_entity) = terrain.chunk_entities.remove(&chunk_pos) {
+            commands.entity(old_entity).despawn_recursive();
+        }
+        if let Some(entity) = spawn_chunk(&terrain.world, chunk_pos, material, meshes, commands) {
+            terrain.chunk_entities.insert(chunk_pos, entity);
+        }
+    }
+}
+
+/// Edits always wait in pending, that way it doesnt matter if they arrive before or after the lobby settings
+fn receive_terrain_edits(
+    mut events: EventReader<MessageEvent<TerrainEdits>>,
+    mut client_terrain: ResMut<ClientTerrain>,
+) {
+    for event in events.read() {
+        let terrain_edits = event.message();
+        debug!(
+            "Received {} terrain edits for lobby {}",
+            terrain_edits.edits.len(),
+            terrain_edits.lobby_id
+        );
+        client_terrain
+            .pending_edits
+            .entry(terrain_edits.lobby_id)
+            .or_default()
+            .extend(terrain_edits.edits.iter().cloned());
+    }
+}
+
+/// Applies edits of my lobby once it is terrain exists, only chunks that changed get rebuilt
+fn apply_pending_terrain_edits(
+    voxel_material: Res<VoxelMaterial>,
+    mut client_terrain: ResMut<ClientTerrain>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut commands: Commands,
+) {
+    if client_terrain.pending_edits.is_empty() {
+        return;
+    }
+    let Some(lobby_id) = client_terrain.lobby_id else {
+        return;
+    };
+
+    let client_terrain = client_terrain.as_mut();
+    let Some(terrain) = client_terrain.terrain.as_mut() else {
+        return;
+    };
+    let Some(edits) = client_terrain.pending_edits.remove(&lobby_id) else {
+        return;
+    };
+
+    let dirty_chunks = terrain.apply_edits(&edits);
+    rebuild_chunks(
+        terrain,
+        dirty_chunks,
+        &voxel_material.0,
+        &mut meshes,
+        &mut commands,
+    );
 }
@@ -1,6 +1,9 @@
+use crate::client::essentials::{my_lobby_id, EasyClient};
+use crate::client::player::camera::MarkerMainCamera;
 use crate::shared::{
-    protocol::world_structs::{CycleTimer, FloorMarker},
-    shared_physics::{FloorPhysics, FLOOR_HEIGHT, FLOOR_WIDTH},
+    protocol::lobby_structs::{Lobbies, LobbyPositionMap},
+    protocol::world_structs::{LobbyTime, MapCollider, MapProp, TimeOfDay},
+    shared_physics::StaticPhysics,
 };
 use avian3d::prelude::*;
 use bevy::pbr::CascadeShadowConfigBuilder;
@@ -14,9 +17,6 @@ pub struct PhysicalWorldPlugin;
 
 impl Plugin for PhysicalWorldPlugin {
     fn build(&self, app: &mut App) {
-        // Adding replicated resource from server that defines my sun position
-        app.insert_resource(CycleTimer::default());
-
         // Set up visual interp plugins for Position and Rotation. This doesn't
         // do anything until you add VisualInterpolationStatus components to
         // entities.
@@ -28,8 +28,8 @@ impl Plugin for PhysicalWorldPlugin {
 
         // Systems related to non physical world
         app.add_systems(Startup, spawn_sun);
-        app.add_systems(Update, add_cosmetic_physics_floor);
-        app.add_systems(Update, orbit_around_point);
+        app.add_systems(Update, (add_cosmetic_map_colliders, add_cosmetic_map_props));
+        app.add_systems(Update, (orbit_around_point, apply_environment));
     }
 }
 
@@ -46,19 +46,25 @@ const MIN_ILUMINANCE: f32 = 400.0;
 
 const MAX_ILUMINANCE: f32 = 10000.0;
 
+/// Helper gives me the time of day of the lobby I am in
+fn my_lobby_time(
+    time_of_day: &TimeOfDay,
+    lobbies: &Lobbies,
+    lobby_position_map: &LobbyPositionMap,
+    easy_client: &Option<Res<EasyClient>>,
+) -> Option<(u64, LobbyTime)> {
+    let lobby_id = my_lobby_id(lobbies, lobby_position_map, easy_client)?;
+
+    time_of_day
+        .of_lobby(lobby_id)
+        .map(|lobby_time| (lobby_id, *lobby_time))
+}
+
 /// This guy will add visual interpolation component to anyone that is not confirmed. or predicted
 /// Basically made to avoid stuttering
 fn add_visual_interpolation_components<T: Component>(
     trigger: Trigger<OnAdd, T>,
-    query: Query<
-        Entity,
-        (
-            With<T>,
-            With<Predicted>,
-            Without<Confirmed>,
-            Without<FloorMarker>,
-        ),
-    >,
+    query: Query<Entity, (With<T>, With<Predicted>, Without<Confirmed>)>,
     mut commands: Commands,
 ) {
     if !query.contains(trigger.entity()) {
@@ -79,26 +85,48 @@ fn add_visual_interpolation_components<T: Component>(
         });
 }
 
-/// Adds cosmeticc to the replicated floor entity given by server also adds it physics
-fn add_cosmetic_physics_floor(
-    floor: Query<Entity, (Added<Replicated>, With<FloorMarker>)>,
+/// Adds cosmetic to the replicated map colliders given by server also adds their physics
+fn add_cosmetic_map_colliders(
+    map_colliders: Query<(Entity, &MapCollider, &Position, &Rotation), Added<Replicated>>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    if let Ok(floor) = floor.get_single() {
-        info!("Spawning physical floor and adding it is cosmetic");
+    for (entity, map_collider, position, rotation) in map_colliders.iter() {
+        info!("Spawning map collider and adding it is cosmetic");
         commands
-            .entity(floor)
-            .insert(FloorPhysics::default())
+            .entity(entity)
+            .insert(StaticPhysics::new(map_collider.shape.collider()))
             .insert(PbrBundle {
-                mesh: meshes.add(Cuboid::new(FLOOR_WIDTH, FLOOR_HEIGHT, FLOOR_WIDTH)),
-                material: materials.add(Color::srgb(0.0, 0.0, 0.0)),
+                mesh: meshes.add(map_collider.shape.mesh()),
+                material: materials.add(map_collider.color),
+                transform: Transform::from_translation(position.0).with_rotation(rotation.0),
                 ..default()
             });
     }
 }
 
+/// Adds the scene of replicated props
+fn add_cosmetic_map_props(
+    map_props: Query<(Entity, &MapProp, &Position, &Rotation), Added<Replicated>>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    for (entity, map_prop, position, rotation) in map_props.iter() {
+        info!("Spawning map prop {}", map_prop.scene);
+        commands.entity(entity).insert(SceneBundle {
+            scene: asset_server.load(GltfAssetLabel::Scene(0).from_asset(map_prop.scene.clone())),
+            transform: Transform::from_translation(position.0).with_rotation(rotation.0),
+            ..default()
+        });
+        if let Some(shape) = &map_prop.collider {
+            commands
+                .entity(entity)
+                .insert(StaticPhysics::new(shape.collider()));
+        }
+    }
+}
+
 /// Forms sun
 fn spawn_sun(
     mut commands: Commands,
@@ -131,17 +159,21 @@ fn spawn_sun(
         .insert(SunMarker);
 }
 
-/// Orbits sun
+/// Orbits sun according to the time of day of my lobby
 fn orbit_around_point(
     mut query: Query<(&mut Transform, &mut DirectionalLight), With<SunMarker>>,
-    // time: Res<Time>,
-    cycle_time: Res<CycleTimer>,
+    time_of_day: Res<TimeOfDay>,
+    lobbies: Res<Lobbies>,
+    lobby_position_map: Res<LobbyPositionMap>,
+    easy_client: Option<Res<EasyClient>>,
 ) {
+    let Some((_, lobby_time)) =
+        my_lobby_time(&time_of_day, &lobbies, &lobby_position_map, &easy_client)
+    else {
+        return;
+    };
     for (mut transform, mut directional_light) in query.iter_mut() {
-        let cycle_fraction = cycle_time.0.elapsed_secs() / cycle_time.0.duration().as_secs_f32();
-
-        // Calculate the max angle
-        let angle = cycle_fraction * std::f32::consts::PI * 2.0;
+        let angle = lobby_time.sun_angle;
 
         // Calculate the new target position using trigonometric functions
         let target_position = Vec3::new(
@@ -164,3 +196,53 @@ fn orbit_around_point(
         }
     }
 }
+
+/// Blends ambient light and fog between the day and night values of my lobby
+fn apply_environment(
+    mut ambient_light: ResMut<AmbientLight>,
+    mut main_camera: Query<(Entity, Option<&mut FogSettings>), With<MarkerMainCamera>>,
+    time_of_day: Res<TimeOfDay>,
+    lobbies: Res<Lobbies>,
+    lobby_position_map: Res<LobbyPositionMap>,
+    easy_client: Option<Res<EasyClient>>,
+    mut commands: Commands,
+) {
+    let Some((lobby_id, lobby_time)) =
+        my_lobby_time(&time_of_day, &lobbies, &lobby_position_map, &easy_client)
+    else {
+        return;
+    };
+    let Some(lobby) = lobbies.find(lobby_id) else {
+        return;
+    };
+    let environment = &lobby.environment;
+    let daylight = lobby_time.daylight();
+
+    ambient_light.brightness = environment
+        .ambient_night
+        .lerp(environment.ambient_day, daylight);
+
+    let fog_color = environment
+        .fog_color_night
+        .mix(&environment.fog_color_day, daylight);
+    let fog_distance = environment
+        .fog_distance_night
+        .lerp(environment.fog_distance_day, daylight);
+    let falloff = FogFalloff::Linear {
+        start: fog_distance * 0.25,
+        end: fog_distance,
+    };
+
+    if let Ok((camera, fog)) = main_camera.get_single_mut() {
+        if let Some(mut fog) = fog {
+            fog.color = fog_color;
+            fog.falloff = falloff;
+        } else {
+            commands.entity(camera).insert(FogSettings {
+                color: fog_color,
+                falloff,
+                ..default()
+            });
+        }
+    }
+}
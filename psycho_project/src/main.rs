@@ -4,6 +4,7 @@ use crate::shared::SharedPlugin;
 use common::app::{Apps, Cli};
 use common::settings::{read_settings, Settings};
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 
 mod client;
 mod server;
@@ -16,6 +17,11 @@ fn main() {
     let settings_str = include_str!("../assets/settings.ron");
 
     let settings = read_settings::<MySettings>(settings_str);
+    // Same address clients connect to, so it must be the one players can reach
+    let public_game_addr = SocketAddr::new(
+        settings.common.client.server_addr.into(),
+        settings.common.client.server_port,
+    );
     let mut apps = Apps::new(settings.common, cli);
 
     apps.update_lightyear_client_config(|config| {
@@ -25,7 +31,11 @@ fn main() {
     // Adding multipler lightyear plugins
     apps.add_lightyear_plugins()
         // add our plugins
-        .add_user_plugins(ExampleClientPlugin, ExampleServerPlugin, SharedPlugin);
+        .add_user_plugins(
+            ExampleClientPlugin,
+            ExampleServerPlugin { public_game_addr },
+            SharedPlugin,
+        );
 
     // run the app
     apps.run();
@@ -0,0 +1,350 @@
+//! Local auth service, players log in or register here and get back a netcode connect token for the game server
+//! Tokens are signed with a key only the server knows, so clients cant make their own and join as somebody else
+//! Requests are plain tcp with the password inside, so the service only listens on loopback
+//! Until it gets tls, players on other machines need a tunnel that encrypts the way up to this machine
+use crate::server::essentials::start_server;
+use crate::shared::shared_auth::*;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bincode::{deserialize_from, serialize_into, Options};
+use lightyear::connection::netcode::{generate_key, ConnectToken, Key};
+use lightyear::prelude::server::{NetConfig, ServerConfig};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Where accounts and the key tokens are signed with are stored
+const ACCOUNTS_PATH: &str = "./psycho_project/src/server/save_files/accounts.bar";
+const SERVER_KEY_PATH: &str = "./psycho_project/src/server/save_files/server_key.bar";
+
+/// Passwords arrive unencrypted, so nobody outside of this machine gets to send them
+const AUTH_BIND_IP: Ipv4Addr = Ipv4Addr::LOCALHOST;
+
+/// How long a slow client can keep the auth service waiting
+const AUTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Threads answering requests, and how many connections can wait for one before new ones get dropped
+const AUTH_WORKERS: usize = 4;
+const AUTH_QUEUE: usize = 32;
+
+pub struct AuthPlugin {
+    pub public_game_addr: SocketAddr,
+}
+
+impl Plugin for AuthPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Accounts::load());
+        app.insert_resource(PublicGameAddr(self.public_game_addr));
+        // Secret key must be in server config before it starts
+        app.add_systems(Startup, start_auth_service.before(start_server));
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Account {
+    account_id: u64,
+    display_name: String,
+    // Argon2 hash with its salt and settings inside, slow on purpose so a leaked file is hard to guess from
+    password_hash: String,
+}
+
+/// Address clients reach the game server through, from settings file. Tokens only work for the address inside of them
+#[derive(Resource)]
+struct PublicGameAddr(SocketAddr);
+
+/// Every account according to lowercase username, shared with the auth service threads
+#[derive(Resource, Clone, Default)]
+pub struct Accounts(Arc<Mutex<HashMap<String, Account>>>);
+
+impl Accounts {
+    /// Reads accounts file, no file means nobody registered yet
+    /// Files we cant understand stop the server, as starting empty would throw every account away on the next register
+    fn load() -> Self {
+        let Ok(file) = File::open(ACCOUNTS_PATH) else {
+            warn!("No accounts file found starting with empty one");
+            return Self::default();
+        };
+        match deserialize_from::<_, HashMap<String, Account>>(BufReader::new(file)) {
+            Ok(accounts) => {
+                info!("Loaded {} accounts", accounts.len());
+                Self(Arc::new(Mutex::new(accounts)))
+            }
+            Err(err) => {
+                error!("Couldnt read accounts file {}: {}", ACCOUNTS_PATH, err);
+                panic!(
+                    "Accounts file {} is unreadable, fix or move it away before starting the server so no account gets overwritten",
+                    ACCOUNTS_PATH
+                );
+            }
+        }
+    }
+
+    /// Name players see for an account, none if nobody owns that id
+    pub fn display_name(&self, account_id: u64) -> Option<String> {
+        let accounts = self.0.lock().unwrap();
+        accounts
+            .values()
+            .find(|account| account.account_id == account_id)
+            .map(|account| account.display_name.clone())
+    }
+
+    /// Account id behind a username, for operators handing legacy profiles to accounts
+    pub fn account_id(&self, username: &str) -> Option<u64> {
+        let accounts = self.0.lock().unwrap();
+        accounts
+            .get(&username.to_lowercase())
+            .map(|account| account.account_id)
+    }
+
+    /// Hashing is slow, so it happens without holding the lock
+    fn login(&self, username: &str, password: &str) -> Result<Account, String> {
+        let account = self
+            .0
+            .lock()
+            .unwrap()
+            .get(&username.to_lowercase())
+            .cloned();
+        match account {
+            Some(account) if verify_password(password, &account.password_hash) => Ok(account),
+            // Same answer for both so nobody can fish for usernames
+            Some(_) => Err("Wrong username or password".to_string()),
+            None => {
+                // Hashing anyway takes as long as a wrong password, so timing doesnt give usernames away either
+                let _ = hash_password(password);
+                Err("Wrong username or password".to_string())
+            }
+        }
+    }
+
+    fn register(
+        &self,
+        username: &str,
+        password: &str,
+        display_name: &str,
+    ) -> Result<Account, String> {
+        if let Some(reason) = validate_credentials(username, password) {
+            return Err(reason);
+        }
+        let display_name = crate::shared::shared_auth::display_name(display_name);
+        if display_name.is_empty() {
+            return Err("Pick a display name".to_string());
+        }
+
+        let password_hash = hash_password(password)?;
+
+        let mut accounts = self.0.lock().unwrap();
+        let key = username.to_lowercase();
+        if accounts.contains_key(&key) {
+            return Err("Username is already taken".to_string());
+        }
+        // Zero is left out as lightyear uses it for the server
+        let mut account_id = rand::random::<u64>();
+        while account_id == 0
+            || accounts
+                .values()
+                .any(|account| account.account_id == account_id)
+        {
+            account_id = rand::random::<u64>();
+        }
+        let account = Account {
+            account_id,
+            display_name,
+            password_hash,
+        };
+        accounts.insert(key, account.clone());
+        save_accounts(&accounts);
+        Ok(account)
+    }
+}
+
+/// Hash comes back as a string with a fresh random salt inside of it
+fn hash_password(password: &str) -> Result<String, String> {
+    let salt =
+        SaltString::encode_b64(&rand::random::<[u8; 16]>()).map_err(|err| err.to_string())?;
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| {
+            error!("Couldnt hash password: {}", err);
+            "Couldnt create account, try again".to_string()
+        })
+}
+
+/// Argon2 compares hashes in constant time, so answer time doesnt tell how close a guess was
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    let Ok(password_hash) = PasswordHash::new(password_hash) else {
+        error!("Stored password hash is broken");
+        return false;
+    };
+    Argon2::default()
+        .verify_password(password.as_bytes(), &password_hash)
+        .is_ok()
+}
+
+fn save_accounts(accounts: &HashMap<String, Account>) {
+    info!("Saving accounts");
+    match File::create(ACCOUNTS_PATH) {
+        Ok(file) => {
+            if let Err(err) = serialize_into(&mut BufWriter::new(file), accounts) {
+                error!("Couldnt save accounts: {}", err);
+            }
+        }
+        Err(err) => error!("Couldnt create accounts file: {}", err),
+    }
+}
+
+/// Key is made once and kept, else every restart would invalidate tokens in flight
+fn server_key() -> Key {
+    if let Ok(file) = File::open(SERVER_KEY_PATH) {
+        match deserialize_from::<_, Key>(BufReader::new(file)) {
+            Ok(key) => return key,
+            Err(err) => error!("Couldnt read server key making a new one: {}", err),
+        }
+    }
+    info!("Generating new server key");
+    let key = generate_key();
+    match File::create(SERVER_KEY_PATH) {
+        Ok(file) => {
+            if let Err(err) = serialize_into(&mut BufWriter::new(file), &key) {
+                error!("Couldnt save server key: {}", err);
+            }
+        }
+        Err(err) => error!("Couldnt create server key file: {}", err),
+    }
+    key
+}
+
+/// Everything a connection thread needs to answer a request
+#[derive(Clone)]
+struct AuthContext {
+    accounts: Accounts,
+    key: Key,
+    protocol_id: u64,
+    public_game_addr: SocketAddr,
+}
+
+/// Puts our secret key in every netcode config and starts listening for logins
+fn start_auth_service(
+    mut server_config: ResMut<ServerConfig>,
+    accounts: Res<Accounts>,
+    public_game_addr: Res<PublicGameAddr>,
+) {
+    let key = server_key();
+    let mut protocol_id = 0;
+    for net_config in server_config.net.iter_mut() {
+        if let NetConfig::Netcode { config, .. } = net_config {
+            config.private_key = key;
+            protocol_id = config.protocol_id;
+        }
+    }
+
+    let listener = match TcpListener::bind((AUTH_BIND_IP, AUTH_PORT)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!("Couldnt start auth service on port {}: {}", AUTH_PORT, err);
+            return;
+        }
+    };
+    info!(
+        "Auth service listening on {}:{}, tokens point to {}",
+        AUTH_BIND_IP, AUTH_PORT, public_game_addr.0
+    );
+
+    let context = AuthContext {
+        accounts: accounts.clone(),
+        key,
+        protocol_id,
+        public_game_addr: public_game_addr.0,
+    };
+    // Few workers share the queue, so a slow client only holds one of them and a flood cant spawn endless threads
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(AUTH_QUEUE);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..AUTH_WORKERS {
+        let context = context.clone();
+        let receiver = receiver.clone();
+        thread::spawn(move || auth_worker(&receiver, &context));
+    }
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    warn!("Couldnt accept auth connection: {}", err);
+                    continue;
+                }
+            };
+            match sender.try_send(stream) {
+                Ok(_) => {}
+                Err(TrySendError::Full(_)) => warn!("Auth service is busy dropping connection"),
+                Err(TrySendError::Disconnected(_)) => {
+                    error!("Every auth worker stopped, auth service is closing");
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Answers queued connections one at a time until the queue closes
+fn auth_worker(receiver: &Mutex<Receiver<TcpStream>>, context: &AuthContext) {
+    loop {
+        // Lock is only held while waiting, so other workers answer while this one works
+        let Ok(stream) = receiver.lock().unwrap().recv() else {
+            return;
+        };
+        if let Err(err) = handle_auth_request(stream, context) {
+            warn!("Auth request failed: {}", err);
+        }
+    }
+}
+
+/// Reads one request and answers it, accepted ones get a token for the game server
+fn handle_auth_request(mut stream: TcpStream, context: &AuthContext) -> anyhow::Result<()> {
+    stream.set_read_timeout(Some(AUTH_TIMEOUT))?;
+    stream.set_write_timeout(Some(AUTH_TIMEOUT))?;
+    let request: AuthRequest = auth_bincode().deserialize_from(&mut stream)?;
+
+    let result = match request {
+        AuthRequest::Login { username, password } => {
+            info!("Login attempt for {}", username);
+            context.accounts.login(&username, &password)
+        }
+        AuthRequest::Register {
+            username,
+            password,
+            display_name,
+        } => {
+            info!("Register attempt for {}", username);
+            context
+                .accounts
+                .register(&username, &password, &display_name)
+        }
+    };
+
+    let response = match result {
+        Ok(account) => {
+            let token = ConnectToken::build(
+                context.public_game_addr,
+                context.protocol_id,
+                account.account_id,
+                context.key,
+            )
+            .generate()?;
+            AuthResponse::Accepted {
+                account_id: account.account_id,
+                display_name: account.display_name,
+                connect_token: token.try_into_bytes()?.to_vec(),
+            }
+        }
+        Err(reason) => AuthResponse::Rejected(reason),
+    };
+    auth_bincode().serialize_into(&mut stream, &response)?;
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! Commands typed into the server terminal, works on headless servers unlike the debug keys of the server window
+//! Console only splits lines into words, each plugin listens to console command and handles the ones it knows
+use bevy::prelude::*;
+use std::io::stdin;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread;
+
+/// How to use every command somebody handles, first word is the name. Unknown commands print them
+const CONSOLE_COMMANDS: [&str; 3] = [
+    "time <lobby_id> <fraction> - 0.0 is noon and 0.5 is midnight",
+    "speed <lobby_id> <speed> - how fast the day passes",
+    "claim <legacy_client_id> <username> - gives a profile from before accounts to that account",
+];
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ConsoleCommand>();
+        app.add_systems(Startup, start_console);
+        app.add_systems(
+            PreUpdate,
+            read_console_lines.run_if(resource_exists::<ConsoleLines>),
+        );
+    }
+}
+
+/// Line typed into server terminal, name is the first word and args the rest of them
+#[derive(Event, Debug, Clone)]
+pub struct ConsoleCommand {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl ConsoleCommand {
+    /// Helper parses an argument, tells whoever typed it what is wrong if it cant
+    pub fn arg<T: FromStr>(&self, index: usize, what: &str) -> Option<T> {
+        let parsed = self.args.get(index).and_then(|arg| arg.parse().ok());
+        if parsed.is_none() {
+            warn!(
+                "Command {} needs {} as argument {}",
+                self.name,
+                what,
+                index + 1
+            );
+        }
+        parsed
+    }
+}
+
+/// Lines the terminal thread read, waiting to become console commands
+#[derive(Resource)]
+struct ConsoleLines(Mutex<Receiver<String>>);
+
+/// Reading terminal blocks, so it happens in its own thread
+fn start_console(mut commands: Commands) {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in stdin().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    commands.insert_resource(ConsoleLines(Mutex::new(receiver)));
+    info!("Server console ready, type help to see commands");
+}
+
+fn read_console_lines(lines: Res<ConsoleLines>, mut console: EventWriter<ConsoleCommand>) {
+    let receiver = lines.0.lock().unwrap();
+    while let Ok(line) = receiver.try_recv() {
+        let mut words = line.split_whitespace().map(str::to_string);
+        let Some(name) = words.next().map(|name| name.to_lowercase()) else {
+            continue;
+        };
+        let known = CONSOLE_COMMANDS
+            .iter()
+            .any(|usage| usage.split_whitespace().next() == Some(name.as_str()));
+        if !known {
+            if name != "help" {
+                warn!("Unknown command {}", name);
+            }
+            for usage in CONSOLE_COMMANDS {
+                info!("{}", usage);
+            }
+            continue;
+        }
+        console.send(ConsoleCommand {
+            name,
+            args: words.collect(),
+        });
+    }
+}
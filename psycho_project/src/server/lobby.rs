@@ -1,11 +1,17 @@
 //! Basically all the events associated to lobby logic
+use crate::server::map::*;
 use crate::server::player::*;
 use crate::shared::protocol::lobby_structs::*;
 use crate::shared::protocol::CommonChannel;
 
+use avian3d::prelude::Position;
 use bevy::prelude::*;
 use lightyear::prelude::server::*;
 use lightyear::prelude::*;
+use lightyear::shared::tick_manager::TickManager;
+
+/// Map the major lobby plays in
+const MAJOR_LOBBY_MAP: &str = "white_world";
 
 /// Utilized to manage the super lobby and the lower end duel lobbies
 pub struct LobbyPlugin;
@@ -26,6 +32,7 @@ impl Plugin for LobbyPlugin {
 
         // Listens to event sent by client
         app.add_systems(Update, listener_join_lobby);
+        app.add_systems(Update, listener_spectate_lobby);
         app.add_systems(Update, listener_exit_lobby);
         app.add_systems(Update, listener_disconnect_event);
     }
@@ -38,34 +45,38 @@ fn replicate_resources(mut commands: Commands) {
 }
 
 /// Creates the major lobby for players also know as the white world
-fn creates_major_lobby(mut lobbies: ResMut<Lobbies>) {
+fn creates_major_lobby(mut lobbies: ResMut<Lobbies>, tick_manager: Res<TickManager>) {
     let mut lobby = Lobby::default();
 
     info!("Grabbing lobby id");
     let lobby_id = lobbies.lobbies.len() as u64;
     lobby.lobby_id = lobby_id;
+    lobby.map = MAJOR_LOBBY_MAP.to_string();
+
+    info!("Starting lobby sun cycle");
+    lobby.sun_cycle.epoch_tick = tick_manager.tick();
 
     info!("Creating lobby and replicating to others {}", lobby_id);
     lobbies.lobbies.push(lobby);
 }
 
-/// Helper patches up according to list of clietn id passed
+/// Helper patches up according to lobby passed, players predict each other and spectators interpolate them
 fn update_replication_targets(
     player: Entity,
     replication_target: &mut Query<(&mut ReplicationTarget, &mut SyncTarget)>,
-    all_players: &[ClientId],
+    lobby: &Lobby,
 ) {
     info!("Updating replication targets for player {}", player);
 
     if let Ok((mut replication, mut sync_target)) = replication_target.get_mut(player) {
         *replication = ReplicationTarget {
-            target: NetworkTarget::Only(all_players.to_vec()),
+            target: NetworkTarget::Only(lobby.everyone()),
             ..Default::default()
         };
 
         *sync_target = SyncTarget {
-            prediction: NetworkTarget::Only(all_players.to_vec()),
-            ..Default::default()
+            prediction: NetworkTarget::Only(lobby.players.clone()),
+            interpolation: NetworkTarget::Only(lobby.spectators.clone()),
         };
     } else {
         warn!(
@@ -75,13 +86,36 @@ fn update_replication_targets(
     }
 }
 
+/// Helper makes players and map of lobby visible to everyone currently in it
+fn refresh_lobby_replication(
+    lobby: &Lobby,
+    player_entity_map: &ServerPlayerEntityMap,
+    loaded_maps: &LoadedMaps,
+    replication_target: &mut Query<(&mut ReplicationTarget, &mut SyncTarget)>,
+) {
+    for all_client in lobby.players.iter() {
+        if let Some(player) = player_entity_map.0.get(all_client) {
+            update_replication_targets(*player, replication_target, lobby);
+        }
+    }
+
+    update_map_replication_targets(
+        lobby.lobby_id,
+        loaded_maps,
+        replication_target,
+        &lobby.everyone(),
+    );
+}
+
 /// Listening for clients that clicked the button start game - MAKE THIS AS LIGHT AS POSSIBLE
 fn listener_join_lobby(
     mut events: EventReader<MessageEvent<EnterLobby>>,
     mut replication_target: Query<(&mut ReplicationTarget, &mut SyncTarget)>,
+    mut positions: Query<&mut Position>,
     mut lobbies: ResMut<Lobbies>,
     mut lobby_position_map: ResMut<LobbyPositionMap>,
     player_entity_map: Res<ServerPlayerEntityMap>,
+    loaded_maps: Res<LoadedMaps>,
     mut connection_manager: ResMut<ConnectionManager>,
 ) {
     for event in events.read() {
@@ -90,6 +124,7 @@ fn listener_join_lobby(
         let lobby_id = lobby.lobby_id;
 
         info!("Inserted player {} unto lobby {}", client_id, lobby_id);
+        lobby.spectators.retain(|spectator| spectator != client_id);
         lobby.players.push(*client_id);
 
         let all_players = lobby.players.clone();
@@ -105,20 +140,86 @@ fn listener_join_lobby(
         lobby_position_map.0.insert(
             *client_id,
             ClientInfo {
+                lobby_id,
                 lobby_position: all_players.len() - 1,
                 lobby_without_me: lobby_without_me,
             },
         );
 
-        for all_client in lobbies.lobbies[0].players.iter() {
-            if let Some(player) = player_entity_map.0.get(all_client) {
-                update_replication_targets(*player, &mut replication_target, &all_players);
+        info!(
+            "Making lobby map and players visible to client {}",
+            client_id
+        );
+        refresh_lobby_replication(
+            &lobbies.lobbies[0],
+            &player_entity_map,
+            &loaded_maps,
+            &mut replication_target,
+        );
+
+        if let (Some(player), Some(spawn_point)) = (
+            player_entity_map.0.get(client_id),
+            loaded_maps.spawn_point(lobby_id, all_players.len() - 1),
+        ) {
+            if let Ok(mut position) = positions.get_mut(*player) {
+                info!(
+                    "Placing client {} at spawn point {}",
+                    client_id, spawn_point
+                );
+                position.0 = spawn_point;
             }
         }
 
         info!("Telling client id {} to start it is game", client_id);
-        let _ = connection_manager
-            .send_message::<CommonChannel, StartGame>(*client_id, &mut StartGame { lobby_id });
+        let _ = connection_manager.send_message::<CommonChannel, StartGame>(
+            *client_id,
+            &mut StartGame {
+                lobby_id,
+                spectator: false,
+            },
+        );
+    }
+}
+
+/// Spectators get lobby replicated as interpolated, they never get a player in lobby nor a lobby position
+/// that way nobody forwards inputs to them
+fn listener_spectate_lobby(
+    mut events: EventReader<MessageEvent<SpectateLobby>>,
+    mut replication_target: Query<(&mut ReplicationTarget, &mut SyncTarget)>,
+    mut lobbies: ResMut<Lobbies>,
+    lobby_position_map: Res<LobbyPositionMap>,
+    player_entity_map: Res<ServerPlayerEntityMap>,
+    loaded_maps: Res<LoadedMaps>,
+    mut connection_manager: ResMut<ConnectionManager>,
+) {
+    for event in events.read() {
+        let client_id = event.context();
+        if lobby_position_map.0.contains_key(client_id) {
+            warn!("Client {} is already playing, he cant spectate", client_id);
+            continue;
+        }
+
+        let lobby = &mut lobbies.lobbies[0];
+        let lobby_id = lobby.lobby_id;
+        if !lobby.spectators.contains(client_id) {
+            info!("Client {} is spectating lobby {}", client_id, lobby_id);
+            lobby.spectators.push(*client_id);
+        }
+
+        refresh_lobby_replication(
+            &lobbies.lobbies[0],
+            &player_entity_map,
+            &loaded_maps,
+            &mut replication_target,
+        );
+
+        let _ = connection_manager.send_message::<CommonChannel, StartGame>(
+            *client_id,
+            &mut StartGame {
+                lobby_id,
+                spectator: true,
+            },
+        );
     }
 }
 
@@ -126,13 +227,29 @@ fn listener_join_lobby(
 fn listener_exit_lobby(
     mut events: EventReader<MessageEvent<ExitLobby>>,
     mut online_state: Query<&mut PlayerStateConnection>,
+    mut replication_target: Query<(&mut ReplicationTarget, &mut SyncTarget)>,
     player_entity_map: Res<ServerPlayerEntityMap>,
+    loaded_maps: Res<LoadedMaps>,
     mut lobby_position_map: ResMut<LobbyPositionMap>,
     mut lobbies: ResMut<Lobbies>,
 ) {
     for event in events.read() {
         let client_id = event.context();
 
+        if lobbies.lobbies[0].spectators.contains(client_id) {
+            info!("Spectator {} left lobby", client_id);
+            lobbies.lobbies[0]
+                .spectators
+                .retain(|spectator| spectator != client_id);
+            refresh_lobby_replication(
+                &lobbies.lobbies[0],
+                &player_entity_map,
+                &loaded_maps,
+                &mut replication_target,
+            );
+            continue;
+        }
+
         if let Some(client_info) = lobby_position_map.0.remove(client_id) {
             info!("Removing client from lobby {}", client_id);
             // Safely remove the player from the `players` vector at the specified index
@@ -146,6 +263,14 @@ fn listener_exit_lobby(
                     client_info.lobby_position
                 );
             }
+
+            info!("Hiding lobby map from client {}", client_id);
+            update_map_replication_targets(
+                client_info.lobby_id,
+                &loaded_maps,
+                &mut replication_target,
+                &lobbies.lobbies[0].players,
+            );
         }
 
         if let Some(player_entity) = player_entity_map.0.get(client_id) {
@@ -173,6 +298,9 @@ fn listener_disconnect_event(
 ) {
     for event in events.read() {
         let client_id = event.client_id;
+        lobbies.lobbies[0]
+            .spectators
+            .retain(|spectator| *spectator != client_id);
         if let Some(client_info) = lobby_position_map.0.remove(&client_id) {
             // Safely remove the player from the `players` vector at the specified index
             if client_info.lobby_position < lobbies.lobbies[0].players.len() {
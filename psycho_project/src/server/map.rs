@@ -0,0 +1,212 @@
+//! Loads maps from data files and spawns their geometry for each lobby
+use crate::shared::protocol::lobby_structs::*;
+use crate::shared::protocol::world_structs::*;
+use crate::shared::shared_physics::StaticPhysics;
+use avian3d::prelude::*;
+use bevy::asset::ron;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use lightyear::prelude::server::*;
+use lightyear::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Folder where our maps are stored, each lobby points to a file name inside of it
+const MAPS_FOLDER: &str = "./psycho_project/assets/maps";
+
+/// Responsible for turning map files into replicated level geometry
+pub struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadedMaps>();
+
+        app.add_systems(Update, spawn_lobby_maps);
+    }
+}
+
+/// How a map is described in it is ron file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MapDefinition {
+    pub name: String,
+    pub colliders: Vec<MapColliderDefinition>,
+    pub spawn_points: Vec<Vec3>,
+    #[serde(default)]
+    pub props: Vec<MapPropDefinition>,
+    #[serde(default)]
+    pub terrain: Option<TerrainSettings>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MapColliderDefinition {
+    pub name: String,
+    pub shape: MapShape,
+    pub position: Vec3,
+    // Rotation around the y axis in radians
+    #[serde(default)]
+    pub yaw: f32,
+    pub color: Color,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MapPropDefinition {
+    pub name: String,
+    // File path to gltf scene
+    pub scene: String,
+    pub position: Vec3,
+    #[serde(default)]
+    pub yaw: f32,
+    #[serde(default)]
+    pub collider: Option<MapShape>,
+}
+
+/// Info of every map that was already spawned, according to lobby id
+#[derive(Resource, Default)]
+pub struct LoadedMaps(pub HashMap<u64, LoadedMap>);
+
+pub struct LoadedMap {
+    // Every replicated entity that makes this map
+    pub entities: Vec<Entity>,
+    pub spawn_points: Vec<Vec3>,
+}
+
+impl LoadedMaps {
+    /// Gives me the spawn point of a player according to his position in lobby
+    pub fn spawn_point(&self, lobby_id: u64, lobby_position: usize) -> Option<Vec3> {
+        let loaded_map = self.0.get(&lobby_id)?;
+        if loaded_map.spawn_points.is_empty() {
+            return None;
+        }
+        Some(loaded_map.spawn_points[lobby_position % loaded_map.spawn_points.len()])
+    }
+}
+
+/// Reads map file, if anything goes wrong we warn and return none
+fn read_map(map: &str) -> Option<MapDefinition> {
+    let path = format!("{}/{}.ron", MAPS_FOLDER, map);
+    let map_str = match fs::read_to_string(&path) {
+        Ok(map_str) => map_str,
+        Err(err) => {
+            error!("Couldnt read map file {}: {}", path, err);
+            return None;
+        }
+    };
+    match ron::de::from_str::<MapDefinition>(&map_str) {
+        Ok(map_definition) => Some(map_definition),
+        Err(err) => {
+            error!("Couldnt parse map file {}: {}", path, err);
+            None
+        }
+    }
+}
+
+/// Every lobby that doesnt have it is map spawned yet, gets it here
+fn spawn_lobby_maps(
+    mut lobbies: ResMut<Lobbies>,
+    mut loaded_maps: ResMut<LoadedMaps>,
+    mut commands: Commands,
+) {
+    // Avoid triggering change detection when every map is already loaded
+    if lobbies
+        .lobbies
+        .iter()
+        .all(|lobby| loaded_maps.0.contains_key(&lobby.lobby_id))
+    {
+        return;
+    }
+
+    for lobby in lobbies.lobbies.iter_mut() {
+        if loaded_maps.0.contains_key(&lobby.lobby_id) {
+            continue;
+        }
+
+        info!("Loading map {} for lobby {}", lobby.map, lobby.lobby_id);
+        let Some(map_definition) = read_map(&lobby.map) else {
+            // Avoid trying to read a broken file every frame
+            loaded_maps.0.insert(
+                lobby.lobby_id,
+                LoadedMap {
+                    entities: Vec::new(),
+                    spawn_points: Vec::new(),
+                },
+            );
+            continue;
+        };
+
+        // Only players inside of lobby should see it is map
+        let replicate = Replicate {
+            target: ReplicationTarget {
+                target: NetworkTarget::Only(lobby.players.clone()),
+            },
+            ..default()
+        };
+
+        let mut entities = Vec::new();
+        for collider in map_definition.colliders.iter() {
+            let entity = commands
+                .spawn(StaticPhysics::new(collider.shape.collider()))
+                .insert(MapCollider {
+                    shape: collider.shape.clone(),
+                    color: collider.color,
+                })
+                .insert(Position(collider.position))
+                .insert(Rotation(Quat::from_rotation_y(collider.yaw)))
+                .insert(Name::new(collider.name.clone()))
+                .insert(replicate.clone())
+                .id();
+            entities.push(entity);
+        }
+
+        for prop in map_definition.props.iter() {
+            let mut entity = commands.spawn(MapProp {
+                scene: prop.scene.clone(),
+                collider: prop.collider.clone(),
+            });
+            entity
+                .insert(Position(prop.position))
+                .insert(Rotation(Quat::from_rotation_y(prop.yaw)))
+                .insert(Name::new(prop.name.clone()))
+                .insert(replicate.clone());
+            if let Some(shape) = &prop.collider {
+                entity.insert(StaticPhysics::new(shape.collider()));
+            }
+            entities.push(entity.id());
+        }
+
+        // Terrain is not replicated as an entity, both sides generate it from it is seed
+        lobby.terrain = map_definition.terrain.clone();
+
+        info!(
+            "Spawned map {} with {} entities",
+            map_definition.name,
+            entities.len()
+        );
+        loaded_maps.0.insert(
+            lobby.lobby_id,
+            LoadedMap {
+                entities,
+                spawn_points: map_definition.spawn_points,
+            },
+        );
+    }
+}
+
+/// Helper makes the map of a lobby visible only to the players currently in it
+pub fn update_map_replication_targets(
+    lobby_id: u64,
+    loaded_maps: &LoadedMaps,
+    replication_target: &mut Query<(&mut ReplicationTarget, &mut SyncTarget)>,
+    all_players: &[ClientId],
+) {
+    let Some(loaded_map) = loaded_maps.0.get(&lobby_id) else {
+        return;
+    };
+
+    for entity in loaded_map.entities.iter() {
+        if let Ok((mut replication, _)) = replication_target.get_mut(*entity) {
+            *replication = ReplicationTarget {
+                target: NetworkTarget::Only(all_players.to_vec()),
+            };
+        }
+    }
+}
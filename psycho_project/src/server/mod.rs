@@ -1,23 +1,34 @@
+use auth::AuthPlugin;
 use bevy::prelude::*;
-use bincode::serialize_into;
+use console::ConsolePlugin;
 use lobby::LobbyPlugin;
+use map::MapPlugin;
 use player::PlayerPlugin;
-use std::fs::File;
-use std::io::BufWriter;
+use std::net::SocketAddr;
+use terrain::TerrainPlugin;
 use world::PhysicsWorldPlugin;
 
 mod essentials;
 
+mod auth;
 mod client_info;
+mod console;
 mod lobby;
+mod map;
 pub mod player;
+mod save_migration;
+mod terrain;
 mod world;
 
 use self::essentials::*;
 use crate::shared::protocol::player_structs::SavePlayerBundleMap;
+use save_migration::write_player_save;
 
 /// Important plugin here you should centralize all systems/plugins that are heavily correlated to server
-pub struct ExampleServerPlugin;
+pub struct ExampleServerPlugin {
+    // Address clients reach the game server through, auth service puts it inside of connect tokens
+    pub public_game_addr: SocketAddr,
+}
 
 impl Plugin for ExampleServerPlugin {
     fn build(&self, app: &mut App) {
@@ -25,30 +36,28 @@ impl Plugin for ExampleServerPlugin {
         // app.add_systems(Startup, create_save_files);
 
         //Self made plugins
+        app.add_plugins(ConsolePlugin);
         app.add_plugins(PhysicsWorldPlugin);
+        app.add_plugins(MapPlugin);
+        app.add_plugins(TerrainPlugin);
         app.add_plugins(EssentialsPlugin);
+        app.add_plugins(AuthPlugin {
+            public_game_addr: self.public_game_addr,
+        });
         app.add_plugins(LobbyPlugin);
         app.add_plugins(PlayerPlugin);
         // app.add_plugins(ClientInfoPlugin);
     }
 }
 
-// IF you adjust one of the player bundle sub-structures you will need to run this solely, if not memory alloc error
+// Wipes every player profile, if you adjust one of the player bundle sub-structures bump the version in save_migration instead
 #[allow(dead_code)]
 fn create_save_files() {
-    let mut f = BufWriter::new(
-        File::create("./psycho_project/src/server/save_files/player_info.bar").unwrap(),
-    );
-    let player_bundle = SavePlayerBundleMap::default();
-    serialize_into(&mut f, &player_bundle).unwrap();
+    write_player_save(&SavePlayerBundleMap::default());
 }
 
 // Overwrites or create new file that will currently store only the player_bundle_map
 fn save_file(save_info: SavePlayerBundleMap) {
     info!("Saving");
-    let mut f = BufWriter::new(
-        File::create("./psycho_project/src/server/save_files/player_info.bar").unwrap(),
-    );
-    let player_bundle = &save_info;
-    serialize_into(&mut f, &player_bundle).unwrap();
+    write_player_save(&save_info);
 }
@@ -1,22 +1,37 @@
 //! All logic associated to player
+use crate::server::auth::Accounts;
+use crate::server::console::ConsoleCommand;
+use crate::server::map::LoadedMaps;
 use crate::server::save_file;
+use crate::server::save_migration::read_player_save;
 use crate::shared::protocol::lobby_structs::*;
 use crate::shared::protocol::player_structs::*;
-use crate::shared::protocol::weapon_structs::Weapon;
+use crate::shared::protocol::weapon_structs::{EquippedWeapon, Weapon};
 use crate::shared::protocol::CommonChannel;
 use crate::shared::shared_gun::process_collisions;
 use crate::shared::shared_gun::shared_spawn_bullet;
 use crate::shared::shared_gun::BulletHitEvent;
+use crate::shared::shared_parts::{PartCatalog, PartSlot, UnlockRequirement};
 use crate::shared::shared_physics::*;
 use avian3d::prelude::*;
 use bevy::prelude::*;
 use bevy::utils::HashMap;
-use bincode::deserialize_from;
+use bincode::{deserialize_from, serialize_into};
 use leafwing_input_manager::prelude::ActionState;
 use lightyear::prelude::server::*;
 use lightyear::prelude::*;
+use lightyear::shared::tick_manager::TickManager;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, BufWriter};
+
+/// Where we store which parts each player owns
+const OWNED_PARTS_PATH: &str = "./psycho_project/src/server/save_files/owned_parts.bar";
+
+/// Longest name a loadout preset can have
+const MAX_LOADOUT_NAME: usize = 24;
+
+/// Seconds a dead player waits before coming back
+const RESPAWN_DELAY: f32 = 3.0;
 
 /// Plugin for logics that are associated to player creation customization and so on
 pub struct PlayerPlugin;
@@ -26,6 +41,8 @@ impl Plugin for PlayerPlugin {
         // Initializing resources
         app.init_resource::<PlayerAmount>();
         app.init_resource::<ServerPlayerEntityMap>();
+        app.init_resource::<OwnedPartsMap>();
+        app.init_resource::<Scoreboard>();
 
         // Debug registering
         app.register_type::<PlayerStateConnection>();
@@ -39,13 +56,35 @@ impl Plugin for PlayerPlugin {
 
         // Reads player bundle map and make it readily available when server boots up
         app.add_systems(Startup, read_save_files);
+        app.add_systems(Startup, read_owned_parts);
+
+        // Events
+        app.add_event::<ApplyVisuals>();
+        app.add_event::<GrantPart>();
 
         // Listens to client sent events
-        app.add_systems(Update, listener_save_visuals);
+        app.add_systems(
+            Update,
+            (
+                listener_save_visuals,
+                listener_change_loadout,
+                apply_player_visuals,
+            )
+                .chain(),
+        );
+        app.add_systems(Update, listener_grant_part);
+        // Headless servers dont have keyboard input
+        app.add_systems(
+            Update,
+            debug_grant_parts_keys.run_if(resource_exists::<ButtonInput<KeyCode>>),
+        );
 
         // What happens when you connects to server
         app.add_systems(Update, handle_connections);
 
+        // Operator hands profiles from before accounts to the account they belong to
+        app.add_systems(Update, console_claim_legacy_profile);
+
         // What happens when you disconnect from server
         app.add_systems(Update, handle_disconnections);
 
@@ -69,6 +108,8 @@ impl Plugin for PlayerPlugin {
                 .run_if(on_event::<BulletHitEvent>())
                 .after(process_collisions),
         );
+
+        app.add_systems(Update, respawn_players);
     }
 }
 
@@ -83,6 +124,29 @@ pub struct PlayerAmount {
 #[reflect(Resource, Default)]
 pub struct ServerPlayerEntityMap(pub HashMap<ClientId, Entity>);
 
+/// Parts that need to be owned to be equiped, according to client id. Granted through grant part server command
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct OwnedPartsMap(pub HashMap<ClientId, Vec<String>>);
+
+/// Server only, counts down until a dead player respawns
+#[derive(Component)]
+struct Dead(Timer);
+
+/// Server command that unlocks an owned part for a player, saved so he keeps it
+#[derive(Event, Debug)]
+pub struct GrantPart {
+    pub client_id: ClientId,
+    // File path of part same as in catalog
+    pub part: String,
+}
+
+/// Visuals a player wants to use, comes from save visual messages and from selecting a loadout
+#[derive(Event)]
+struct ApplyVisuals {
+    client_id: ClientId,
+    visuals: PlayerVisuals,
+}
+
 /// State of connection of our player
 #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
 pub struct PlayerStateConnection {
@@ -93,43 +157,368 @@ pub struct PlayerStateConnection {
 
 fn replicate_resource(mut commands: Commands) {
     commands.replicate_resource::<SavePlayerBundleMap, CommonChannel>(NetworkTarget::All);
+    commands.replicate_resource::<Scoreboard, CommonChannel>(NetworkTarget::All);
 }
 
 /// Reads current save files and fill up the resource playerbundlemap each basically gives me all player info
+/// Older save formats get migrated, see save_migration
 fn read_save_files(mut commands: Commands) {
-    let f = BufReader::new(
-        File::open("./psycho_project/src/server/save_files/player_info.bar").unwrap(),
-    );
-    let player_bundle_map: SavePlayerBundleMap = deserialize_from(f).unwrap();
+    let player_bundle_map = read_player_save();
     // info!("Read from save file: {:?}", player_bundle_map);
 
     commands.insert_resource(player_bundle_map);
 }
 
-/// Responsible for saving player info
+/// Reads owned parts file, if it doesnt exist yet nobody owns anything
+fn read_owned_parts(mut commands: Commands) {
+    let Ok(file) = File::open(OWNED_PARTS_PATH) else {
+        warn!("No owned parts file found starting with empty one");
+        return;
+    };
+    match deserialize_from::<_, OwnedPartsMap>(BufReader::new(file)) {
+        Ok(owned_parts) => commands.insert_resource(owned_parts),
+        Err(err) => error!("Couldnt read owned parts file: {}", err),
+    }
+}
+
+/// Overwrites owned parts file with everything players own right now
+fn save_owned_parts(owned_parts: &OwnedPartsMap) {
+    info!("Saving owned parts");
+    match File::create(OWNED_PARTS_PATH) {
+        Ok(file) => {
+            if let Err(err) = serialize_into(&mut BufWriter::new(file), owned_parts) {
+                error!("Couldnt save owned parts: {}", err);
+            }
+        }
+        Err(err) => error!("Couldnt create owned parts file: {}", err),
+    }
+}
+
+fn listener_grant_part(
+    mut events: EventReader<GrantPart>,
+    catalog: Res<PartCatalog>,
+    mut owned_parts: ResMut<OwnedPartsMap>,
+    mut connection_manager: ResMut<ConnectionManager>,
+) {
+    let mut granted_to = Vec::new();
+    for event in events.read() {
+        let Some(part) = catalog.find(&event.part) else {
+            warn!("Cant grant unknown part {}", event.part);
+            continue;
+        };
+        if part.unlock != UnlockRequirement::Owned {
+            info!("Part {} is free no need to grant it", part.display_name);
+            continue;
+        }
+        let client_parts = owned_parts.0.entry(event.client_id).or_default();
+        if client_parts.contains(&event.part) {
+            continue;
+        }
+        info!(
+            "Granting {} to client {}",
+            part.display_name, event.client_id
+        );
+        client_parts.push(event.part.clone());
+        if !granted_to.contains(&event.client_id) {
+            granted_to.push(event.client_id);
+        }
+    }
+    if granted_to.is_empty() {
+        return;
+    }
+    save_owned_parts(&owned_parts);
+    // Offline players get theirs when they connect
+    for client_id in granted_to {
+        let mut client_owned_parts = SendOwnedParts(owned_parts.0[&client_id].clone());
+        let _ = connection_manager
+            .send_message::<CommonChannel, SendOwnedParts>(client_id, &mut client_owned_parts);
+    }
+}
+
+/// Debugging key in server window, G grants every owned part to everyone online
+fn debug_grant_parts_keys(
+    keys: Res<ButtonInput<KeyCode>>,
+    catalog: Res<PartCatalog>,
+    player_entity_map: Res<ServerPlayerEntityMap>,
+    mut grant_part: EventWriter<GrantPart>,
+) {
+    if !keys.just_pressed(KeyCode::KeyG) {
+        return;
+    }
+    for client_id in player_entity_map.0.keys() {
+        for part in catalog
+            .parts
+            .iter()
+            .filter(|part| part.unlock == UnlockRequirement::Owned)
+        {
+            grant_part.send(GrantPart {
+                client_id: *client_id,
+                part: part.mesh.clone(),
+            });
+        }
+    }
+}
+
+/// Client picked new visuals in inventory
 fn listener_save_visuals(
     mut events: EventReader<MessageEvent<SaveVisual>>,
+    mut apply_visuals: EventWriter<ApplyVisuals>,
+) {
+    for event in events.read() {
+        apply_visuals.send(ApplyVisuals {
+            client_id: *event.context(),
+            visuals: event.message().0.clone(),
+        });
+    }
+}
+
+/// Helper avoids empty or gigantic loadout names
+fn loadout_name(name: &str) -> String {
+    let name: String = name.trim().chars().take(MAX_LOADOUT_NAME).collect();
+    if name.is_empty() {
+        String::from("Loadout")
+    } else {
+        name
+    }
+}
+
+/// Creates, renames, deletes and selects loadout presets of a player, selecting one applies it is visuals and weapon
+fn listener_change_loadout(
+    mut events: EventReader<MessageEvent<ChangeLoadout>>,
     mut player_map: ResMut<SavePlayerBundleMap>,
     player_entity_map: Res<ServerPlayerEntityMap>,
-    mut server_player_visuals: Query<&mut PlayerVisuals>,
+    catalog: Res<PartCatalog>,
+    owned_parts: Res<OwnedPartsMap>,
+    mut server_players: Query<(&mut EquippedWeapon, &mut PlayerLoadouts)>,
+    mut apply_visuals: EventWriter<ApplyVisuals>,
+    mut connection_manager: ResMut<ConnectionManager>,
 ) {
     for event in events.read() {
-        let client_id = event.context();
+        let client_id = *event.context();
+        let Some(player_bundle) = player_map.0.get_mut(&client_id) else {
+            error!("Something went wrong in grabing this id info in server");
+            continue;
+        };
+        let server_player = player_entity_map
+            .0
+            .get(&client_id)
+            .and_then(|entity| server_players.get_mut(*entity).ok());
+
+        let loadouts = &mut player_bundle.loadouts;
+        let mut selected = false;
+        match event.message() {
+            ChangeLoadout::Create(name) => {
+                // Every preset ends up in the save file, so players cant grow it forever
+                if loadouts.presets.len() >= MAX_LOADOUT_PRESETS {
+                    warn!(
+                        "Client {} already has {} loadouts cant create another",
+                        client_id, MAX_LOADOUT_PRESETS
+                    );
+                    continue;
+                }
+                info!("Client {} created loadout {}", client_id, name);
+                let equipped_weapon = server_player
+                    .as_ref()
+                    .map(|(equipped_weapon, _)| equipped_weapon.0)
+                    .unwrap_or_default();
+                loadouts.presets.push(LoadoutPreset {
+                    name: loadout_name(name),
+                    visuals: player_bundle.visuals.clone(),
+                    equipped_weapon,
+                });
+                loadouts.active = loadouts.presets.len() - 1;
+            }
+            ChangeLoadout::Rename(index, name) => {
+                let Some(preset) = loadouts.presets.get_mut(*index) else {
+                    warn!(
+                        "Client {} tried renaming unknown loadout {}",
+                        client_id, index
+                    );
+                    continue;
+                };
+                info!(
+                    "Client {} renamed loadout {} to {}",
+                    client_id, preset.name, name
+                );
+                preset.name = loadout_name(name);
+            }
+            ChangeLoadout::Delete(index) => {
+                // Player always needs something to wear
+                if loadouts.presets.len() <= 1 || *index >= loadouts.presets.len() {
+                    warn!("Client {} cant delete loadout {}", client_id, index);
+                    continue;
+                }
+                // Deleting the active one puts on the preset after it, or the one before if it was last
+                if *index == loadouts.active {
+                    let next = if *index + 1 < loadouts.presets.len() {
+                        *index + 1
+                    } else {
+                        *index - 1
+                    };
+                    let visuals = &loadouts.presets[next].visuals;
+                    if let Err(reason) =
+                        checked_visuals(visuals, &client_id, &catalog, &owned_parts)
+                    {
+                        reject_visuals(&mut connection_manager, client_id, reason);
+                        continue;
+                    }
+                }
+                info!("Client {} deleted loadout {}", client_id, index);
+                loadouts.presets.remove(*index);
+                if *index < loadouts.active {
+                    loadouts.active -= 1;
+                } else if *index == loadouts.active {
+                    loadouts.active = loadouts.active.min(loadouts.presets.len() - 1);
+                    selected = true;
+                }
+            }
+            ChangeLoadout::Select(index) => {
+                if *index >= loadouts.presets.len() {
+                    warn!(
+                        "Client {} tried selecting unknown loadout {}",
+                        client_id, index
+                    );
+                    continue;
+                }
+                // Nothing switches unless player is allowed to wear what the preset has
+                let visuals = &loadouts.presets[*index].visuals;
+                if let Err(reason) = checked_visuals(visuals, &client_id, &catalog, &owned_parts) {
+                    reject_visuals(&mut connection_manager, client_id, reason);
+                    continue;
+                }
+                info!("Client {} selected loadout {}", client_id, index);
+                loadouts.active = *index;
+                selected = true;
+            }
+        }
+
+        if let Some((mut equipped_weapon, mut server_loadouts)) = server_player {
+            if selected {
+                *equipped_weapon = EquippedWeapon(loadouts.active_preset().equipped_weapon);
+            }
+            *server_loadouts = loadouts.clone();
+        }
+        if selected {
+            apply_visuals.send(ApplyVisuals {
+                client_id,
+                visuals: loadouts.active_preset().visuals.clone(),
+            });
+        }
+
+        save_file(player_map.clone());
+    }
+}
+
+/// Helper gives me visuals with clamped tints if the client is allowed to wear them, else the reason why not
+fn checked_visuals(
+    visuals: &PlayerVisuals,
+    client_id: &ClientId,
+    catalog: &PartCatalog,
+    owned_parts: &OwnedPartsMap,
+) -> Result<PlayerVisuals, String> {
+    let mut visuals = visuals.clone();
+    // Tints go straight into materials of everyone in lobby and into the save file
+    if !visuals.sanitize_tints() {
+        return Err("Tint isnt a valid color".to_string());
+    }
+    let client_owned_parts = owned_parts
+        .0
+        .get(client_id)
+        .map(|parts| parts.as_slice())
+        .unwrap_or_default();
+    catalog.validate(&visuals, client_owned_parts)?;
+    Ok(visuals)
+}
+
+/// Tells client why his visuals werent applied
+fn reject_visuals(connection_manager: &mut ConnectionManager, client_id: ClientId, reason: String) {
+    warn!("Rejecting visuals of client {}: {}", client_id, reason);
+    let _ = connection_manager
+        .send_message::<CommonChannel, RejectVisual>(client_id, &mut RejectVisual(reason));
+}
+
+/// Responsible for saving player info, only after making sure the client is allowed to use those visuals
+fn apply_player_visuals(
+    mut events: EventReader<ApplyVisuals>,
+    mut player_map: ResMut<SavePlayerBundleMap>,
+    player_entity_map: Res<ServerPlayerEntityMap>,
+    catalog: Res<PartCatalog>,
+    owned_parts: Res<OwnedPartsMap>,
+    lobby_position_map: Res<LobbyPositionMap>,
+    lobbies: Res<Lobbies>,
+    mut server_player_visuals: Query<(&mut PlayerVisuals, &mut PlayerLoadouts)>,
+    mut connection_manager: ResMut<ConnectionManager>,
+) {
+    for event in events.read() {
+        let client_id = &event.client_id;
 
         info!("Grabbing player visuals and body part to change from client");
-        let client_player_visuals = event.message().0.clone();
+        let client_player_visuals =
+            match checked_visuals(&event.visuals, client_id, &catalog, &owned_parts) {
+                Ok(visuals) => visuals,
+                Err(reason) => {
+                    reject_visuals(&mut connection_manager, *client_id, reason);
+                    continue;
+                }
+            };
 
         info!("Saving player info {}", client_id);
 
         if let Some(player_bundle) = player_map.0.get_mut(client_id) {
             info!("Found it is bundle and changing visual  for what client said in resource player bundle map");
+            let old_visuals = player_bundle.visuals.clone();
             player_bundle.visuals = client_player_visuals.clone();
+            // Active loadout follows whatever player is wearing
+            player_bundle.loadouts.active_preset_mut().visuals = client_player_visuals.clone();
+
+            // Only players that can actually see him need to know, others get it through replicated player visuals
+            let target = visual_recipients(*client_id, &lobby_position_map, &lobbies);
+            for ((_, old_part), (_, new_part)) in old_visuals
+                .iter_visuals()
+                .zip(client_player_visuals.iter_visuals())
+            {
+                if old_part == new_part {
+                    continue;
+                }
+                info!(
+                    "Telling lobby client {} changed {} to {}",
+                    client_id, old_part, new_part
+                );
+                let _ = connection_manager.send_message_to_target::<CommonChannel, ChangeChar>(
+                    &mut ChangeChar((
+                        *client_id,
+                        PartToChange {
+                            old_part: old_part.clone(),
+                            new_part: new_part.clone(),
+                        },
+                    )),
+                    NetworkTarget::Only(target.clone()),
+                );
+            }
+            for slot in [PartSlot::Head, PartSlot::Torso, PartSlot::Legs] {
+                let new_tint = client_player_visuals.tint(slot);
+                if old_visuals.tint(slot) != new_tint {
+                    info!("Telling lobby client {} changed {:?} tint", client_id, slot);
+                    let _ = connection_manager.send_message_to_target::<CommonChannel, ChangeTint>(
+                        &mut ChangeTint((*client_id, slot, new_tint)),
+                        NetworkTarget::Only(target.clone()),
+                    );
+                } else if event.visuals.tint(slot) != new_tint {
+                    // Client is previewing a tint we clamped back to what he had, so only he needs it put back
+                    let _ = connection_manager.send_message::<CommonChannel, ChangeTint>(
+                        *client_id,
+                        &mut ChangeTint((*client_id, slot, new_tint)),
+                    );
+                }
+            }
+
             if let Some(server_player) = player_entity_map.0.get(client_id) {
                 info!("Grabbing server player and also adjusting his component");
-                let mut server_visuals = server_player_visuals
+                let (mut server_visuals, mut server_loadouts) = server_player_visuals
                     .get_mut(*server_player)
                     .expect("Server player to have visuals");
                 *server_visuals = client_player_visuals.clone();
+                *server_loadouts = player_bundle.loadouts.clone();
             } else {
                 error!("Couldnt find server player something went wrong")
             }
@@ -142,14 +531,33 @@ fn listener_save_visuals(
     }
 }
 
+/// Helper gives me who should be told about a player visual change, himself and whoever is in or watching his lobby
+fn visual_recipients(
+    client_id: ClientId,
+    lobby_position_map: &LobbyPositionMap,
+    lobbies: &Lobbies,
+) -> Vec<ClientId> {
+    let mut recipients = lobby_position_map
+        .0
+        .get(&client_id)
+        .and_then(|client_info| lobbies.find(client_info.lobby_id))
+        .map(|lobby| lobby.everyone())
+        .unwrap_or_default();
+    if !recipients.contains(&client_id) {
+        recipients.push(client_id);
+    }
+    recipients
+}
+
 /// Helper function spawns the player that is gonna be replicated
 fn spawn_server_player(
     client_id: ClientId,
     commands: &mut Commands,
     player_bundle: Option<SavePlayerBundle>,
+    account: PlayerAccount,
     player_entity_map: &mut ResMut<ServerPlayerEntityMap>,
 ) -> SavePlayerBundle {
-    let name = Name::new(format!("Player {:?}", client_id));
+    let name = Name::new(format!("Player {}", account.display_name));
 
     info!("Setting their status to online");
     let online_state = PlayerStateConnection {
@@ -175,6 +583,8 @@ fn spawn_server_player(
 
     if let Some(old_player_bun) = player_bundle {
         info!("Inserting into entity map resource");
+        let equipped_weapon =
+            EquippedWeapon(old_player_bun.loadouts.active_preset().equipped_weapon);
         let id = commands
             .spawn(old_player_bun.clone())
             .insert(online_state)
@@ -183,6 +593,7 @@ fn spawn_server_player(
             .insert(PlayerBundle::default())
             .insert(PlayerAction::default_input_map())
             .insert(Weapon::default())
+            .insert(equipped_weapon)
             .insert(Position(Vec3::new(0.0, 2.0, 0.0)))
             .id();
         player_entity_map.0.insert(client_id, id);
@@ -192,7 +603,8 @@ fn spawn_server_player(
         // Setting default visuals
         let player_visual = PlayerVisuals::default();
         let player_position = PlayerPosition::default();
-        let new_player_bundle = SavePlayerBundle::new(client_id, player_visual, player_position);
+        let new_player_bundle =
+            SavePlayerBundle::new(client_id, player_visual, player_position, account);
         let id = commands
             .spawn(new_player_bundle.clone())
             .insert(online_state)
@@ -201,6 +613,7 @@ fn spawn_server_player(
             .insert(PlayerBundle::default())
             .insert(PlayerAction::default_input_map())
             .insert(Weapon::default())
+            .insert(EquippedWeapon::default())
             .insert(Position(Vec3::new(0.0, 2.0, 0.0)))
             .id();
 
@@ -215,18 +628,40 @@ fn handle_connections(
     mut connections: EventReader<ConnectEvent>,
     mut player_map: ResMut<SavePlayerBundleMap>,
     mut player_entity_map: ResMut<ServerPlayerEntityMap>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    accounts: Res<Accounts>,
+    owned_parts: Res<OwnedPartsMap>,
     mut commands: Commands,
 ) {
     for connection in connections.read() {
+        // Client id came from the account id inside of the connect token
+        let account_id = connection.client_id.to_bits();
+        let account = PlayerAccount {
+            account_id,
+            display_name: accounts
+                .display_name(account_id)
+                .unwrap_or_else(|| format!("Player {}", account_id)),
+        };
+        info!(
+            "Account {} connected as {}",
+            account_id, account.display_name
+        );
+
         info!("Checking if new client or if already exists");
-        if let Some(old_player_bundle) = player_map.0.get(&connection.client_id) {
+        if let Some(old_player_bundle) = player_map.0.get_mut(&connection.client_id) {
             info!(
                 "This player {:?} already connected once spawn it is entity according to it is settings",old_player_bundle.id
             );
+            info!("Wearing his active loadout");
+            old_player_bundle.visuals = old_player_bundle.loadouts.active_preset().visuals.clone();
+            // Display name could have changed since last time
+            old_player_bundle.account = account.clone();
+
             spawn_server_player(
                 connection.client_id,
                 &mut commands,
                 Some(old_player_bundle.clone()),
+                account,
                 &mut player_entity_map,
             );
         } else {
@@ -235,6 +670,7 @@ fn handle_connections(
                 connection.client_id,
                 &mut commands,
                 None,
+                account,
                 &mut player_entity_map,
             );
 
@@ -246,20 +682,103 @@ fn handle_connections(
             save_file(player_map.clone());
         }
 
+        // Inventory needs it to know which parts are locked
+        let client_owned_parts = owned_parts
+            .0
+            .get(&connection.client_id)
+            .cloned()
+            .unwrap_or_default();
+        let _ = connection_manager.send_message::<CommonChannel, SendOwnedParts>(
+            connection.client_id,
+            &mut SendOwnedParts(client_owned_parts),
+        );
+
         current_players.quantity += 1;
         info!("Current players online is {}", current_players.quantity);
     }
 }
 
+/// Saves from before accounts are under the client id of the client config, clients cant prove that id was theirs
+/// So only whoever runs the server moves them, claim <legacy_client_id> <username> gives the profile and its parts to that account
+fn console_claim_legacy_profile(
+    mut console: EventReader<ConsoleCommand>,
+    accounts: Res<Accounts>,
+    player_entity_map: Res<ServerPlayerEntityMap>,
+    mut player_map: ResMut<SavePlayerBundleMap>,
+    mut owned_parts: ResMut<OwnedPartsMap>,
+) {
+    for command in console.read() {
+        if command.name != "claim" {
+            continue;
+        }
+        let (Some(legacy_client_id), Some(username)) = (
+            command.arg::<u64>(0, "legacy client id"),
+            command.arg::<String>(1, "username"),
+        ) else {
+            continue;
+        };
+        let Some(account_id) = accounts.account_id(&username) else {
+            warn!("No account named {}", username);
+            continue;
+        };
+        let legacy_client_id = ClientId::Netcode(legacy_client_id);
+        let client_id = ClientId::Netcode(account_id);
+
+        // Only profiles migrated from before accounts are free to take
+        let unclaimed = player_map
+            .0
+            .get(&legacy_client_id)
+            .is_some_and(|bundle| bundle.account.account_id == 0);
+        if !unclaimed {
+            warn!("Client {} has no unclaimed profile", legacy_client_id);
+            continue;
+        }
+        // Online player would keep playing on and saving the profile he has now
+        if player_entity_map.0.contains_key(&client_id) {
+            warn!("{} is online, claim once he disconnects", username);
+            continue;
+        }
+
+        let Some(mut bundle) = player_map.0.remove(&legacy_client_id) else {
+            continue;
+        };
+        info!(
+            "Account {} takes over profile of legacy client {}",
+            username, legacy_client_id
+        );
+        bundle.id = PlayerId(client_id);
+        bundle.account = PlayerAccount {
+            account_id,
+            display_name: accounts
+                .display_name(account_id)
+                .unwrap_or_else(|| username.clone()),
+        };
+        player_map.0.insert(client_id, bundle);
+        save_file(player_map.clone());
+
+        if let Some(parts) = owned_parts.0.remove(&legacy_client_id) {
+            let account_parts = owned_parts.0.entry(client_id).or_default();
+            for part in parts {
+                if !account_parts.contains(&part) {
+                    account_parts.push(part);
+                }
+            }
+            save_owned_parts(&owned_parts);
+        }
+    }
+}
+
 /// Spawns a player everytime someone disconnects
 fn handle_disconnections(
     mut disconnections: EventReader<DisconnectEvent>,
     mut current_players: ResMut<PlayerAmount>,
     mut player_entity_map: ResMut<ServerPlayerEntityMap>,
+    mut scoreboard: ResMut<Scoreboard>,
 ) {
     for disconnection in disconnections.read() {
         let client_id = disconnection.client_id;
         info!("Client disconnected {}", client_id);
+        scoreboard.0.remove(&client_id);
 
         // Decrease player count
         current_players.quantity -= 1;
@@ -296,9 +815,17 @@ fn replicate_inputs(
 }
 fn handle_character_actions(
     time: Res<Time>,
-    mut query: Query<(&ActionState<PlayerAction>, &RayHits, CharacterQuery)>,
+    mut query: Query<(
+        &ActionState<PlayerAction>,
+        &RayHits,
+        &PlayerHealth,
+        CharacterQuery,
+    )>,
 ) {
-    for (action_state, ray_hits, mut character) in &mut query {
+    for (action_state, ray_hits, player_health, mut character) in &mut query {
+        if player_health.is_dead() {
+            continue;
+        }
         apply_character_action(&time, action_state, ray_hits, &mut character);
     }
 }
@@ -331,21 +858,64 @@ fn insert_physics_server_player(
 }
 
 /// Responsible for encapsulating the bullet hit event and changing player health when occurs
+/// When health reaches zero the lobby is told so they can ragdoll him and scoreboard counts it
 fn handle_bullet_hit(
     mut bullet_hit_event: EventReader<BulletHitEvent>,
-    mut player_health: Query<&mut PlayerHealth>,
+    mut player_health: Query<(&mut PlayerHealth, &mut PlayerAnimation)>,
     entity_map: Res<ServerPlayerEntityMap>,
+    tick_manager: Res<TickManager>,
+    lobby_position_map: Res<LobbyPositionMap>,
+    lobbies: Res<Lobbies>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut commands: Commands,
 ) {
     for bullet_hit in bullet_hit_event.read() {
         if let Some(_) = entity_map.0.get(&bullet_hit.bullet_owner) {
             if let Some(victim_id) = bullet_hit.victim_client_id {
                 if let Some(victim) = entity_map.0.get(&victim_id) {
-                    if let Ok(mut player_health) = player_health.get_mut(*victim) {
+                    if let Ok((mut player_health, mut player_animation)) =
+                        player_health.get_mut(*victim)
+                    {
+                        // No point in shooting corpses
+                        if player_health.is_dead() {
+                            continue;
+                        }
                         info!(
                             "Shooter id {} just shoot {} diminishing his health",
                             bullet_hit.bullet_owner, victim_id
                         );
                         player_health.0 -= 2;
+                        // Clients cant predict getting hit, so server tells them when it started
+                        *player_animation =
+                            PlayerAnimation::new(OneShotAnimation::HitReceive, tick_manager.tick());
+
+                        if player_health.is_dead() {
+                            info!(
+                                "Player {} was killed by {}",
+                                victim_id, bullet_hit.bullet_owner
+                            );
+                            scoreboard.0.entry(victim_id).or_default().deaths += 1;
+                            scoreboard
+                                .0
+                                .entry(bullet_hit.bullet_owner)
+                                .or_default()
+                                .kills += 1;
+                            commands
+                                .entity(*victim)
+                                .insert(Dead(Timer::from_seconds(RESPAWN_DELAY, TimerMode::Once)));
+                            let target =
+                                visual_recipients(victim_id, &lobby_position_map, &lobbies);
+                            let _ = connection_manager
+                                .send_message_to_target::<CommonChannel, PlayerDeath>(
+                                    &mut PlayerDeath {
+                                        victim: victim_id,
+                                        killer: bullet_hit.bullet_owner,
+                                        bullet_velocity: bullet_hit.bullet_velocity,
+                                    },
+                                    NetworkTarget::Only(target),
+                                );
+                        }
                     }
                 } else {
                     warn!("Couldnt grab victim entity in entity map somethin went terribly wrong")
@@ -358,3 +928,48 @@ fn handle_bullet_hit(
         }
     }
 }
+
+/// Brings dead players back with full health at their lobby spawn point
+fn respawn_players(
+    time: Res<Time>,
+    mut dead_players: Query<(
+        Entity,
+        &PlayerId,
+        &mut Dead,
+        &mut PlayerHealth,
+        &mut Position,
+        &mut LinearVelocity,
+    )>,
+    loaded_maps: Res<LoadedMaps>,
+    lobby_position_map: Res<LobbyPositionMap>,
+    lobbies: Res<Lobbies>,
+    mut connection_manager: ResMut<ConnectionManager>,
+    mut commands: Commands,
+) {
+    for (entity, player_id, mut dead, mut player_health, mut position, mut linear_velocity) in
+        dead_players.iter_mut()
+    {
+        if !dead.0.tick(time.delta()).finished() {
+            continue;
+        }
+        let client_id = player_id.0;
+        info!("Respawning player {}", client_id);
+
+        *player_health = PlayerHealth::default();
+        linear_velocity.0 = Vec3::ZERO;
+        position.0 = lobby_position_map
+            .0
+            .get(&client_id)
+            .and_then(|client_info| {
+                loaded_maps.spawn_point(client_info.lobby_id, client_info.lobby_position)
+            })
+            .unwrap_or(Vec3::new(0.0, 2.0, 0.0));
+        commands.entity(entity).remove::<Dead>();
+
+        let target = visual_recipients(client_id, &lobby_position_map, &lobbies);
+        let _ = connection_manager.send_message_to_target::<CommonChannel, PlayerRespawn>(
+            &mut PlayerRespawn(client_id),
+            NetworkTarget::Only(target),
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Player save file format, whenever something inside of the save player bundle changes the version goes up
+//! and a migration from the previous version is added here, so nobody loses their profile
+use crate::shared::protocol::player_structs::*;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bincode::{deserialize_from, serialize_into};
+use lightyear::prelude::ClientId;
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+pub const PLAYER_SAVE_PATH: &str = "./psycho_project/src/server/save_files/player_info.bar";
+
+/// Starts every versioned save, files without it are from before saves had a version
+const SAVE_MAGIC: [u8; 4] = *b"PSAV";
+
+/// Bump whenever save player bundle changes and add a migration from the old version
+const SAVE_VERSION: u32 = 1;
+
+/// Reads player save migrating it if it is from an older version
+/// Saves we cant understand stop the server, as starting fresh would throw every profile away on the next save
+pub fn read_player_save() -> SavePlayerBundleMap {
+    let bytes = match fs::read(PLAYER_SAVE_PATH) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            warn!("No player save found starting with empty one: {}", err);
+            return SavePlayerBundleMap::default();
+        }
+    };
+
+    let result = match bytes.strip_prefix(&SAVE_MAGIC) {
+        Some(mut versioned) => match deserialize_from::<_, u32>(&mut versioned) {
+            Ok(SAVE_VERSION) => {
+                deserialize_from::<_, SavePlayerBundleMap>(versioned).map_err(|err| err.to_string())
+            }
+            Ok(version) => Err(format!(
+                "save is version {} but this server only knows up to {}",
+                version, SAVE_VERSION
+            )),
+            Err(err) => Err(err.to_string()),
+        },
+        None => read_v0(&bytes),
+    };
+
+    match result {
+        Ok(player_bundle_map) => {
+            info!("Loaded {} player profiles", player_bundle_map.0.len());
+            player_bundle_map
+        }
+        Err(err) => {
+            error!("Couldnt read player save {}: {}", PLAYER_SAVE_PATH, err);
+            panic!(
+                "Player save {} is unreadable, fix or move it away before starting the server so no profile gets overwritten",
+                PLAYER_SAVE_PATH
+            );
+        }
+    }
+}
+
+/// Writes player save in the current version
+pub fn write_player_save(player_bundle_map: &SavePlayerBundleMap) {
+    let result = File::create(PLAYER_SAVE_PATH)
+        .map_err(bincode::Error::from)
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            writer.write_all(&SAVE_MAGIC)?;
+            serialize_into(&mut writer, &SAVE_VERSION)?;
+            serialize_into(&mut writer, player_bundle_map)?;
+            writer.flush()?;
+            Ok(())
+        });
+    if let Err(err) = result {
+        error!("Couldnt save players in {}: {}", PLAYER_SAVE_PATH, err);
+    }
+}
+
+// Version 0 is how players were saved before saves had a version, only visuals and position
+#[derive(Deserialize)]
+struct PlayerVisualsV0 {
+    character: String,
+    head: String,
+    torso: String,
+    legs: String,
+    weapon_1: String,
+    skeleton: String,
+}
+
+#[derive(Deserialize)]
+struct SavePlayerBundleV0 {
+    id: PlayerId,
+    visuals: PlayerVisualsV0,
+    position: PlayerPosition,
+}
+
+/// Old saves get a second weapon, no tints, a single loadout and no account until somebody claims them
+fn read_v0(bytes: &[u8]) -> Result<SavePlayerBundleMap, String> {
+    let old_map: HashMap<ClientId, SavePlayerBundleV0> =
+        bincode::deserialize(bytes).map_err(|err| err.to_string())?;
+    info!(
+        "Migrating {} player profiles from unversioned save",
+        old_map.len()
+    );
+
+    // Keep the old file around, next save overwrites it with the new version
+    let backup_path = format!("{}.v0", PLAYER_SAVE_PATH);
+    if let Err(err) = fs::write(&backup_path, bytes) {
+        error!(
+            "Couldnt back up old player save to {}: {}",
+            backup_path, err
+        );
+    }
+
+    let default_visuals = PlayerVisuals::default();
+    let player_bundle_map = old_map
+        .into_iter()
+        .map(|(client_id, old_bundle)| {
+            let visuals = PlayerVisuals {
+                character: old_bundle.visuals.character,
+                head: old_bundle.visuals.head,
+                torso: old_bundle.visuals.torso,
+                legs: old_bundle.visuals.legs,
+                weapon_1: old_bundle.visuals.weapon_1,
+                skeleton: old_bundle.visuals.skeleton,
+                ..default_visuals.clone()
+            };
+            let bundle = SavePlayerBundle::new(
+                old_bundle.id.0,
+                visuals,
+                old_bundle.position,
+                PlayerAccount::default(),
+            );
+            (client_id, bundle)
+        })
+        .collect();
+    Ok(SavePlayerBundleMap(player_bundle_map))
+}
@@ -0,0 +1,346 @@
+//! Server side of voxel terrain, only colliders are needed here as clients build their own meshes from the same seed
+//! Server is the only one that edits terrain, edits are batched and sent to the lobby players and saved per lobby
+use crate::shared::protocol::lobby_structs::*;
+use crate::shared::protocol::world_structs::TerrainEdits;
+use crate::shared::protocol::CommonChannel;
+use crate::shared::shared_gun::{process_collisions, BulletHitEvent};
+use crate::shared::shared_physics::StaticPhysics;
+use crate::shared::shared_voxel::*;
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bincode::{deserialize_from, serialize_into};
+use lightyear::prelude::server::*;
+use lightyear::prelude::*;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+/// Radius of the hole a bullet makes in terrain
+const BULLET_CARVE_RADIUS: f32 = 1.5;
+
+/// Seconds between terrain saves, edits in between only mark the lobby as dirty
+const TERRAIN_SAVE_INTERVAL: f32 = 10.0;
+
+/// Generates and keeps the voxel terrain of each lobby
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LobbyTerrains>();
+        app.init_resource::<PendingTerrainEdits>();
+        app.init_resource::<UnsavedTerrains>();
+
+        app.add_systems(Update, build_lobby_terrains);
+        app.add_systems(
+            FixedUpdate,
+            carve_terrain_on_bullet_hit
+                .run_if(on_event::<BulletHitEvent>())
+                .after(process_collisions),
+        );
+        app.add_systems(Update, (flush_terrain_edits, send_terrain_to_joiner));
+        app.add_systems(Update, save_dirty_terrains.after(flush_terrain_edits));
+        app.add_systems(Last, save_terrains_on_exit);
+    }
+}
+
+/// Voxel terrain of every lobby that has one, according to lobby id
+#[derive(Resource, Default)]
+pub struct LobbyTerrains(pub HashMap<u64, VoxelTerrain>);
+
+/// Edits made since the last time we told the clients, according to lobby id
+#[derive(Resource, Default)]
+pub struct PendingTerrainEdits(pub HashMap<u64, Vec<VoxelEdit>>);
+
+/// Lobbies with edits that are not on disk yet, saved together once timer finishes
+#[derive(Resource)]
+pub struct UnsavedTerrains {
+    lobbies: HashSet<u64>,
+    timer: Timer,
+}
+
+impl Default for UnsavedTerrains {
+    fn default() -> Self {
+        Self {
+            lobbies: HashSet::default(),
+            timer: Timer::from_seconds(TERRAIN_SAVE_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+/// Each lobby has it is own terrain save file, so lobbies sharing a map dont overwrite each other
+fn terrain_save_path(map: &str, lobby_id: u64) -> String {
+    format!(
+        "./psycho_project/src/server/save_files/terrain_{}_lobby_{}.bar",
+        map, lobby_id
+    )
+}
+
+/// Saves from before terrain was kept per lobby, only used when a lobby has no save of it is own
+fn legacy_terrain_save_path(map: &str) -> String {
+    format!("./psycho_project/src/server/save_files/terrain_{}.bar", map)
+}
+
+/// Reads the edits a lobby had last time the server ran, no file means no edits
+fn read_terrain_edits(map: &str, lobby_id: u64) -> Vec<VoxelEdit> {
+    let file = match File::open(terrain_save_path(map, lobby_id)) {
+        Ok(file) => file,
+        Err(_) => match File::open(legacy_terrain_save_path(map)) {
+            Ok(file) => {
+                info!(
+                    "Lobby {} starts from old terrain save of map {}",
+                    lobby_id, map
+                );
+                file
+            }
+            Err(_) => return Vec::new(),
+        },
+    };
+    match deserialize_from(BufReader::new(file)) {
+        Ok(edits) => edits,
+        Err(err) => {
+            error!("Couldnt read terrain save of lobby {}: {}", lobby_id, err);
+            Vec::new()
+        }
+    }
+}
+
+/// Overwrites the terrain save file of a lobby
+fn save_terrain_edits(map: &str, lobby_id: u64, edits: &Vec<VoxelEdit>) {
+    info!("Saving terrain of lobby {}", lobby_id);
+    match File::create(terrain_save_path(map, lobby_id)) {
+        Ok(file) => {
+            if let Err(err) = serialize_into(&mut BufWriter::new(file), edits) {
+                error!("Couldnt save terrain of lobby {}: {}", lobby_id, err);
+            }
+        }
+        Err(err) => error!("Couldnt create terrain save of lobby {}: {}", lobby_id, err),
+    }
+}
+
+/// Helper writes every lobby that has unsaved edits
+fn save_unsaved_terrains(
+    unsaved: &mut UnsavedTerrains,
+    lobby_terrains: &LobbyTerrains,
+    lobbies: &Lobbies,
+) {
+    for lobby_id in unsaved.lobbies.drain() {
+        let (Some(lobby), Some(terrain)) =
+            (lobbies.find(lobby_id), lobby_terrains.0.get(&lobby_id))
+        else {
+            continue;
+        };
+        save_terrain_edits(&lobby.map, lobby_id, &terrain.edit_list());
+    }
+}
+
+/// Helper remakes the colliders of the given chunks
+fn rebuild_chunk_colliders(
+    terrain: &mut VoxelTerrain,
+    dirty_chunks: HashSet<IVec3>,
+    commands: &mut Commands,
+) {
+    for chunk_pos in dirty_chunks {
+        if let Some(old_entity) = terrain.chunk_entities.remove(&chunk_pos) {
+            commands.entity(old_entity).despawn_recursive();
+        }
+        if let Some(entity) = spawn_chunk_collider(&terrain.world, chunk_pos, commands) {
+            terrain.chunk_entities.insert(chunk_pos, entity);
+        }
+    }
+}
+
+/// Helper spawns the collider of a single chunk, returns none if chunk is only air
+pub fn spawn_chunk_collider(
+    world: &VoxelWorld,
+    chunk_pos: IVec3,
+    commands: &mut Commands,
+) -> Option<Entity> {
+    let collider = world.mesh_chunk(chunk_pos).collider()?;
+    let entity = commands
+        .spawn(StaticPhysics::new(collider))
+        .insert(Position(chunk_origin(chunk_pos)))
+        .insert(VoxelChunkMarker(chunk_pos))
+        .insert(Name::new(format!("VoxelChunk {}", chunk_pos)))
+        .id();
+    Some(entity)
+}
+
+/// Whenever a lobby receives terrain settings generate it is chunks
+fn build_lobby_terrains(
+    lobbies: Res<Lobbies>,
+    mut lobby_terrains: ResMut<LobbyTerrains>,
+    mut commands: Commands,
+) {
+    if !lobbies.is_changed() {
+        return;
+    }
+
+    for lobby in lobbies.lobbies.iter() {
+        let Some(settings) = &lobby.terrain else {
+            continue;
+        };
+        if let Some(terrain) = lobby_terrains.0.get(&lobby.lobby_id) {
+            if &terrain.settings == settings {
+                continue;
+            }
+            info!(
+                "Terrain of lobby {} changed despawning old chunks",
+                lobby.lobby_id
+            );
+            for entity in terrain.chunk_entities.values() {
+                commands.entity(*entity).despawn_recursive();
+            }
+        }
+
+        info!(
+            "Generating terrain with seed {} for lobby {}",
+            settings.seed, lobby.lobby_id
+        );
+        let mut terrain = VoxelTerrain::new(settings);
+
+        let saved_edits = read_terrain_edits(&lobby.map, lobby.lobby_id);
+        info!("Applying {} saved terrain edits", saved_edits.len());
+        terrain.apply_edits(&saved_edits);
+
+        let chunk_positions: Vec<IVec3> = terrain.world.chunks.keys().copied().collect();
+        for chunk_pos in chunk_positions {
+            if let Some(entity) = spawn_chunk_collider(&terrain.world, chunk_pos, &mut commands) {
+                terrain.chunk_entities.insert(chunk_pos, entity);
+            }
+        }
+        lobby_terrains.0.insert(lobby.lobby_id, terrain);
+    }
+}
+
+/// Bullets that didnt hit a player carve a hole wherever they landed
+fn carve_terrain_on_bullet_hit(
+    mut bullet_hit_event: EventReader<BulletHitEvent>,
+    lobby_position_map: Res<LobbyPositionMap>,
+    mut lobby_terrains: ResMut<LobbyTerrains>,
+    mut pending_edits: ResMut<PendingTerrainEdits>,
+    mut commands: Commands,
+) {
+    for bullet_hit in bullet_hit_event.read() {
+        if bullet_hit.victim_client_id.is_some() {
+            continue;
+        }
+        let Some(client_info) = lobby_position_map.0.get(&bullet_hit.bullet_owner) else {
+            continue;
+        };
+        let Some(terrain) = lobby_terrains.0.get_mut(&client_info.lobby_id) else {
+            continue;
+        };
+
+        let edits = terrain
+            .world
+            .carve_sphere(bullet_hit.position, BULLET_CARVE_RADIUS);
+        if edits.is_empty() {
+            continue;
+        }
+
+        let dirty_chunks = terrain.apply_edits(&edits);
+        rebuild_chunk_colliders(terrain, dirty_chunks, &mut commands);
+
+        pending_edits
+            .0
+            .entry(client_info.lobby_id)
+            .or_default()
+            .extend(edits);
+    }
+}
+
+/// Sends every edit made since last flush in a single message per lobby and marks it for saving
+fn flush_terrain_edits(
+    mut pending_edits: ResMut<PendingTerrainEdits>,
+    mut unsaved: ResMut<UnsavedTerrains>,
+    lobbies: Res<Lobbies>,
+    mut connection_manager: ResMut<ConnectionManager>,
+) {
+    if pending_edits.0.is_empty() {
+        return;
+    }
+
+    for (lobby_id, edits) in pending_edits.0.drain() {
+        let Some(lobby) = lobbies.find(lobby_id) else {
+            continue;
+        };
+
+        debug!(
+            "Sending {} terrain edits to lobby {}",
+            edits.len(),
+            lobby_id
+        );
+        let _ = connection_manager.send_message_to_target::<CommonChannel, TerrainEdits>(
+            &mut TerrainEdits { lobby_id, edits },
+            NetworkTarget::Only(lobby.everyone()),
+        );
+        unsaved.lobbies.insert(lobby_id);
+    }
+}
+
+/// Writes edited terrains every few seconds instead of every frame that had an edit
+fn save_dirty_terrains(
+    time: Res<Time>,
+    mut unsaved: ResMut<UnsavedTerrains>,
+    lobby_terrains: Res<LobbyTerrains>,
+    lobbies: Res<Lobbies>,
+) {
+    if !unsaved.timer.tick(time.delta()).just_finished() || unsaved.lobbies.is_empty() {
+        return;
+    }
+    save_unsaved_terrains(&mut unsaved, &lobby_terrains, &lobbies);
+}
+
+/// Edits made after the last timed save would be lost when server closes
+fn save_terrains_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    mut unsaved: ResMut<UnsavedTerrains>,
+    lobby_terrains: Res<LobbyTerrains>,
+    lobbies: Res<Lobbies>,
+) {
+    if exit_events.read().next().is_none() || unsaved.lobbies.is_empty() {
+        return;
+    }
+    info!("Server closing saving terrain edits");
+    save_unsaved_terrains(&mut unsaved, &lobby_terrains, &lobbies);
+}
+
+/// Late joiners and spectators receive every edit made so far, as generating from seed would give them the untouched terrain
+fn send_terrain_to_joiner(
+    mut enter_events: EventReader<MessageEvent<EnterLobby>>,
+    mut spectate_events: EventReader<MessageEvent<SpectateLobby>>,
+    lobbies: Res<Lobbies>,
+    lobby_terrains: Res<LobbyTerrains>,
+    mut connection_manager: ResMut<ConnectionManager>,
+) {
+    let joiners: Vec<ClientId> = enter_events
+        .read()
+        .map(|event| *event.context())
+        .chain(spectate_events.read().map(|event| *event.context()))
+        .collect();
+    for client_id in joiners.iter() {
+        // Same lobby the join listener puts players in
+        let Some(lobby) = lobbies.lobbies.first() else {
+            continue;
+        };
+        let Some(terrain) = lobby_terrains.0.get(&lobby.lobby_id) else {
+            continue;
+        };
+        if terrain.edits.is_empty() {
+            continue;
+        }
+
+        info!(
+            "Sending {} terrain edits to client {}",
+            terrain.edits.len(),
+            client_id
+        );
+        let _ = connection_manager.send_message::<CommonChannel, TerrainEdits>(
+            *client_id,
+            &mut TerrainEdits {
+                lobby_id: lobby.lobby_id,
+                edits: terrain.edit_list(),
+            },
+        );
+    }
+}
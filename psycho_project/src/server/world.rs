@@ -1,41 +1,172 @@
+use crate::server::console::ConsoleCommand;
+use crate::shared::protocol::lobby_structs::Lobbies;
 use crate::shared::protocol::world_structs::*;
-use crate::shared::protocol::CommonChannel;
-use crate::shared::shared_physics::FloorPhysics;
-use avian3d::prelude::Position;
 use bevy::prelude::*;
-use lightyear::prelude::server::Replicate;
-use lightyear::prelude::*;
-use lightyear::shared::replication::network_target::NetworkTarget;
+use lightyear::shared::tick_manager::TickManager;
 
-/// Responsible for spawning the entities that are correlated to physics mechanic
+/// Responsible for the world state that is not level geometry, like the sun
 pub struct PhysicsWorldPlugin;
 
 impl Plugin for PhysicsWorldPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<CycleTimer>();
-        app.add_systems(Startup, replicate_resource);
-        app.add_systems(Startup, spawn_floor_collider);
-        //This needs refacotring make it tick based TODO
-        app.add_systems(FixedUpdate, tick_sun_cycle);
+        // Events
+        app.add_event::<SetTimeOfDay>();
+        app.add_event::<SetCycleSpeed>();
+
+        app.add_systems(FixedUpdate, rebase_sun_cycle);
+        // Headless servers dont have keyboard input
+        app.add_systems(
+            Update,
+            debug_time_of_day_keys.run_if(resource_exists::<ButtonInput<KeyCode>>),
+        );
+        app.add_systems(
+            Update,
+            (
+                console_world_commands,
+                listener_set_time_of_day,
+                listener_set_cycle_speed,
+            )
+                .chain(),
+        );
     }
 }
 
-// This need refactoring make it tick based to avoid excess of bandwith usage
-fn replicate_resource(mut commands: Commands) {
-    commands.replicate_resource::<CycleTimer, CommonChannel>(NetworkTarget::All);
+/// Server command that makes a lobby jump to a certain time of day, 0.0 is noon and 0.5 is midnight
+#[derive(Event, Debug)]
+pub struct SetTimeOfDay {
+    pub lobby_id: u64,
+    pub fraction: f32,
+}
+
+/// Server command that changes how fast the day passes in a lobby
+#[derive(Event, Debug)]
+pub struct SetCycleSpeed {
+    pub lobby_id: u64,
+    pub speed: f32,
+}
+
+/// Ticks wrap around, so every once in a while we move the epoch forward and resend it
+fn rebase_sun_cycle(mut lobbies: ResMut<Lobbies>, tick_manager: Res<TickManager>) {
+    let current_tick = tick_manager.tick();
+    // Avoid triggering change detection, as that would resend lobbies every tick
+    let needs_rebase = lobbies
+        .lobbies
+        .iter()
+        .any(|lobby| current_tick - lobby.sun_cycle.epoch_tick >= SUN_CYCLE_REBASE_TICKS);
+
+    if needs_rebase {
+        for lobby in lobbies.lobbies.iter_mut() {
+            debug!(
+                "Rebasing sun cycle epoch of lobby {} to tick {:?}",
+                lobby.lobby_id, current_tick
+            );
+            lobby.sun_cycle.rebase(current_tick);
+        }
+    }
 }
 
-/// Spawn in both server and client a single cubicle collider
-fn spawn_floor_collider(mut commands: Commands) {
-    info!("Spawning server floor and replicating to client");
-    commands
-        .spawn(FloorPhysics::default())
-        .insert(FloorMarker)
-        .insert(Replicate::default())
-        .insert(Name::new("PhysicalFloor"))
-        .insert(Position(Vec3::new(0.0, 0.0, 0.0)));
+fn listener_set_time_of_day(
+    mut events: EventReader<SetTimeOfDay>,
+    mut lobbies: ResMut<Lobbies>,
+    tick_manager: Res<TickManager>,
+) {
+    for event in events.read() {
+        if let Some(lobby) = lobbies.find_mut(event.lobby_id) {
+            info!(
+                "Setting time of day of lobby {} to {}",
+                event.lobby_id, event.fraction
+            );
+            lobby
+                .sun_cycle
+                .set_fraction(tick_manager.tick(), event.fraction);
+        } else {
+            warn!("Couldnt find lobby {} to set time of day", event.lobby_id);
+        }
+    }
 }
 
-fn tick_sun_cycle(mut cycle_time: ResMut<CycleTimer>, time: Res<Time>) {
-    cycle_time.0.tick(time.delta());
+fn listener_set_cycle_speed(
+    mut events: EventReader<SetCycleSpeed>,
+    mut lobbies: ResMut<Lobbies>,
+    tick_manager: Res<TickManager>,
+) {
+    for event in events.read() {
+        if let Some(lobby) = lobbies.find_mut(event.lobby_id) {
+            info!(
+                "Setting cycle speed of lobby {} to {}",
+                event.lobby_id, event.speed
+            );
+            lobby.sun_cycle.set_speed(tick_manager.tick(), event.speed);
+        } else {
+            warn!("Couldnt find lobby {} to set cycle speed", event.lobby_id);
+        }
+    }
+}
+
+/// Time and speed commands typed into server console, they can target any lobby
+fn console_world_commands(
+    mut console: EventReader<ConsoleCommand>,
+    mut set_time: EventWriter<SetTimeOfDay>,
+    mut set_speed: EventWriter<SetCycleSpeed>,
+) {
+    for command in console.read() {
+        match command.name.as_str() {
+            "time" => {
+                let (Some(lobby_id), Some(fraction)) =
+                    (command.arg(0, "lobby id"), command.arg(1, "fraction"))
+                else {
+                    continue;
+                };
+                set_time.send(SetTimeOfDay { lobby_id, fraction });
+            }
+            "speed" => {
+                let (Some(lobby_id), Some(speed)) =
+                    (command.arg(0, "lobby id"), command.arg(1, "speed"))
+                else {
+                    continue;
+                };
+                set_speed.send(SetCycleSpeed { lobby_id, speed });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Debug shortcut in server window for the major lobby, N makes it night, M makes it noon and up/down speed the day
+/// Other lobbies are reached through the time and speed console commands
+fn debug_time_of_day_keys(
+    keys: Res<ButtonInput<KeyCode>>,
+    lobbies: Res<Lobbies>,
+    mut set_time: EventWriter<SetTimeOfDay>,
+    mut set_speed: EventWriter<SetCycleSpeed>,
+) {
+    let Some(lobby) = lobbies.lobbies.first() else {
+        return;
+    };
+    let lobby_id = lobby.lobby_id;
+
+    if keys.just_pressed(KeyCode::KeyN) {
+        set_time.send(SetTimeOfDay {
+            lobby_id,
+            fraction: 0.5,
+        });
+    }
+    if keys.just_pressed(KeyCode::KeyM) {
+        set_time.send(SetTimeOfDay {
+            lobby_id,
+            fraction: 0.0,
+        });
+    }
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        set_speed.send(SetCycleSpeed {
+            lobby_id,
+            speed: lobby.sun_cycle.speed * 2.0,
+        });
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        set_speed.send(SetCycleSpeed {
+            lobby_id,
+            speed: lobby.sun_cycle.speed / 2.0,
+        });
+    }
 }
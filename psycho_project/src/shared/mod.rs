@@ -1,11 +1,17 @@
 use bevy::prelude::*;
+use shared_environment::SharedEnvironmentPlugin;
 use shared_gun::SharedGunPlugin;
+use shared_parts::SharedPartsPlugin;
 use shared_physics::SharedPhysicsPlugin;
 
 pub mod diagnostics;
 pub mod protocol;
+pub mod shared_auth;
+pub mod shared_environment;
 pub mod shared_gun;
+pub mod shared_parts;
 pub mod shared_physics;
+pub mod shared_voxel;
 
 use self::diagnostics::CentralDiagnosticsPlugin;
 use self::protocol::ProtocolPlugin;
@@ -22,6 +28,8 @@ impl Plugin for SharedPlugin {
         app.add_plugins(ProtocolPlugin);
         app.add_plugins(SharedPhysicsPlugin);
         app.add_plugins(SharedGunPlugin);
+        app.add_plugins(SharedPartsPlugin);
+        app.add_plugins(SharedEnvironmentPlugin);
         app.add_plugins(CentralDiagnosticsPlugin);
     }
 }
@@ -1,3 +1,4 @@
+use super::world_structs::{EnvironmentSettings, SunCycle, TerrainSettings};
 use bevy::prelude::*;
 use bevy::utils::HashMap;
 use lightyear::prelude::*;
@@ -18,6 +19,7 @@ impl Plugin for LobbyStructsPlugin {
         // Message start match related
         app.register_message::<EnterLobby>(ChannelDirection::ClientToServer);
         app.register_message::<ExitLobby>(ChannelDirection::ClientToServer);
+        app.register_message::<SpectateLobby>(ChannelDirection::ClientToServer);
 
         //Debugging
         app.register_type::<Lobbies>();
@@ -32,14 +34,47 @@ pub struct Lobbies {
     pub lobbies: Vec<Lobby>,
 }
 
+impl Lobbies {
+    pub fn find(&self, lobby_id: u64) -> Option<&Lobby> {
+        self.lobbies.iter().find(|lobby| lobby.lobby_id == lobby_id)
+    }
+
+    pub fn find_mut(&mut self, lobby_id: u64) -> Option<&mut Lobby> {
+        self.lobbies
+            .iter_mut()
+            .find(|lobby| lobby.lobby_id == lobby_id)
+    }
+}
+
+impl Lobby {
+    /// Gives me players and spectators, everyone that should see what happens in lobby
+    pub fn everyone(&self) -> Vec<ClientId> {
+        self.players
+            .iter()
+            .chain(self.spectators.iter())
+            .copied()
+            .collect()
+    }
+}
+
 // Components
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, Reflect)]
 #[reflect(PartialEq, Debug, Default, Serialize, Deserialize)]
 pub struct Lobby {
     // List of lobby players
     pub players: Vec<ClientId>,
+    // Clients only watching, they have no player and their inputs go nowhere
+    pub spectators: Vec<ClientId>,
     // Identifier of lobby in list
     pub lobby_id: u64,
+    // File name of the map this lobby plays in
+    pub map: String,
+    // Voxel terrain of this lobby map if it has one
+    pub terrain: Option<TerrainSettings>,
+    // Sun of this lobby
+    pub sun_cycle: SunCycle,
+    // Lighting and fog of this lobby
+    pub environment: EnvironmentSettings,
 }
 
 /// Gives me client precious info for other logics like how would be looby without that client and what is it is position on index
@@ -50,6 +85,7 @@ pub struct LobbyPositionMap(pub HashMap<ClientId, ClientInfo>);
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Reflect, Default)]
 #[reflect(Default, PartialEq, Debug, Serialize, Deserialize)]
 pub struct ClientInfo {
+    pub lobby_id: u64,
     pub lobby_position: usize,
     pub lobby_without_me: Vec<ClientId>,
 }
@@ -59,12 +95,18 @@ pub struct ClientInfo {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct StartGame {
     pub lobby_id: u64,
+    // If true client just watches the lobby
+    pub spectator: bool,
 }
 
 /// Happens when someone enters lobby
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct EnterLobby;
 
+/// Happens when someone wants to watch lobby without playing
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SpectateLobby;
+
 /// Happens when a player wants to leave lobbby, warn does not consider player disconnections. It  is our controlled versions
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct ExitLobby;
@@ -1,8 +1,10 @@
 use crate::shared::protocol::ComponentSyncMode;
+use crate::shared::shared_parts::PartSlot;
 use bevy::prelude::*;
 use bevy::{reflect::Reflect, utils::HashMap};
 use leafwing_input_manager::prelude::*;
 use lightyear::prelude::*;
+use lightyear::shared::tick_manager::Tick;
 use serde::{Deserialize, Serialize};
 use std::vec;
 
@@ -16,33 +18,55 @@ impl Plugin for PlayerStructPlugin {
         // Leafwing input plugin handles the whole leafwing shenanigans - WARNING FOR NOW DONT USE THE RESOURCE NOT SUPPORTED
         app.add_plugins(LeafwingInputPlugin::<PlayerAction>::default());
 
+        // Interpolation is what spectators get
         app.register_component::<MarkerPlayer>(ChannelDirection::ServerToClient)
-            .add_prediction(ComponentSyncMode::Once);
+            .add_prediction(ComponentSyncMode::Once)
+            .add_interpolation(ComponentSyncMode::Once);
 
         app.register_component::<PlayerId>(ChannelDirection::ServerToClient)
-            .add_prediction(ComponentSyncMode::Once);
+            .add_prediction(ComponentSyncMode::Once)
+            .add_interpolation(ComponentSyncMode::Once);
 
         app.register_component::<PlayerVisuals>(ChannelDirection::ServerToClient)
-            .add_prediction(ComponentSyncMode::Once);
+            .add_prediction(ComponentSyncMode::Once)
+            .add_interpolation(ComponentSyncMode::Once);
 
         app.register_component::<PlayerHealth>(ChannelDirection::ServerToClient)
-            .add_prediction(ComponentSyncMode::Simple);
+            .add_prediction(ComponentSyncMode::Simple)
+            .add_interpolation(ComponentSyncMode::Simple);
 
         app.register_component::<PlayerLookAt>(ChannelDirection::Bidirectional)
             .add_prediction(ComponentSyncMode::Full);
 
+        app.register_component::<PlayerAnimation>(ChannelDirection::ServerToClient)
+            .add_prediction(ComponentSyncMode::Full)
+            .add_interpolation(ComponentSyncMode::Simple);
+
         // Register resources
         app.register_resource::<SavePlayerBundleMap>(ChannelDirection::ServerToClient);
+        app.register_resource::<Scoreboard>(ChannelDirection::ServerToClient);
 
         // Messages when starting game and just connection
         app.register_message::<SendBundle>(ChannelDirection::ServerToClient);
 
         // Messages related to visuals
         app.register_message::<SaveVisual>(ChannelDirection::ClientToServer);
-        app.register_message::<ChangeChar>(ChannelDirection::Bidirectional);
+        app.register_message::<RejectVisual>(ChannelDirection::ServerToClient);
+        app.register_message::<ChangeChar>(ChannelDirection::ServerToClient);
+        app.register_message::<ChangeTint>(ChannelDirection::ServerToClient);
+        app.register_message::<SendOwnedParts>(ChannelDirection::ServerToClient);
+
+        // Messages related to loadouts
+        app.register_message::<ChangeLoadout>(ChannelDirection::ClientToServer);
+
+        // Messages related to dying
+        app.register_message::<PlayerDeath>(ChannelDirection::ServerToClient);
+        app.register_message::<PlayerRespawn>(ChannelDirection::ServerToClient);
 
         app.register_type::<PlayerHealth>();
+        app.register_type::<Scoreboard>();
         app.register_type::<PlayerLookAt>();
+        app.register_type::<PlayerAnimation>();
     }
 }
 
@@ -52,12 +76,25 @@ impl Plugin for PlayerStructPlugin {
 #[reflect(Resource, PartialEq, Debug, Serialize, Deserialize)]
 pub struct SavePlayerBundleMap(pub HashMap<ClientId, SavePlayerBundle>);
 
+// Kills and deaths of every player according to client id, each lobby just shows it is own players
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect, Default)]
+#[reflect(Resource, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Scoreboard(pub HashMap<ClientId, PlayerScore>);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Reflect, Default)]
+#[reflect(PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct PlayerScore {
+    pub kills: u32,
+    pub deaths: u32,
+}
+
 // Components
 /// Things that dont need to be saved
 #[derive(Bundle, Default)]
 pub struct PlayerBundle {
     player_marker: MarkerPlayer,
     health: PlayerHealth,
+    animation: PlayerAnimation,
 }
 
 // Save Player bundle - Shared player related info important to server and client here we add things that need to be saved
@@ -67,18 +104,77 @@ pub struct SavePlayerBundle {
     pub id: PlayerId,
     pub visuals: PlayerVisuals,
     pub position: PlayerPosition,
+    pub loadouts: PlayerLoadouts,
+    pub account: PlayerAccount,
 }
 
 impl SavePlayerBundle {
-    pub fn new(id: ClientId, visuals: PlayerVisuals, position: PlayerPosition) -> Self {
+    pub fn new(
+        id: ClientId,
+        visuals: PlayerVisuals,
+        position: PlayerPosition,
+        account: PlayerAccount,
+    ) -> Self {
         Self {
             id: PlayerId(id),
+            account,
+            loadouts: PlayerLoadouts::new(visuals.clone()),
             visuals: visuals,
             position: position,
         }
     }
 }
 
+/// Most loadout presets a player can keep, server rejects anything past it
+pub const MAX_LOADOUT_PRESETS: usize = 8;
+
+/// Named presets of visuals and weapon a player can swap between, visuals always mirror the active one
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+pub struct PlayerLoadouts {
+    pub presets: Vec<LoadoutPreset>,
+    // Index of preset currently in use
+    pub active: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+pub struct LoadoutPreset {
+    pub name: String,
+    pub visuals: PlayerVisuals,
+    // Weapon slot player starts holding
+    pub equipped_weapon: usize,
+}
+
+impl PlayerLoadouts {
+    pub fn new(visuals: PlayerVisuals) -> Self {
+        Self {
+            presets: vec![LoadoutPreset {
+                name: String::from("Default"),
+                visuals,
+                equipped_weapon: 0,
+            }],
+            active: 0,
+        }
+    }
+
+    // There is always at least one preset so this never fails
+    pub fn active_preset(&self) -> &LoadoutPreset {
+        &self.presets[self.active.min(self.presets.len() - 1)]
+    }
+
+    pub fn active_preset_mut(&mut self) -> &mut LoadoutPreset {
+        let active = self.active.min(self.presets.len() - 1);
+        &mut self.presets[active]
+    }
+}
+
+/// Account that owns this player, auth service made the client id out of the account id so it never changes
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect, Default)]
+pub struct PlayerAccount {
+    pub account_id: u64,
+    // Name everybody sees instead of client id
+    pub display_name: String,
+}
+
 #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
 pub struct MarkerPlayer;
 
@@ -99,8 +195,14 @@ pub struct PlayerVisuals {
     pub legs: String,
     // Character available weapon - Vec of weapons file paths
     pub weapon_1: String,
+    // Character second weapon slot
+    pub weapon_2: String,
     // Also know as the "glue" of modular characters
     pub skeleton: String,
+    // Colors of each tintable part
+    pub head_tint: PartTint,
+    pub torso_tint: PartTint,
+    pub legs_tint: PartTint,
 }
 impl Default for PlayerVisuals {
     fn default() -> Self {
@@ -110,24 +212,247 @@ impl Default for PlayerVisuals {
             torso: String::from("characters/parts/scifi_torso.glb"),
             legs: String::from("characters/parts/witch_legs.glb"),
             weapon_1: String::from("weapons/katana.glb"),
+            weapon_2: String::from("weapons/katana.glb"),
             skeleton: String::from("characters/parts/main_skeleton.glb"),
+            head_tint: PartTint::default(),
+            torso_tint: PartTint::default(),
+            legs_tint: PartTint::default(),
         }
     }
 }
 impl PlayerVisuals {
-    // Returns an iterator over the visual components. Inclu
-    pub fn iter_visuals(&self) -> impl Iterator<Item = &String> {
-        vec![&self.head, &self.torso, &self.legs, &self.skeleton].into_iter()
+    // Returns an iterator over the visual components that make the modular character and their slot
+    pub fn iter_visuals(&self) -> impl Iterator<Item = (PartSlot, &String)> {
+        vec![
+            (PartSlot::Head, &self.head),
+            (PartSlot::Torso, &self.torso),
+            (PartSlot::Legs, &self.legs),
+            (PartSlot::Skeleton, &self.skeleton),
+        ]
+        .into_iter()
+    }
+
+    // Same as above but with every single visual
+    pub fn iter_all_visuals(&self) -> impl Iterator<Item = (PartSlot, &String)> {
+        vec![
+            (PartSlot::Character, &self.character),
+            (PartSlot::Head, &self.head),
+            (PartSlot::Torso, &self.torso),
+            (PartSlot::Legs, &self.legs),
+            (PartSlot::Weapon, &self.weapon_1),
+            (PartSlot::Weapon, &self.weapon_2),
+            (PartSlot::Skeleton, &self.skeleton),
+        ]
+        .into_iter()
+    }
+
+    // Gives me the file path of the weapon in a certain weapon slot
+    pub fn weapon(&self, weapon_slot: usize) -> &String {
+        match weapon_slot {
+            1 => &self.weapon_2,
+            _ => &self.weapon_1,
+        }
+    }
+
+    // Gives me the file path of the part in a certain slot
+    pub fn part(&self, slot: PartSlot) -> &String {
+        match slot {
+            PartSlot::Character => &self.character,
+            PartSlot::Head => &self.head,
+            PartSlot::Torso => &self.torso,
+            PartSlot::Legs => &self.legs,
+            PartSlot::Weapon => &self.weapon_1,
+            PartSlot::Skeleton => &self.skeleton,
+        }
+    }
+
+    // Gives me the tint of a slot, slots that cant be tinted always have the default one
+    pub fn tint(&self, slot: PartSlot) -> PartTint {
+        match slot {
+            PartSlot::Head => self.head_tint,
+            PartSlot::Torso => self.torso_tint,
+            PartSlot::Legs => self.legs_tint,
+            _ => PartTint::default(),
+        }
+    }
+
+    // Mutable acess to tint of a slot, none if slot cant be tinted
+    pub fn tint_mut(&mut self, slot: PartSlot) -> Option<&mut PartTint> {
+        match slot {
+            PartSlot::Head => Some(&mut self.head_tint),
+            PartSlot::Torso => Some(&mut self.torso_tint),
+            PartSlot::Legs => Some(&mut self.legs_tint),
+            _ => None,
+        }
+    }
+
+    /// Clamps every tint into range, false if one of them isnt a color at all
+    pub fn sanitize_tints(&mut self) -> bool {
+        for tint in [
+            &mut self.head_tint,
+            &mut self.torso_tint,
+            &mut self.legs_tint,
+        ] {
+            match tint.sanitized() {
+                Some(sanitized) => *tint = sanitized,
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Brightest a tint emissive channel can be, enough for neon preset to glow
+pub const MAX_TINT_EMISSIVE: f32 = 4.0;
+
+/// Colors applied on top of a part materials. First material of the part gltf gets primary, the rest secondary
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct PartTint {
+    pub primary: Color,
+    pub secondary: Color,
+    pub emissive: Color,
+}
+
+impl Default for PartTint {
+    fn default() -> Self {
+        Self {
+            primary: Color::WHITE,
+            secondary: Color::WHITE,
+            emissive: Color::BLACK,
+        }
+    }
+}
+
+/// Helper clamps color channels, alpha always stays in 0 to 1
+fn clamp_color(color: Color, max: f32) -> Option<Color> {
+    let srgba = color.to_srgba();
+    let channels = [srgba.red, srgba.green, srgba.blue, srgba.alpha];
+    if channels.iter().any(|channel| !channel.is_finite()) {
+        return None;
+    }
+    Some(Color::srgba(
+        srgba.red.clamp(0.0, max),
+        srgba.green.clamp(0.0, max),
+        srgba.blue.clamp(0.0, max),
+        srgba.alpha.clamp(0.0, 1.0),
+    ))
+}
+
+impl PartTint {
+    /// Helper gives me this tint with base colors in 0 to 1 and emissive up to max, none if a channel isnt a number
+    pub fn sanitized(&self) -> Option<PartTint> {
+        Some(PartTint {
+            primary: clamp_color(self.primary, 1.0)?,
+            secondary: clamp_color(self.secondary, 1.0)?,
+            emissive: clamp_color(self.emissive, MAX_TINT_EMISSIVE)?,
+        })
+    }
+
+    // Presets players can choose from
+    pub fn presets() -> Vec<(&'static str, PartTint)> {
+        vec![
+            ("Default", PartTint::default()),
+            (
+                "Crimson",
+                PartTint {
+                    primary: Color::srgb(0.8, 0.1, 0.1),
+                    secondary: Color::srgb(0.2, 0.2, 0.2),
+                    ..default()
+                },
+            ),
+            (
+                "Navy",
+                PartTint {
+                    primary: Color::srgb(0.1, 0.2, 0.6),
+                    secondary: Color::srgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ),
+            (
+                "Forest",
+                PartTint {
+                    primary: Color::srgb(0.2, 0.5, 0.2),
+                    secondary: Color::srgb(0.4, 0.3, 0.2),
+                    ..default()
+                },
+            ),
+            (
+                "Neon",
+                PartTint {
+                    primary: Color::srgb(0.1, 0.1, 0.1),
+                    secondary: Color::srgb(0.1, 0.1, 0.1),
+                    emissive: Color::srgb(0.0, 4.0, 4.0),
+                },
+            ),
+        ]
     }
 }
 
+/// Health players spawn and respawn with
+pub const MAX_HEALTH: i32 = 10;
+
 //// Responsible for health display
 #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
 pub struct PlayerHealth(pub i32);
 
 impl Default for PlayerHealth {
     fn default() -> Self {
-        Self(10)
+        Self(MAX_HEALTH)
+    }
+}
+
+impl PlayerHealth {
+    pub fn is_dead(&self) -> bool {
+        self.0 <= 0
+    }
+}
+
+/// One shot animations that need to look the same for everyone, locomotion is derived from physics instead
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Reflect)]
+pub enum OneShotAnimation {
+    Shoot,
+    HitReceive,
+}
+
+impl OneShotAnimation {
+    /// Only upper body plays them, so each gets masked with every locomotion clip
+    pub const ALL: [OneShotAnimation; 2] = [OneShotAnimation::Shoot, OneShotAnimation::HitReceive];
+
+    /// Name of the clip in our skeletons
+    pub fn clip_name(&self) -> &'static str {
+        match self {
+            OneShotAnimation::Shoot => "Gun_Shoot",
+            OneShotAnimation::HitReceive => "HitRecieve",
+        }
+    }
+}
+
+/// Last one shot animation of a player and the tick it started, every client plays it from that tick on
+/// Set by shared systems when it can be predicted and by server when it cant, like getting hit
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+pub struct PlayerAnimation {
+    pub clip: Option<OneShotAnimation>,
+    pub start_tick: Tick,
+    pub speed: f32,
+}
+
+impl Default for PlayerAnimation {
+    fn default() -> Self {
+        Self {
+            clip: None,
+            start_tick: Tick(0),
+            speed: 1.0,
+        }
+    }
+}
+
+impl PlayerAnimation {
+    pub fn new(clip: OneShotAnimation, start_tick: Tick) -> Self {
+        Self {
+            clip: Some(clip),
+            start_tick,
+            speed: 1.0,
+        }
     }
 }
 
@@ -160,6 +485,9 @@ pub struct PlayerLookAt(pub Vec3);
 )]
 pub struct PlayerPosition(pub Vec3);
 
+/// Stick values below this are ignored, sticks rarely rest at exactly zero
+pub const DEFAULT_STICK_DEADZONE: f32 = 0.15;
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Reflect, Serialize, Deserialize)]
 pub enum PlayerAction {
     Forward,
@@ -168,8 +496,13 @@ pub enum PlayerAction {
     Right,
     Jump,
     Shoot,
+    SwitchWeapon,
+    // Pressed by aiming camera modes, player faces RotateToCamera while it is
+    Aim,
     RotateToCamera,
     Direction,
+    // Left stick, client turns it into direction according to camera
+    Move,
 }
 
 impl Actionlike for PlayerAction {
@@ -181,17 +514,47 @@ impl Actionlike for PlayerAction {
             Self::Right => InputControlKind::Button,
             Self::Jump => InputControlKind::Button,
             Self::Shoot => InputControlKind::Button,
+            Self::SwitchWeapon => InputControlKind::Button,
+            Self::Aim => InputControlKind::Button,
             Self::RotateToCamera => InputControlKind::DualAxis,
             Self::Direction => InputControlKind::DualAxis,
+            Self::Move => InputControlKind::DualAxis,
         }
     }
 }
 
 impl PlayerAction {
+    /// Direction player is aiming at, only when camera is in an aiming mode
+    pub fn aim_rotation(action_state: &ActionState<Self>) -> Option<Quat> {
+        if !action_state.pressed(&Self::Aim) {
+            return None;
+        }
+        let camera_rotation = action_state.axis_pair(&Self::RotateToCamera);
+        Some(Quat::from_euler(
+            EulerRot::YXZ,
+            camera_rotation.y,
+            camera_rotation.x,
+            0.0,
+        ))
+    }
+
+    /// Gamepad layout is fixed, only the stick deadzone changes
+    pub fn gamepad_input_map(deadzone: f32) -> InputMap<Self> {
+        InputMap::default()
+            .with_dual_axis(
+                Self::Move,
+                GamepadStick::LEFT.with_circle_deadzone(deadzone),
+            )
+            .with(Self::Jump, GamepadButtonType::South)
+            .with(Self::Shoot, GamepadButtonType::RightTrigger2)
+            .with(Self::SwitchWeapon, GamepadButtonType::North)
+    }
+
     pub fn default_input_map() -> InputMap<Self> {
-        let input_map = InputMap::default()
+        let input_map = Self::gamepad_input_map(DEFAULT_STICK_DEADZONE)
             .with(Self::Jump, KeyCode::Space)
             .with(Self::Shoot, MouseButton::Left)
+            .with(Self::SwitchWeapon, KeyCode::KeyQ)
             .with(Self::Forward, KeyCode::KeyW)
             .with(Self::Backward, KeyCode::KeyS)
             .with(Self::Left, KeyCode::KeyA)
@@ -205,14 +568,49 @@ impl PlayerAction {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct SendBundle(pub SavePlayerBundle);
 
+// An event message sent by server to clients in the same lobby when a player health reaches zero
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlayerDeath {
+    pub victim: ClientId,
+    pub killer: ClientId,
+    // Velocity of the bullet that killed him, ragdoll gets pushed along it
+    pub bullet_velocity: Vec3,
+}
+
+// An event message sent by server to clients in the same lobby when a dead player comes back
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlayerRespawn(pub ClientId);
+
 // An event message sent by client to server that gives the player currently chosen loadout
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct SaveVisual(pub PlayerVisuals);
 
-// An event message sent by client to server to all clients
+// An event message sent by server when it didnt accept the loadout the client asked for, tells him why
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RejectVisual(pub String);
+
+// An event message sent by server to a client with every part he owns, when he connects and when he gets new ones
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SendOwnedParts(pub Vec<String>);
+
+// An event message sent by server to clients in the same lobby, after it accepted a player new visuals
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
 pub struct ChangeChar(pub (ClientId, PartToChange));
 
+// An event message sent by client to server to manage his loadout presets, indexes are positions in presets
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ChangeLoadout {
+    // Creates a new preset from current visuals and selects it
+    Create(String),
+    Rename(usize, String),
+    Delete(usize),
+    Select(usize),
+}
+
+// An event message sent by server to clients in the same lobby, when a player changes the colors of one of his parts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+pub struct ChangeTint(pub (ClientId, PartSlot, PartTint));
+
 // Tell me the parts to change when grabing char customizer resource
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
 pub struct PartToChange {
@@ -12,9 +12,13 @@ impl Plugin for WeaponStructPlugin {
     fn build(&self, app: &mut App) {
         app.register_component::<Weapon>(ChannelDirection::ServerToClient)
             .add_prediction(ComponentSyncMode::Full);
+        app.register_component::<EquippedWeapon>(ChannelDirection::ServerToClient)
+            .add_prediction(ComponentSyncMode::Full)
+            .add_interpolation(ComponentSyncMode::Simple);
         app.register_component::<BulletMarker>(ChannelDirection::ServerToClient)
             .add_prediction(ComponentSyncMode::Once);
         app.register_type::<Weapon>();
+        app.register_type::<EquippedWeapon>();
     }
 }
 
@@ -39,6 +43,19 @@ impl Weapon {
         }
     }
 }
+/// Amount of weapons a player can carry
+pub const WEAPON_SLOTS: usize = 2;
+
+/// Which of the player weapon slots he is currently holding
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default, Reflect)]
+pub struct EquippedWeapon(pub usize);
+
+impl EquippedWeapon {
+    pub fn next(&self) -> Self {
+        Self((self.0 + 1) % WEAPON_SLOTS)
+    }
+}
+
 /// Utilized for spawning bullet
 #[derive(Bundle)]
 pub struct BulletBundle {
@@ -1,7 +1,10 @@
 //! Here are located every single struct that is synced and envolves world
 use crate::shared::protocol::*;
+use crate::shared::shared_voxel::VoxelEdit;
 use avian3d::prelude::*;
-use bevy::utils::Duration;
+use bevy::utils::HashMap;
+use common::shared::FIXED_TIMESTEP_HZ;
+use lightyear::shared::tick_manager::Tick;
 use serde::{Deserialize, Serialize};
 
 /// Anything that is general and need to be synced is here
@@ -9,18 +12,21 @@ pub struct WorldStructsPlugin;
 
 impl Plugin for WorldStructsPlugin {
     fn build(&self, app: &mut App) {
-        // Resources
-        app.register_resource::<CycleTimer>(ChannelDirection::ServerToClient);
+        // Messages
+        app.register_message::<TerrainEdits>(ChannelDirection::ServerToClient);
 
         // Physics
+        // Spectators get players interpolated, animations need their velocity too
         app.register_component::<LinearVelocity>(ChannelDirection::ServerToClient)
-            .add_prediction(ComponentSyncMode::Full);
+            .add_prediction(ComponentSyncMode::Full)
+            .add_interpolation(ComponentSyncMode::Simple);
 
         app.register_component::<AngularVelocity>(ChannelDirection::ServerToClient)
             .add_prediction(ComponentSyncMode::Full);
 
         app.register_component::<Name>(ChannelDirection::ServerToClient)
-            .add_prediction(ComponentSyncMode::Once);
+            .add_prediction(ComponentSyncMode::Once)
+            .add_interpolation(ComponentSyncMode::Once);
 
         // Position and Rotation have a `correction_fn` set, which is used to smear rollback errors
         // over a few frames, just for the rendering part in postudpate.
@@ -29,38 +35,236 @@ impl Plugin for WorldStructsPlugin {
         // out rendering between fixedupdate ticks.
         app.register_component::<Position>(ChannelDirection::ServerToClient)
             .add_prediction(ComponentSyncMode::Full)
+            .add_interpolation(ComponentSyncMode::Full)
             .add_interpolation_fn(position::lerp)
             .add_correction_fn(position::lerp);
 
         app.register_component::<Rotation>(ChannelDirection::ServerToClient)
             .add_prediction(ComponentSyncMode::Full)
+            .add_interpolation(ComponentSyncMode::Full)
             .add_interpolation_fn(rotation::lerp)
             .add_correction_fn(rotation::lerp);
 
         // World components
-        app.register_component::<FloorMarker>(ChannelDirection::ServerToClient)
+        app.register_component::<MapCollider>(ChannelDirection::ServerToClient)
+            .add_prediction(ComponentSyncMode::Once);
+
+        app.register_component::<MapProp>(ChannelDirection::ServerToClient)
             .add_prediction(ComponentSyncMode::Once);
 
         // Shared debuggin
-        app.register_type::<CycleTimer>();
+        app.register_type::<MapCollider>();
+        app.register_type::<MapProp>();
+        app.register_type::<TerrainSettings>();
+        app.register_type::<SunCycle>();
+        app.register_type::<EnvironmentSettings>();
+        app.register_type::<LobbyTime>();
+        app.register_type::<TimeOfDay>();
+    }
+}
+
+/// Shapes available for map geometry, sizes are full lengths same as avian
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+pub enum MapShape {
+    Cuboid { x: f32, y: f32, z: f32 },
+    Sphere { radius: f32 },
+    Cylinder { radius: f32, height: f32 },
+    Capsule { radius: f32, height: f32 },
+}
+
+impl MapShape {
+    pub fn collider(&self) -> Collider {
+        match *self {
+            MapShape::Cuboid { x, y, z } => Collider::cuboid(x, y, z),
+            MapShape::Sphere { radius } => Collider::sphere(radius),
+            MapShape::Cylinder { radius, height } => Collider::cylinder(radius, height),
+            MapShape::Capsule { radius, height } => Collider::capsule(radius, height),
+        }
+    }
+
+    pub fn mesh(&self) -> Mesh {
+        match *self {
+            MapShape::Cuboid { x, y, z } => Cuboid::new(x, y, z).into(),
+            MapShape::Sphere { radius } => Sphere::new(radius).into(),
+            MapShape::Cylinder { radius, height } => Cylinder::new(radius, height).into(),
+            MapShape::Capsule { radius, height } => Capsule3d::new(radius, height).into(),
+        }
+    }
+}
+
+/// Static piece of level geometry that came from a map file, client only needs to add physics and cosmetics to it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Component, Reflect)]
+pub struct MapCollider {
+    pub shape: MapShape,
+    pub color: Color,
+}
+
+/// Decorative scene placed by a map file, collider is optional as some props are just for show
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Component, Reflect)]
+pub struct MapProp {
+    // File path to gltf scene
+    pub scene: String,
+    pub collider: Option<MapShape>,
+}
+
+/// Batch of voxel changes sent by server to the players of a lobby
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TerrainEdits {
+    pub lobby_id: u64,
+    pub edits: Vec<VoxelEdit>,
+}
+
+/// Everything needed to generate the exact same voxel terrain in server and client
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+#[reflect(PartialEq, Debug, Serialize, Deserialize)]
+pub struct TerrainSettings {
+    pub seed: u64,
+    // Chunks generated in each direction of x and z starting from origin
+    pub radius: i32,
+    // Chunks stacked from y zero
+    pub height_chunks: i32,
+    // Average height of terrain in voxels
+    pub base_height: i32,
+    // How many voxels terrain goes above and below base height
+    pub amplitude: f32,
+    // How stretched the hills are smaller means wider hills
+    pub frequency: f32,
+}
+
+/// Default cycle duration is 24 hours (in ticks), but this can be changed
+pub const DEFAULT_DAY_LENGTH_TICKS: u32 = 24 * 60 * 60 * FIXED_TIMESTEP_HZ as u32;
+
+/// Tick differences are an i16 so the server must move the epoch forward before we get near that limit
+pub const SUN_CYCLE_REBASE_TICKS: i16 = i16::MAX / 2;
+
+/// Deterministic sun cycle, both sides derive the sun position from the shared tick so the server only sends this when the epoch or speed changes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+#[reflect(PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct SunCycle {
+    // Tick in which the cycle was at epoch fraction
+    pub epoch_tick: Tick,
+    // How much of the cycle had already passed in epoch tick, goes from 0.0 to 1.0
+    pub epoch_fraction: f32,
+    // Amount of ticks a full day takes when speed is 1.0
+    pub day_length_ticks: u32,
+    // Multiplier of how fast the day passes
+    pub speed: f32,
+}
+
+impl Default for SunCycle {
+    fn default() -> Self {
+        Self {
+            epoch_tick: Tick(0),
+            epoch_fraction: 0.0,
+            day_length_ticks: DEFAULT_DAY_LENGTH_TICKS,
+            speed: 1.0,
+        }
     }
 }
 
-/// Marker component utilized in sync to know which entity from server I should replicated
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Component)]
-pub struct FloorMarker;
+impl SunCycle {
+    /// How much of the day has passed in the given tick, goes from 0.0 to 1.0
+    pub fn fraction_at(&self, tick: Tick) -> f32 {
+        let elapsed_ticks = (tick - self.epoch_tick) as f32;
+        let elapsed_fraction = elapsed_ticks * self.speed / self.day_length_ticks as f32;
+        (self.epoch_fraction + elapsed_fraction).rem_euclid(1.0)
+    }
+
+    /// Moves the epoch to the given tick while keeping the same sun position
+    pub fn rebase(&mut self, tick: Tick) {
+        self.epoch_fraction = self.fraction_at(tick);
+        self.epoch_tick = tick;
+    }
+
+    /// Jumps straight to the given fraction of the day
+    pub fn set_fraction(&mut self, tick: Tick, fraction: f32) {
+        self.epoch_fraction = fraction.rem_euclid(1.0);
+        self.epoch_tick = tick;
+    }
+
+    /// Changes how fast the day passes without making the sun jump
+    pub fn set_speed(&mut self, tick: Tick, speed: f32) {
+        self.rebase(tick);
+        self.speed = speed;
+    }
+}
 
-/// Cycle time of the sun, a simple time that is repeating mode everytime he finished we tick a little bit our server sun position
-#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
-#[reflect(Resource, PartialEq, Debug, Default, Serialize, Deserialize)]
-pub struct CycleTimer(pub Timer);
+/// Lighting and fog of a lobby, what is in between day and night values is blended according to sun height
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+#[reflect(PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct EnvironmentSettings {
+    pub ambient_day: f32,
+    pub ambient_night: f32,
+    pub fog_color_day: Color,
+    pub fog_color_night: Color,
+    // Distance where everything is swallowed by fog
+    pub fog_distance_day: f32,
+    pub fog_distance_night: f32,
+}
 
-impl Default for CycleTimer {
+impl Default for EnvironmentSettings {
     fn default() -> Self {
-        CycleTimer(Timer::new(
-            // Default cycle duration is 24 hours (in seconds), but this can be changed
-            Duration::from_secs(24 * 60 * 60),
-            TimerMode::Repeating,
-        ))
+        Self {
+            ambient_day: 500.0,
+            ambient_night: 20.0,
+            fog_color_day: Color::srgb(0.55, 0.65, 0.75),
+            fog_color_night: Color::srgb(0.02, 0.02, 0.05),
+            fog_distance_day: 200.0,
+            fog_distance_night: 30.0,
+        }
+    }
+}
+
+/// Current time of day of every lobby, gameplay systems on both sides should read this instead of the sun
+#[derive(Resource, Clone, Debug, PartialEq, Reflect, Default)]
+#[reflect(Resource, PartialEq, Debug, Default)]
+pub struct TimeOfDay(pub HashMap<u64, LobbyTime>);
+
+impl TimeOfDay {
+    pub fn of_lobby(&self, lobby_id: u64) -> Option<&LobbyTime> {
+        self.0.get(&lobby_id)
+    }
+
+    /// Lobbies that were not ticked yet are considered day
+    pub fn is_night(&self, lobby_id: u64) -> bool {
+        self.of_lobby(lobby_id)
+            .map(|lobby_time| lobby_time.is_night())
+            .unwrap_or(false)
+    }
+}
+
+/// Time of day of a single lobby, derived from it is sun cycle so it is the same in server and client
+#[derive(Clone, Copy, Debug, PartialEq, Reflect, Default)]
+pub struct LobbyTime {
+    // Fraction of cycle 0.0 is noon and 0.5 is midnight
+    pub fraction: f32,
+    // Angle of the sun around the world in radians
+    pub sun_angle: f32,
+    // Goes from -1.0 sun straight below to 1.0 sun straight above
+    pub sun_height: f32,
+}
+
+impl LobbyTime {
+    pub fn from_fraction(fraction: f32) -> Self {
+        let sun_angle = fraction * std::f32::consts::PI * 2.0;
+        Self {
+            fraction,
+            sun_angle,
+            sun_height: sun_angle.cos(),
+        }
+    }
+
+    /// Hour in a 24 hours clock
+    pub fn hour(&self) -> f32 {
+        (self.fraction * 24.0 + 12.0).rem_euclid(24.0)
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.sun_height < 0.0
+    }
+
+    /// How much of the day light is present, 0.0 in night and 1.0 at noon
+    pub fn daylight(&self) -> f32 {
+        self.sun_height.max(0.0)
     }
 }
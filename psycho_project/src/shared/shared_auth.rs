@@ -0,0 +1,69 @@
+//! What clients and the auth service say to each other, it is plain tcp outside of lightyear
+//! Auth service gives out netcode connect tokens, so only clients that logged in can join the game server
+//! Passwords arent encrypted on the way, that is why auth service only listens on the machine of the game server
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+
+/// Port auth service listens to, only reachable from the same machine as the game server
+pub const AUTH_PORT: u16 = 5001;
+
+/// Limits of what players can pick
+pub const USERNAME_LENGTH: (usize, usize) = (3, 16);
+pub const MIN_PASSWORD_LENGTH: usize = 6;
+pub const MAX_DISPLAY_NAME: usize = 16;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum AuthRequest {
+    Login {
+        username: String,
+        password: String,
+    },
+    Register {
+        username: String,
+        password: String,
+        display_name: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum AuthResponse {
+    // Account id is also the client id of the token
+    Accepted {
+        account_id: u64,
+        display_name: String,
+        connect_token: Vec<u8>,
+    },
+    // Tells player why
+    Rejected(String),
+}
+
+/// Both sides must encode the same way, limit keeps someone from making us allocate a huge message
+pub fn auth_bincode() -> impl Options {
+    bincode::DefaultOptions::new().with_limit(4096)
+}
+
+/// Helper gives me the name players see, trimmed and cut down to size
+pub fn display_name(name: &str) -> String {
+    name.trim().chars().take(MAX_DISPLAY_NAME).collect()
+}
+
+/// Helper tells me what is wrong with the credentials, none if they are fine
+pub fn validate_credentials(username: &str, password: &str) -> Option<String> {
+    let (min, max) = USERNAME_LENGTH;
+    if username.len() < min || username.len() > max {
+        return Some(format!("Username needs {} to {} characters", min, max));
+    }
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Some("Username can only have letters, numbers and _".to_string());
+    }
+    if password.len() < MIN_PASSWORD_LENGTH {
+        return Some(format!(
+            "Password needs at least {} characters",
+            MIN_PASSWORD_LENGTH
+        ));
+    }
+    None
+}
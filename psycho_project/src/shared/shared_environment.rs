@@ -0,0 +1,32 @@
+//! Time of day logic that must be equal in server and client, everything is derived from the lobby sun cycle plus the current tick
+use crate::shared::protocol::lobby_structs::Lobbies;
+use crate::shared::protocol::world_structs::*;
+use bevy::prelude::*;
+use lightyear::shared::tick_manager::TickManager;
+
+/// Keeps the time of day of every lobby updated
+pub struct SharedEnvironmentPlugin;
+
+impl Plugin for SharedEnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeOfDay>();
+        app.add_systems(FixedUpdate, update_time_of_day);
+    }
+}
+
+/// Calculates the time of day of every lobby we know about
+fn update_time_of_day(
+    lobbies: Res<Lobbies>,
+    tick_manager: Res<TickManager>,
+    mut time_of_day: ResMut<TimeOfDay>,
+) {
+    let current_tick = tick_manager.tick();
+
+    time_of_day.0.clear();
+    for lobby in lobbies.lobbies.iter() {
+        let fraction = lobby.sun_cycle.fraction_at(current_tick);
+        time_of_day
+            .0
+            .insert(lobby.lobby_id, LobbyTime::from_fraction(fraction));
+    }
+}
@@ -26,7 +26,7 @@ impl Plugin for SharedGunPlugin {
         // Fixed update because physics related also needs to occur in input
         app.add_systems(
             FixedUpdate,
-            (process_collisions, lifetime_despawner).in_set(InputPhysicsSet::Input),
+            (process_collisions, lifetime_despawner, switch_weapon).in_set(InputPhysicsSet::Input),
         );
 
         app.add_systems(PostProcessCollisions, filter_own_bullet_collisions);
@@ -42,6 +42,7 @@ pub struct BulletHitEvent {
     /// if it struck a player, this is their clientid:
     pub victim_client_id: Option<ClientId>,
     pub position: Vec3,
+    pub bullet_velocity: Vec3,
 }
 
 /// Responsible for spawning predicted bullets both in client and in server
@@ -54,6 +55,8 @@ pub fn shared_spawn_bullet(
             &PlayerId,
             &ActionState<PlayerAction>,
             &mut Weapon,
+            &mut PlayerAnimation,
+            &PlayerHealth,
         ),
         Or<(With<Predicted>, With<ReplicationTarget>)>,
     >,
@@ -69,10 +72,18 @@ pub fn shared_spawn_bullet(
     // Current tick
     let current_tick = tick_manager.tick();
 
-    for (player_position, player_rotation, player_velocity, player_id, action_state, mut weapon) in
-        query.iter_mut()
+    for (
+        player_position,
+        player_rotation,
+        player_velocity,
+        player_id,
+        action_state,
+        mut weapon,
+        mut player_animation,
+        player_health,
+    ) in query.iter_mut()
     {
-        if !action_state.just_pressed(&PlayerAction::Shoot) {
+        if !action_state.just_pressed(&PlayerAction::Shoot) || player_health.is_dead() {
             continue;
         }
         // Tick difference between weapon and current tick
@@ -89,10 +100,14 @@ pub fn shared_spawn_bullet(
 
         let _prev_last_fire_tick = weapon.last_fire_tick;
         weapon.last_fire_tick = current_tick;
+        // Same tick on server and client so shooting animation needs no extra message
+        *player_animation = PlayerAnimation::new(OneShotAnimation::Shoot, current_tick);
 
         let bullet_spawn_offset = Vec3::new(0.0, 0.5, -2.0);
         let bullet_origin = player_position.0 + bullet_spawn_offset;
-        let bullet_linvel = player_rotation * (-Vec3::Z * weapon.bullet_speed) + player_velocity.0;
+        // Aiming shoots where camera looks, pitch included
+        let aim_rotation = PlayerAction::aim_rotation(action_state).unwrap_or(player_rotation.0);
+        let bullet_linvel = aim_rotation * (-Vec3::Z * weapon.bullet_speed) + player_velocity.0;
 
         // We do this to avoid interlapse among bullets fired in same tick
         let prespawned = PreSpawnedPlayerObject::default_with_salt(player_id.0.to_bits());
@@ -124,6 +139,24 @@ pub fn shared_spawn_bullet(
     }
 }
 
+/// Cycles through weapon slots, runs in rollback too as equipped weapon is predicted
+pub fn switch_weapon(
+    mut query: Query<
+        (
+            &ActionState<PlayerAction>,
+            &PlayerHealth,
+            &mut EquippedWeapon,
+        ),
+        Or<(With<Predicted>, With<ReplicationTarget>)>,
+    >,
+) {
+    for (action_state, player_health, mut equipped_weapon) in query.iter_mut() {
+        if action_state.just_pressed(&PlayerAction::SwitchWeapon) && !player_health.is_dead() {
+            *equipped_weapon = equipped_weapon.next();
+        }
+    }
+}
+
 /// THE EXTERMINATOR OF BULLETS
 pub fn lifetime_despawner(
     q: Query<(Entity, &Lifetime)>,
@@ -150,7 +183,7 @@ pub fn lifetime_despawner(
 /// Process all type of collisions warning this is gonna be a biggie
 pub fn process_collisions(
     mut collision_event_reader: EventReader<Collision>,
-    bullet_q: Query<(&BulletMarker, &Position)>,
+    bullet_q: Query<(&BulletMarker, &Position, &LinearVelocity)>,
     player_q: Query<&PlayerId>,
     identity: NetworkIdentity,
     mut commands: Commands,
@@ -158,7 +191,7 @@ pub fn process_collisions(
 ) {
     for collision in collision_event_reader.read() {
         let contact = &collision.0;
-        if let Ok((bullet, bullet_pos)) = bullet_q.get(contact.entity1) {
+        if let Ok((bullet, bullet_pos, bullet_velocity)) = bullet_q.get(contact.entity1) {
             // despawn the bullet
             if identity.is_server() {
                 commands
@@ -175,6 +208,7 @@ pub fn process_collisions(
                     bullet_owner: bullet.owner,
                     victim_client_id: Some(victim_client_id.0),
                     position: bullet_pos.0,
+                    bullet_velocity: bullet_velocity.0,
                 };
                 hit_ev_writer.send(ev);
             } else {
@@ -184,12 +218,13 @@ pub fn process_collisions(
                     bullet_owner: bullet.owner,
                     victim_client_id: None,
                     position: bullet_pos.0,
+                    bullet_velocity: bullet_velocity.0,
                 };
                 hit_ev_writer.send(ev);
             }
         }
         // Twice because collisions are vice versa sometimes
-        if let Ok((bullet, bullet_pos)) = bullet_q.get(contact.entity2) {
+        if let Ok((bullet, bullet_pos, bullet_velocity)) = bullet_q.get(contact.entity2) {
             // despawn the bullet
             if identity.is_server() {
                 commands
@@ -206,6 +241,7 @@ pub fn process_collisions(
                     bullet_owner: bullet.owner,
                     victim_client_id: Some(victim_client_id.0),
                     position: bullet_pos.0,
+                    bullet_velocity: bullet_velocity.0,
                 };
                 hit_ev_writer.send(ev);
             } else {
@@ -214,6 +250,7 @@ pub fn process_collisions(
                     bullet_owner: bullet.owner,
                     victim_client_id: None,
                     position: bullet_pos.0,
+                    bullet_velocity: bullet_velocity.0,
                 };
                 hit_ev_writer.send(ev);
             };
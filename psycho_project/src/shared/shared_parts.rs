@@ -0,0 +1,135 @@
+//! Catalog of every character part, client uses it to load and display parts and server to validate them
+//! Adding a new part should only require a new entry in the catalog file
+use crate::shared::protocol::player_structs::PlayerVisuals;
+use bevy::asset::ron;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Where our part catalog is stored
+const PART_CATALOG_PATH: &str = "./psycho_project/assets/characters/parts.ron";
+
+pub struct SharedPartsPlugin;
+
+impl Plugin for SharedPartsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PartCatalog>();
+
+        // Needs to exist before client starts loading assets, so no startup system here
+        app.insert_resource(PartCatalog::read());
+    }
+}
+
+/// Where in the character a part goes
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum PartSlot {
+    Character,
+    Head,
+    Torso,
+    Legs,
+    Weapon,
+    Skeleton,
+}
+
+/// What a player needs to be able to equip a part
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, Reflect)]
+pub enum UnlockRequirement {
+    // Everyone has it
+    #[default]
+    Free,
+    // Only players that own it
+    Owned,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Reflect)]
+pub struct PartDefinition {
+    pub slot: PartSlot,
+    // File path to gltf file, also works as the part identifier
+    pub mesh: String,
+    // File path to image shown in inventory
+    pub thumbnail: String,
+    pub display_name: String,
+    #[serde(default)]
+    pub unlock: UnlockRequirement,
+    // Only used by skeletons, bone name in parts to bone name in this skeleton. Bones not in here keep their name
+    #[serde(default)]
+    pub bone_map: HashMap<String, String>,
+    // Only used by skeletons, this bone and everything under it plays upper body actions while legs keep walking
+    #[serde(default)]
+    pub upper_body_bone: Option<String>,
+}
+
+impl PartDefinition {
+    /// Gives me the bone of this skeleton that a part bone should follow
+    pub fn skeleton_bone<'a>(&'a self, part_bone: &'a str) -> &'a str {
+        self.bone_map
+            .get(part_bone)
+            .map(|skeleton_bone| skeleton_bone.as_str())
+            .unwrap_or(part_bone)
+    }
+}
+
+/// Every part available in game
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default, Reflect)]
+#[reflect(Resource)]
+pub struct PartCatalog {
+    pub parts: Vec<PartDefinition>,
+}
+
+impl PartCatalog {
+    /// Reads catalog file, if anything goes wrong we error and return an empty catalog
+    pub fn read() -> Self {
+        let catalog_str = match fs::read_to_string(PART_CATALOG_PATH) {
+            Ok(catalog_str) => catalog_str,
+            Err(err) => {
+                error!("Couldnt read part catalog {}: {}", PART_CATALOG_PATH, err);
+                return Self::default();
+            }
+        };
+        match ron::de::from_str::<PartCatalog>(&catalog_str) {
+            Ok(catalog) => {
+                info!("Loaded part catalog with {} parts", catalog.parts.len());
+                catalog
+            }
+            Err(err) => {
+                error!("Couldnt parse part catalog {}: {}", PART_CATALOG_PATH, err);
+                Self::default()
+            }
+        }
+    }
+
+    /// Gives me the part of a given gltf file path
+    pub fn find(&self, mesh: &str) -> Option<&PartDefinition> {
+        self.parts.iter().find(|part| part.mesh == mesh)
+    }
+
+    /// Every part that goes into the given slot
+    pub fn of_slot(&self, slot: PartSlot) -> impl Iterator<Item = &PartDefinition> {
+        self.parts.iter().filter(move |part| part.slot == slot)
+    }
+
+    /// File path of every gltf file we need to load
+    pub fn mesh_paths(&self) -> Vec<String> {
+        self.parts.iter().map(|part| part.mesh.clone()).collect()
+    }
+
+    /// Checks that every part exists, is in the right slot and that the player is able to use it
+    pub fn validate(&self, visuals: &PlayerVisuals, owned_parts: &[String]) -> Result<(), String> {
+        for (slot, mesh) in visuals.iter_all_visuals() {
+            let Some(part) = self.find(mesh) else {
+                return Err(format!("Unknown part {}", mesh));
+            };
+            if part.slot != slot {
+                return Err(format!(
+                    "Part {} doesnt go in {:?}",
+                    part.display_name, slot
+                ));
+            }
+            if part.unlock == UnlockRequirement::Owned && !owned_parts.contains(mesh) {
+                return Err(format!("You dont own {}", part.display_name));
+            }
+        }
+        Ok(())
+    }
+}
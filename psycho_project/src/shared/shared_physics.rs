@@ -77,18 +77,16 @@ pub const REPLICATION_GROUP: ReplicationGroup = ReplicationGroup::new_id(1);
 pub const CHARACTER_CAPSULE_RADIUS: f32 = 0.5;
 pub const CHARACTER_CAPSULE_HEIGHT: f32 = 0.5;
 
-pub const FLOOR_WIDTH: f32 = 100.0;
-pub const FLOOR_HEIGHT: f32 = 0.5;
-
 pub const BULLET_RADIUS: f32 = 0.5;
 pub const BULLET_HEIGHT: f32 = 0.5;
 
 /// Collision layers
 #[derive(PhysicsLayer)]
-enum GameLayer {
+pub enum GameLayer {
     Player,
     Ground,
     Bullet,
+    Ragdoll,
 }
 
 #[derive(Bundle)]
@@ -131,17 +129,43 @@ impl Default for PlayerPhysics {
     }
 }
 
+/// Physics of level geometry, anything that is part of the map and never moves
 #[derive(Bundle)]
-pub struct FloorPhysics {
+pub struct StaticPhysics {
     pub collider: Collider,
     pub rigid_body: RigidBody,
+    pub collison_layer: CollisionLayers,
 }
 
-impl Default for FloorPhysics {
-    fn default() -> Self {
+impl StaticPhysics {
+    pub fn new(collider: Collider) -> Self {
         Self {
-            collider: Collider::cuboid(FLOOR_WIDTH, FLOOR_HEIGHT, FLOOR_WIDTH),
+            collider,
             rigid_body: RigidBody::Static,
+            collison_layer: CollisionLayers::new(
+                GameLayer::Ground,
+                [GameLayer::Player, GameLayer::Bullet, GameLayer::Ragdoll],
+            ),
+        }
+    }
+}
+
+/// Physics of a single ragdoll bone, client only and only hits level geometry
+#[derive(Bundle)]
+pub struct RagdollPhysics {
+    pub collider: Collider,
+    pub rigid_body: RigidBody,
+    pub collison_layer: CollisionLayers,
+    pub friction: Friction,
+}
+
+impl RagdollPhysics {
+    pub fn new(collider: Collider) -> Self {
+        Self {
+            collider,
+            rigid_body: RigidBody::Dynamic,
+            collison_layer: CollisionLayers::new(GameLayer::Ragdoll, [GameLayer::Ground]),
+            friction: Friction::new(0.8),
         }
     }
 }
@@ -239,11 +263,9 @@ pub fn apply_character_action(
     //     .external_force
     //     .apply_force(Vec3::new(0.0, -10.0, 0.0));
 
-    // Handle looking at
-
-    // let camera_rotation = action_state.axis_pair(&PlayerAction::RotateToCamera);
-
-    // let quat_rotation = Quat::from_euler(EulerRot::YXZ, camera_rotation.y, 0.0, 0.0);
-
-    // character.rotation.0 = quat_rotation;
+    // Handle looking at, only yaw as we dont want player tilting
+    if action_state.pressed(&PlayerAction::Aim) {
+        let camera_rotation = action_state.axis_pair(&PlayerAction::RotateToCamera);
+        character.rotation.0 = Quat::from_rotation_y(camera_rotation.y);
+    }
 }
@@ -0,0 +1,481 @@
+//! Voxel terrain data that must be identical in server and client, generation is seeded so only the seed needs to travel
+//! Here lies the chunk storage, the generator and the greedy mesher that feeds both the render mesh and the trimesh collider
+use crate::shared::protocol::world_structs::TerrainSettings;
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use bevy::render::{
+    mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology,
+};
+use bevy::utils::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+/// Amount of voxels in each axis of a chunk
+pub const CHUNK_SIZE: i32 = 16;
+
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// A single block of our world
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Hash, Serialize, Deserialize, Reflect)]
+pub enum Voxel {
+    #[default]
+    Air,
+    Grass,
+    Dirt,
+    Stone,
+}
+
+impl Voxel {
+    pub fn is_solid(&self) -> bool {
+        *self != Voxel::Air
+    }
+
+    /// Vertex color of each voxel type, no textures for now
+    pub fn color(&self) -> [f32; 4] {
+        match self {
+            Voxel::Air => [0.0, 0.0, 0.0, 0.0],
+            Voxel::Grass => [0.2, 0.6, 0.2, 1.0],
+            Voxel::Dirt => [0.45, 0.3, 0.15, 1.0],
+            Voxel::Stone => [0.5, 0.5, 0.5, 1.0],
+        }
+    }
+}
+
+/// Marker component of chunk entities tells me which chunk they represent
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct VoxelChunkMarker(pub IVec3);
+
+/// A cube of voxels
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chunk {
+    voxels: Vec<Voxel>,
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self {
+            voxels: vec![Voxel::Air; CHUNK_VOLUME],
+        }
+    }
+}
+
+impl Chunk {
+    fn index(local: IVec3) -> usize {
+        (local.x + local.y * CHUNK_SIZE + local.z * CHUNK_SIZE * CHUNK_SIZE) as usize
+    }
+
+    pub fn get(&self, local: IVec3) -> Voxel {
+        self.voxels[Self::index(local)]
+    }
+
+    pub fn set(&mut self, local: IVec3, voxel: Voxel) {
+        self.voxels[Self::index(local)] = voxel;
+    }
+}
+
+/// Every chunk of a terrain according to it is chunk position
+#[derive(Clone, Debug, Default)]
+pub struct VoxelWorld {
+    pub chunks: HashMap<IVec3, Chunk>,
+}
+
+/// A single change made to the generated terrain
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, Reflect)]
+pub struct VoxelEdit {
+    // Global voxel position
+    pub position: IVec3,
+    pub voxel: Voxel,
+}
+
+/// A generated terrain plus the entities that represent each one of it is chunks
+#[derive(Debug)]
+pub struct VoxelTerrain {
+    pub settings: TerrainSettings,
+    pub world: VoxelWorld,
+    pub chunk_entities: HashMap<IVec3, Entity>,
+    // Every voxel that differs from what the seed generates, latest edit wins
+    pub edits: HashMap<IVec3, Voxel>,
+}
+
+impl VoxelTerrain {
+    pub fn new(settings: &TerrainSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+            world: VoxelWorld::generate(settings),
+            chunk_entities: HashMap::default(),
+            edits: HashMap::default(),
+        }
+    }
+
+    /// Applies edits and gives me every chunk that needs to be remeshed, neighbours included when touching a border
+    pub fn apply_edits(&mut self, edits: &[VoxelEdit]) -> HashSet<IVec3> {
+        let mut dirty_chunks = HashSet::default();
+        for edit in edits.iter() {
+            let Some(chunk_pos) = self.world.set_voxel(edit.position, edit.voxel) else {
+                continue;
+            };
+            self.edits.insert(edit.position, edit.voxel);
+            dirty_chunks.insert(chunk_pos);
+
+            let (_, local) = chunk_and_local(edit.position);
+            for axis in 0..3 {
+                let mut offset = IVec3::ZERO;
+                if local[axis] == 0 {
+                    offset[axis] = -1;
+                } else if local[axis] == CHUNK_SIZE - 1 {
+                    offset[axis] = 1;
+                } else {
+                    continue;
+                }
+                let neighbour = chunk_pos + offset;
+                if self.world.chunks.contains_key(&neighbour) {
+                    dirty_chunks.insert(neighbour);
+                }
+            }
+        }
+        dirty_chunks
+    }
+
+    /// All edits made so far, usefull for saving and late joiners
+    pub fn edit_list(&self) -> Vec<VoxelEdit> {
+        self.edits
+            .iter()
+            .map(|(position, voxel)| VoxelEdit {
+                position: *position,
+                voxel: *voxel,
+            })
+            .collect()
+    }
+}
+
+/// Helper splits a voxel global position into chunk position and local position
+pub fn chunk_and_local(global: IVec3) -> (IVec3, IVec3) {
+    let chunk = global.div_euclid(IVec3::splat(CHUNK_SIZE));
+    let local = global.rem_euclid(IVec3::splat(CHUNK_SIZE));
+    (chunk, local)
+}
+
+/// Helper gives me the world position of a chunk corner
+pub fn chunk_origin(chunk_pos: IVec3) -> Vec3 {
+    (chunk_pos * CHUNK_SIZE).as_vec3()
+}
+
+impl VoxelWorld {
+    /// Generates every chunk described by the terrain settings
+    pub fn generate(settings: &TerrainSettings) -> Self {
+        let mut world = VoxelWorld::default();
+        for x in -settings.radius..settings.radius {
+            for z in -settings.radius..settings.radius {
+                for y in 0..settings.height_chunks {
+                    let chunk_pos = IVec3::new(x, y, z);
+                    world
+                        .chunks
+                        .insert(chunk_pos, generate_chunk(settings, chunk_pos));
+                }
+            }
+        }
+        world
+    }
+
+    /// Voxel in global voxel coordinates, anything outside of generated chunks is air
+    pub fn get_voxel(&self, global: IVec3) -> Voxel {
+        let (chunk_pos, local) = chunk_and_local(global);
+        self.chunks
+            .get(&chunk_pos)
+            .map(|chunk| chunk.get(local))
+            .unwrap_or(Voxel::Air)
+    }
+
+    /// Changes a voxel and returns the chunk that owns it, none if it is outside of the world
+    pub fn set_voxel(&mut self, global: IVec3, voxel: Voxel) -> Option<IVec3> {
+        let (chunk_pos, local) = chunk_and_local(global);
+        let chunk = self.chunks.get_mut(&chunk_pos)?;
+        chunk.set(local, voxel);
+        Some(chunk_pos)
+    }
+
+    /// Edits that turn every solid voxel inside of sphere into air
+    pub fn carve_sphere(&self, center: Vec3, radius: f32) -> Vec<VoxelEdit> {
+        let min = (center - Vec3::splat(radius)).floor().as_ivec3();
+        let max = (center + Vec3::splat(radius)).ceil().as_ivec3();
+        let mut edits = Vec::new();
+
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    let position = IVec3::new(x, y, z);
+                    // Distance to voxel center
+                    if (position.as_vec3() + Vec3::splat(0.5)).distance(center) > radius {
+                        continue;
+                    }
+                    if self.get_voxel(position).is_solid() {
+                        edits.push(VoxelEdit {
+                            position,
+                            voxel: Voxel::Air,
+                        });
+                    }
+                }
+            }
+        }
+        edits
+    }
+
+    /// Greedy meshes a chunk, faces that touch another solid voxel are culled even across chunk borders
+    pub fn mesh_chunk(&self, chunk_pos: IVec3) -> ChunkMesh {
+        let mut chunk_mesh = ChunkMesh::default();
+        let Some(chunk) = self.chunks.get(&chunk_pos) else {
+            return chunk_mesh;
+        };
+        let chunk_corner = chunk_pos * CHUNK_SIZE;
+        let size = CHUNK_SIZE as usize;
+
+        // Voxel of this chunk, or from the neighbour chunk if we step outside
+        let voxel_at = |local: IVec3| -> Voxel {
+            if local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(CHUNK_SIZE)).all() {
+                chunk.get(local)
+            } else {
+                self.get_voxel(chunk_corner + local)
+            }
+        };
+
+        let mut mask: Vec<Option<Voxel>> = vec![None; size * size];
+
+        // Sweep each axis in both directions
+        for axis in 0..3 {
+            let u_axis = (axis + 1) % 3;
+            let v_axis = (axis + 2) % 3;
+
+            for positive in [true, false] {
+                let mut step = IVec3::ZERO;
+                step[axis] = if positive { 1 } else { -1 };
+
+                for slice in 0..CHUNK_SIZE {
+                    // Build mask of visible faces in this slice
+                    for j in 0..CHUNK_SIZE {
+                        for i in 0..CHUNK_SIZE {
+                            let mut local = IVec3::ZERO;
+                            local[axis] = slice;
+                            local[u_axis] = i;
+                            local[v_axis] = j;
+
+                            let voxel = chunk.get(local);
+                            let visible = voxel.is_solid() && !voxel_at(local + step).is_solid();
+                            mask[i as usize + j as usize * size] = visible.then_some(voxel);
+                        }
+                    }
+
+                    // Merge equal faces into the biggest rectangles possible
+                    for j in 0..size {
+                        let mut i = 0;
+                        while i < size {
+                            let Some(voxel) = mask[i + j * size] else {
+                                i += 1;
+                                continue;
+                            };
+
+                            let mut width = 1;
+                            while i + width < size && mask[i + width + j * size] == Some(voxel) {
+                                width += 1;
+                            }
+
+                            let mut height = 1;
+                            'grow: while j + height < size {
+                                for k in 0..width {
+                                    if mask[i + k + (j + height) * size] != Some(voxel) {
+                                        break 'grow;
+                                    }
+                                }
+                                height += 1;
+                            }
+
+                            let mut corner = Vec3::ZERO;
+                            corner[axis] = slice as f32 + if positive { 1.0 } else { 0.0 };
+                            corner[u_axis] = i as f32;
+                            corner[v_axis] = j as f32;
+
+                            let mut du = Vec3::ZERO;
+                            du[u_axis] = width as f32;
+                            let mut dv = Vec3::ZERO;
+                            dv[v_axis] = height as f32;
+
+                            chunk_mesh.push_quad(corner, du, dv, step.as_vec3(), positive, voxel);
+
+                            for l in 0..height {
+                                for k in 0..width {
+                                    mask[i + k + (j + l) * size] = None;
+                                }
+                            }
+                            i += width;
+                        }
+                    }
+                }
+            }
+        }
+
+        chunk_mesh
+    }
+}
+
+/// Raw mesh data of a chunk, in chunk local space
+#[derive(Default, Debug, Clone)]
+pub struct ChunkMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub colors: Vec<[f32; 4]>,
+    pub indices: Vec<u32>,
+}
+
+impl ChunkMesh {
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    fn push_quad(
+        &mut self,
+        corner: Vec3,
+        du: Vec3,
+        dv: Vec3,
+        normal: Vec3,
+        positive: bool,
+        voxel: Voxel,
+    ) {
+        let start = self.positions.len() as u32;
+        for position in [corner, corner + du, corner + du + dv, corner + dv] {
+            self.positions.push(position.to_array());
+            self.normals.push(normal.to_array());
+            self.colors.push(voxel.color());
+        }
+        // Uvs repeat once per voxel so a texture can tile later
+        let (width, height) = (du.length(), dv.length());
+        self.uvs
+            .extend([[0.0, 0.0], [width, 0.0], [width, height], [0.0, height]]);
+
+        // Counter clockwise when looking from the side the normal points to
+        if positive {
+            self.indices
+                .extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+        } else {
+            self.indices
+                .extend([start, start + 2, start + 1, start, start + 3, start + 2]);
+        }
+    }
+
+    /// Visual mesh of chunk
+    pub fn to_mesh(&self) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions.clone())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals.clone())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs.clone())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors.clone())
+        .with_inserted_indices(Indices::U32(self.indices.clone()))
+    }
+
+    /// Physical shape of chunk, none if there is nothing to collide with
+    pub fn collider(&self) -> Option<Collider> {
+        if self.is_empty() {
+            return None;
+        }
+        let vertices = self
+            .positions
+            .iter()
+            .map(|p| Vec3::from_array(*p))
+            .collect();
+        let triangles = self
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+        Some(Collider::trimesh(vertices, triangles))
+    }
+}
+
+/// Fills a chunk according to the terrain height map
+pub fn generate_chunk(settings: &TerrainSettings, chunk_pos: IVec3) -> Chunk {
+    let mut chunk = Chunk::default();
+    let chunk_corner = chunk_pos * CHUNK_SIZE;
+
+    for x in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            let global_x = chunk_corner.x + x;
+            let global_z = chunk_corner.z + z;
+            let height = terrain_height(settings, global_x, global_z);
+
+            for y in 0..CHUNK_SIZE {
+                let global_y = chunk_corner.y + y;
+                let voxel = if global_y > height {
+                    Voxel::Air
+                } else if global_y == height {
+                    Voxel::Grass
+                } else if global_y > height - 3 {
+                    Voxel::Dirt
+                } else {
+                    Voxel::Stone
+                };
+                chunk.set(IVec3::new(x, y, z), voxel);
+            }
+        }
+    }
+    chunk
+}
+
+/// Height of the terrain column, values below zero mean no column at all
+pub fn terrain_height(settings: &TerrainSettings, x: i32, z: i32) -> i32 {
+    let noise = fractal_noise(
+        settings.seed,
+        x as f32 * settings.frequency,
+        z as f32 * settings.frequency,
+    );
+    (settings.base_height as f32 + noise * settings.amplitude).floor() as i32
+}
+
+/// Few octaves of value noise goes from -1.0 to 1.0
+fn fractal_noise(seed: u64, x: f32, z: f32) -> f32 {
+    const OCTAVES: u32 = 4;
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    let mut max_value = 0.0;
+
+    for octave in 0..OCTAVES {
+        let octave_seed = seed.wrapping_add(octave as u64);
+        total += value_noise(octave_seed, x * frequency, z * frequency) * amplitude;
+        max_value += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    (total / max_value) * 2.0 - 1.0
+}
+
+/// Smoothly interpolated random values in a grid goes from 0.0 to 1.0
+fn value_noise(seed: u64, x: f32, z: f32) -> f32 {
+    let (x0, z0) = (x.floor(), z.floor());
+    let (tx, tz) = (smooth(x - x0), smooth(z - z0));
+    let (ix, iz) = (x0 as i64, z0 as i64);
+
+    let c00 = hash_to_unit(seed, ix, iz);
+    let c10 = hash_to_unit(seed, ix + 1, iz);
+    let c01 = hash_to_unit(seed, ix, iz + 1);
+    let c11 = hash_to_unit(seed, ix + 1, iz + 1);
+
+    let top = c00.lerp(c10, tx);
+    let bottom = c01.lerp(c11, tx);
+    top.lerp(bottom, tz)
+}
+
+fn smooth(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Splitmix based hash, same input always gives the same output in every machine
+fn hash_to_unit(seed: u64, x: i64, z: i64) -> f32 {
+    let mut value = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (z as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^= value >> 31;
+    (value >> 40) as f32 / (1u64 << 24) as f32
+}